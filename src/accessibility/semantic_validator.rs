@@ -44,7 +44,7 @@ fn check_div_button_antipattern(content: &str, report: &mut AccessibilityReport)
 
 /// Check for opportunities to use semantic HTML
 fn check_semantic_alternatives(content: &str, report: &mut AccessibilityReport) {
-    let patterns = vec![
+    let patterns = [
         (
             r#"<div[^>]*class="[^"]*header[^"]*"[^>]*>"#,
             "header",
@@ -8,8 +8,10 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::clock::Clock;
+use crate::config::AdminUser;
 use crate::error_handler::{AppError, ResultExt};
-use crate::storage::{AdminUser, JsonStorage, StorageError};
+use crate::storage_v2::{Storage, StorageError};
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
@@ -59,7 +61,7 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
 
 /// Login handler for POST /admin/login
 pub async fn login_handler(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: Session,
     login_data: web::Json<LoginRequest>,
 ) -> Result<impl Responder, AppError> {
@@ -70,6 +72,11 @@ pub async fn login_handler(
         .map_storage_err()?
         .ok_or(AppError::Auth("Invalid username or password".to_string()))?;
 
+    // Deactivated accounts cannot log in
+    if !user.is_active {
+        return Err(AppError::Auth("Invalid username or password".to_string()));
+    }
+
     // Verify password
     if !verify_password(&login_data.password, &user.password_hash)? {
         return Err(AppError::Auth("Invalid username or password".to_string()));
@@ -146,8 +153,20 @@ pub async fn require_auth(session: &Session) -> Result<Uuid, AppError> {
     Ok(user_id)
 }
 
+/// Username of the logged-in admin, for recording who made a change
+pub fn session_username(session: &Session) -> String {
+    session
+        .get::<String>("username")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 /// Create a default admin user if none exists
-pub async fn create_default_admin(storage: web::Data<JsonStorage>) -> Result<(), AppError> {
+pub async fn create_default_admin(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+) -> Result<(), AppError> {
     log::debug!("create_default_admin() started");
 
     log::debug!("Getting admin users list");
@@ -164,6 +183,10 @@ pub async fn create_default_admin(storage: web::Data<JsonStorage>) -> Result<(),
             id: Uuid::new_v4(),
             username: "admin".to_string(),
             password_hash,
+            created_at: Some(clock.now()),
+            last_login: None,
+            roles: vec!["super_admin".to_string()],
+            is_active: true,
         };
 
         log::debug!("Adding admin user to storage on blocking thread");
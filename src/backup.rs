@@ -0,0 +1,540 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use actix_web::web::Data;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::{error, info, warn};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::config::StorageConfig;
+use crate::history::{self, Change, RevisionAction};
+use crate::migrations::{self, DataKind};
+use crate::storage_v2::{HasId, Storage, StorageError, StorageSnapshot, write_atomic};
+
+const BACKUP_PREFIX: &str = "backup-";
+const BACKUP_EXTENSION: &str = ".json";
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// A backup file as listed by the admin API
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupInfo {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Creates, lists, prunes and restores snapshot backups in `backup_directory`
+pub struct BackupManager {
+    directory: PathBuf,
+    max_backup_count: usize,
+}
+
+impl BackupManager {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self {
+            directory: PathBuf::from(&config.backup_directory),
+            max_backup_count: config.max_backup_count,
+        }
+    }
+
+    /// Snapshot every collection into a new backup named for `now`, verify it and
+    /// prune old ones
+    pub fn create_backup(
+        &self,
+        storage: &dyn Storage,
+        now: DateTime<Utc>,
+    ) -> Result<BackupInfo, StorageError> {
+        let (backup, _) = self.write_backup(storage, now)?;
+        self.prune(None)?;
+        Ok(backup)
+    }
+
+    /// Snapshot every collection into a new, verified backup, returning the snapshot
+    /// it holds
+    fn write_backup(
+        &self,
+        storage: &dyn Storage,
+        now: DateTime<Utc>,
+    ) -> Result<(BackupInfo, StorageSnapshot), StorageError> {
+        fs::create_dir_all(&self.directory)?;
+        let snapshot = storage.snapshot(now)?;
+        let name = format!(
+            "{}{}{}",
+            BACKUP_PREFIX,
+            snapshot.created_at.format(BACKUP_TIMESTAMP_FORMAT),
+            BACKUP_EXTENSION
+        );
+        let path = self.directory.join(&name);
+        let json_data = serde_json::to_string_pretty(&snapshot)?;
+        write_atomic(&path.to_string_lossy(), json_data.as_bytes())?;
+
+        // Never keep a backup that could not be restored
+        if let Err(e) = Self::verify(&path, &snapshot) {
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+
+        info!(
+            "Created backup {} ({} items, {} notices, {} presets, {} schedules)",
+            name,
+            snapshot.menu_items.len(),
+            snapshot.notices.len(),
+            snapshot.menu_presets.len(),
+            snapshot.menu_schedules.len()
+        );
+        Ok((Self::info(&path)?, snapshot))
+    }
+
+    /// All backups, newest first
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, StorageError> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if !Self::is_backup_name(&path.file_name().unwrap_or_default().to_string_lossy()) {
+                continue;
+            }
+            match Self::info(&path) {
+                Ok(info) => backups.push(info),
+                Err(e) => warn!("Ignoring {}: {}", path.display(), e),
+            }
+        }
+        backups.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(backups)
+    }
+
+    /// Raw contents of a backup, for download
+    pub fn read_backup(&self, name: &str) -> Result<Vec<u8>, StorageError> {
+        Ok(fs::read(self.backup_path(name)?)?)
+    }
+
+    /// Replace every collection with the contents of a backup, recording a
+    /// revision of each record it restores or removes.
+    ///
+    /// The current data is backed up first so a mistaken restore can be undone.
+    pub fn restore_backup(
+        &self,
+        storage: &dyn Storage,
+        name: &str,
+        restored: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let content = fs::read_to_string(self.backup_path(name)?)?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)?;
+        migrations::migrate_snapshot(&mut value, name)?;
+        let snapshot: StorageSnapshot = serde_json::from_value(value)?;
+        let (safety_backup, previous) = self.write_backup(storage, restored.at)?;
+        info!(
+            "Restoring backup {} (current data saved as {})",
+            name, safety_backup.name
+        );
+        storage.restore_snapshot(snapshot)?;
+        // The restore is committed by now, so missing history doesn't undo it
+        if let Err(e) = record_restore(storage, &previous, restored) {
+            error!(
+                "Restored backup {}, but its history could not be recorded: {}",
+                name, e
+            );
+        }
+
+        // Pruning waits until the restore is done, and never takes the backup just
+        // restored even when it is the oldest
+        if let Err(e) = self.prune(Some(name)) {
+            warn!(
+                "Failed to prune old backups after restoring {}: {}",
+                name, e
+            );
+        }
+        Ok(())
+    }
+
+    /// Delete the oldest backups beyond `max_backup_count`, other than `keep`
+    fn prune(&self, keep: Option<&str>) -> Result<(), StorageError> {
+        let backups = self.list_backups()?;
+        for backup in backups
+            .iter()
+            .filter(|backup| Some(backup.name.as_str()) != keep)
+            .skip(self.max_backup_count.max(1))
+        {
+            info!("Pruning old backup {}", backup.name);
+            fs::remove_file(self.directory.join(&backup.name))?;
+        }
+        Ok(())
+    }
+
+    /// Read a backup back and check it holds exactly the snapshot that was written
+    fn verify(path: &Path, expected: &StorageSnapshot) -> Result<(), StorageError> {
+        let content = fs::read_to_string(path)?;
+        let written: StorageSnapshot = serde_json::from_str(&content)?;
+        if serde_json::to_value(&written)? != serde_json::to_value(expected)? {
+            return Err(StorageError::Validation(format!(
+                "Backup {} failed verification: its records differ from the snapshot",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn info(path: &Path) -> Result<BackupInfo, StorageError> {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let timestamp = &name[BACKUP_PREFIX.len()..name.len() - BACKUP_EXTENSION.len()];
+        let created_at = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+            .map(|naive| naive.and_utc())
+            .map_err(|e| {
+                StorageError::Validation(format!("Invalid backup name {}: {}", name, e))
+            })?;
+        Ok(BackupInfo {
+            size_bytes: fs::metadata(path)?.len(),
+            name,
+            created_at,
+        })
+    }
+
+    fn is_backup_name(name: &str) -> bool {
+        name.starts_with(BACKUP_PREFIX)
+            && name.ends_with(BACKUP_EXTENSION)
+            && name.len() > BACKUP_PREFIX.len() + BACKUP_EXTENSION.len()
+    }
+
+    /// Resolve a backup name from a request, refusing anything outside the backup directory
+    fn backup_path(&self, name: &str) -> Result<PathBuf, StorageError> {
+        let safe = Self::is_backup_name(name)
+            && !name.contains("..")
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        if !safe {
+            return Err(StorageError::Validation(format!(
+                "Invalid backup name: {}",
+                name
+            )));
+        }
+        let path = self.directory.join(name);
+        if !path.exists() {
+            return Err(StorageError::NotFound(format!("Backup {} not found", name)));
+        }
+        Ok(path)
+    }
+}
+
+/// Record a `Restored` revision of every record a restore brought back and a
+/// `Purged` one of every record it removed. `previous` is the data from before.
+fn record_restore(
+    storage: &dyn Storage,
+    previous: &StorageSnapshot,
+    change: Change<'_>,
+) -> Result<(), StorageError> {
+    let restored = storage.snapshot(change.at)?;
+    record_collection(
+        storage,
+        DataKind::MenuItems,
+        &previous.menu_items,
+        &restored.menu_items,
+        change,
+    )?;
+    record_collection(
+        storage,
+        DataKind::Notices,
+        &previous.notices,
+        &restored.notices,
+        change,
+    )?;
+    record_collection(
+        storage,
+        DataKind::MenuPresets,
+        &previous.menu_presets,
+        &restored.menu_presets,
+        change,
+    )?;
+    record_collection(
+        storage,
+        DataKind::MenuSchedules,
+        &previous.menu_schedules,
+        &restored.menu_schedules,
+        change,
+    )
+}
+
+fn record_collection<T: HasId + Serialize>(
+    storage: &dyn Storage,
+    kind: DataKind,
+    previous: &[T],
+    restored: &[T],
+    change: Change<'_>,
+) -> Result<(), StorageError> {
+    let before: HashMap<Uuid, &T> = previous.iter().map(|r| (r.get_id(), r)).collect();
+    for record in restored {
+        let id = record.get_id();
+        history::record(
+            storage,
+            kind,
+            id,
+            RevisionAction::Restored,
+            before.get(&id).copied(),
+            Some(record),
+            change,
+        )?;
+    }
+    let kept: HashSet<Uuid> = restored.iter().map(HasId::get_id).collect();
+    for record in previous.iter().filter(|r| !kept.contains(&r.get_id())) {
+        history::record(
+            storage,
+            kind,
+            record.get_id(),
+            RevisionAction::Purged,
+            Some(record),
+            None,
+            change,
+        )?;
+    }
+    Ok(())
+}
+
+/// Starts the background task that takes a backup every `backup_interval_hours`
+pub async fn start_backup_task(storage: Data<dyn Storage>, clock: Data<dyn Clock>) {
+    let settings = match storage.get_app_settings() {
+        Ok(settings) => settings.storage,
+        Err(e) => {
+            error!("Cannot start backup task: {}", e);
+            return;
+        }
+    };
+    if !settings.enable_auto_backup {
+        info!("Automatic backups are disabled in settings.toml");
+        return;
+    }
+
+    info!(
+        "Starting backup task (every {} hours, keeping {})",
+        settings.backup_interval_hours, settings.max_backup_count
+    );
+    let period = Duration::hours(i64::from(settings.backup_interval_hours.max(1)));
+    tokio::spawn(async move {
+        let manager = BackupManager::new(&settings);
+        // A backup is taken at startup, then every period
+        loop {
+            let now = clock.now();
+            if let Err(e) = manager.create_backup(storage.get_ref(), now) {
+                warn!("Automatic backup failed: {}", e);
+            }
+            clock.sleep_until(now + period).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::config::{AppSettings, ValidationRules};
+    use crate::memory_storage::MemoryStorage;
+    use crate::storage::{MenuCategory, MenuItem};
+    use crate::storage_v2::HybridStorage;
+    use uuid::Uuid;
+
+    fn setup(max_backup_count: usize) -> (MemoryStorage, BackupManager) {
+        let mut settings = AppSettings::load("config/settings.toml").unwrap();
+        settings.storage.backup_directory = std::env::temp_dir()
+            .join(format!("platter-backups-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
+        settings.storage.max_backup_count = max_backup_count;
+        let manager = BackupManager::new(&settings.storage);
+        (
+            MemoryStorage::new(
+                settings,
+                ValidationRules::load("config/validation.toml").unwrap(),
+            ),
+            manager,
+        )
+    }
+
+    fn sample_item(name: &str) -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: MenuCategory::Mains,
+            description: "A test dish".to_string(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_backups_are_pruned_to_retention_count() {
+        let (storage, manager) = setup(2);
+        let start = Utc::now();
+        let mut created = Vec::new();
+        for hour in 0..3 {
+            let backup = manager
+                .create_backup(&storage, start + Duration::hours(hour))
+                .unwrap();
+            created.push(backup.name);
+        }
+
+        let names: Vec<String> = manager
+            .list_backups()
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, vec![created[2].clone(), created[1].clone()]);
+    }
+
+    #[test]
+    fn test_restore_replaces_data_and_keeps_a_safety_backup() {
+        let (storage, manager) = setup(10);
+        let original = sample_item("Fish Pie");
+        storage.add_menu_item(original.clone()).unwrap();
+        let now = Utc::now();
+        let backup = manager.create_backup(&storage, now).unwrap();
+
+        let later = now + Duration::minutes(1);
+        storage
+            .delete_menu_item(original.id, 1, Change::new("admin", later))
+            .unwrap();
+        storage.add_menu_item(sample_item("Lasagne")).unwrap();
+        manager
+            .restore_backup(&storage, &backup.name, Change::new("admin", later))
+            .unwrap();
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, original.id);
+        assert_eq!(manager.list_backups().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_supersedes_versions_and_records_history() {
+        let (storage, manager) = setup(2);
+        let original = sample_item("Fish Pie");
+        storage.add_menu_item(original.clone()).unwrap();
+        let now = Utc::now();
+        let backup = manager.create_backup(&storage, now).unwrap();
+        manager
+            .create_backup(&storage, now + Duration::hours(1))
+            .unwrap();
+        let later = now + Duration::hours(2);
+        let added = sample_item("Lasagne");
+        storage.add_menu_item(added.clone()).unwrap();
+        storage
+            .delete_menu_item(original.id, 1, Change::new("admin", later))
+            .unwrap();
+
+        // The backup restored is the oldest, but the safety backup does not prune it
+        manager
+            .restore_backup(&storage, &backup.name, Change::new("carol", later))
+            .unwrap();
+        let names: Vec<String> = manager
+            .list_backups()
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&backup.name));
+
+        // An ETag for version 1 from before the backup no longer matches
+        let restored = storage.get_menu_item_by_id(original.id).unwrap().unwrap();
+        assert_eq!(restored.version, 3);
+        let revisions = storage
+            .get_revisions(DataKind::MenuItems, original.id)
+            .unwrap();
+        let last = revisions.last().unwrap();
+        assert_eq!(
+            (last.action, last.version, last.changed_by.as_str()),
+            (RevisionAction::Restored, 3, "carol")
+        );
+        let revisions = storage
+            .get_revisions(DataKind::MenuItems, added.id)
+            .unwrap();
+        assert_eq!(revisions.last().unwrap().action, RevisionAction::Purged);
+    }
+
+    #[test]
+    fn test_restore_stands_when_its_history_cannot_be_recorded() {
+        let root = std::env::temp_dir().join(format!("platter-backups-{}", Uuid::new_v4()));
+        let (data_dir, config_dir) = (root.join("data"), root.join("config"));
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::copy("config/settings.toml", config_dir.join("settings.toml")).unwrap();
+        fs::copy("config/validation.toml", config_dir.join("validation.toml")).unwrap();
+        let storage = HybridStorage::new(
+            data_dir.to_str().unwrap(),
+            config_dir.to_str().unwrap(),
+            std::sync::Arc::new(SystemClock),
+        )
+        .unwrap();
+        let mut settings = storage.get_app_settings().unwrap();
+        settings.storage.backup_directory = root.join("backups").to_string_lossy().to_string();
+        let manager = BackupManager::new(&settings.storage);
+        let original = sample_item("Fish Pie");
+        storage.add_menu_item(original.clone()).unwrap();
+        let now = Utc::now();
+        let backup = manager.create_backup(&storage, now).unwrap();
+        storage
+            .delete_menu_item(original.id, 1, Change::new("admin", now))
+            .unwrap();
+
+        fs::create_dir(data_dir.join("history.jsonl")).unwrap();
+        manager
+            .restore_backup(&storage, &backup.name, Change::new("admin", now))
+            .unwrap();
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, original.id);
+    }
+
+    #[test]
+    fn test_verification_compares_every_record() {
+        let (storage, manager) = setup(2);
+        storage.add_menu_item(sample_item("Fish Pie")).unwrap();
+        let backup = manager.create_backup(&storage, Utc::now()).unwrap();
+        let path = manager.directory.join(&backup.name);
+        let snapshot: StorageSnapshot =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(BackupManager::verify(&path, &snapshot).is_ok());
+
+        // Same record counts, different contents
+        let garbled = fs::read_to_string(&path)
+            .unwrap()
+            .replace("Fish Pie", "Fish");
+        fs::write(&path, garbled).unwrap();
+
+        assert!(matches!(
+            BackupManager::verify(&path, &snapshot),
+            Err(StorageError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_backup_names_outside_the_directory_are_rejected() {
+        let (_storage, manager) = setup(2);
+
+        assert!(matches!(
+            manager.read_backup("../settings.toml"),
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            manager.read_backup("backup-..%2f.json"),
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            manager.read_backup("backup-20250101T000000.000Z.json"),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+
+/// Where the scheduler and handlers read the time, so tests can move it on
+/// instead of waiting for it
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Wait until `deadline` has passed by this clock
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// The system clock, sleeping with the Tokio timer
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        // A deadline in the past is due straight away
+        let wait = (deadline - Utc::now()).to_std().unwrap_or_default();
+        Box::pin(tokio::time::sleep(wait))
+    }
+}
+
+/// A clock that only moves when it is set, waking whatever sleeps until then. For
+/// tests and simulations that step through time rather than wait for it
+pub struct ManualClock {
+    now: Arc<watch::Sender<DateTime<Utc>>>,
+    /// The deadline of the latest `sleep_until` through this handle
+    sleeping_until: watch::Sender<Option<DateTime<Utc>>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        ManualClock {
+            now: Arc::new(watch::Sender::new(now)),
+            sleeping_until: watch::Sender::new(None),
+        }
+    }
+
+    /// Another handle on the same time, whose sleeps are tracked apart from this
+    /// one's. Give one to a task running alongside the one under test, such as the
+    /// lease heartbeat, so its sleeps can be told apart
+    pub fn handle(&self) -> Self {
+        ManualClock {
+            now: self.now.clone(),
+            sleeping_until: watch::Sender::new(None),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.send_replace(now);
+    }
+
+    /// Wait until something sleeps on this handle until a time that is still to
+    /// come, and return that time. Once the scheduler does, it has dealt with
+    /// everything due
+    pub async fn next_deadline(&self) -> DateTime<Utc> {
+        let now = self.now();
+        let mut deadlines = self.sleeping_until.subscribe();
+        let deadline = deadlines
+            .wait_for(|deadline| deadline.is_some_and(|deadline| deadline > now))
+            .await
+            .expect("the clock outlives its receivers");
+        deadline.unwrap()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.borrow()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let mut now = self.now.subscribe();
+        self.sleeping_until.send_replace(Some(deadline));
+        Box::pin(async move {
+            // Dropping the clock wakes the sleeper rather than leaving it hanging
+            let _ = now.wait_for(|now| *now >= deadline).await;
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -12,6 +13,8 @@ pub enum ConfigError {
     Io(#[from] std::io::Error),
     #[error("TOML parsing error: {0}")]
     TomlParse(String),
+    #[error("TOML serialization error: {0}")]
+    TomlSerialize(String),
     #[error("Config validation error: {0}")]
     Validation(String),
 }
@@ -22,11 +25,18 @@ impl From<toml::de::Error> for ConfigError {
     }
 }
 
+impl From<toml::ser::Error> for ConfigError {
+    fn from(err: toml::ser::Error) -> Self {
+        ConfigError::TomlSerialize(err.to_string())
+    }
+}
+
 // Admin Configuration Structures
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AdminConfig {
     pub metadata: ConfigMetadata,
     pub admin_users: Vec<AdminUser>,
+    #[serde(default)]
     pub roles: HashMap<String, Role>,
 }
 
@@ -112,6 +122,12 @@ pub struct MenuScheduleValidation {
     pub min_schedule_duration_hours: usize,
     pub max_schedule_duration_days: usize,
     pub allow_overlapping_schedules: bool,
+    /// How the scheduler settles overlapping occurrences when overlaps are allowed
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// How many days of occurrences are compared when checking schedules for overlaps
+    #[serde(default = "default_conflict_horizon_days")]
+    pub conflict_horizon_days: u32,
     pub check_preset_availability: bool,
     pub valid_recurrence: Vec<String>,
     pub valid_status: Vec<String>,
@@ -219,15 +235,103 @@ pub struct LoggingConfig {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
     pub data_directory: String,
     pub backup_directory: String,
     pub enable_auto_backup: bool,
     pub backup_interval_hours: u32,
     pub max_backup_count: usize,
+    /// Days a deleted record stays in the trash before it is purged
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// What deleting a menu item or preset that is still referenced does
+    #[serde(default)]
+    pub on_delete_referenced: ReferencePolicy,
     pub menu_items_file: String,
     pub notices_file: String,
     pub menu_presets_file: String,
     pub menu_schedules_file: String,
+    #[serde(default = "default_database_file")]
+    pub database_file: String,
+}
+
+/// Where menu data and admin users are kept
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Enveloped JSON files in the data directory, admin users in admin.toml
+    #[default]
+    Json,
+    /// Process memory only; everything is lost on shutdown
+    Memory,
+    /// A single SQLite database (requires the `sqlite` cargo feature)
+    Sqlite,
+}
+
+/// How deletes treat records that other records still point at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferencePolicy {
+    /// Refuse the delete and report what still uses the record
+    #[default]
+    Reject,
+    /// Remove deleted menu items from presets and trash schedules of deleted presets
+    Cascade,
+}
+
+/// How the scheduler settles schedules whose occurrences overlap
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Refuse overlapping schedules and mark a late overlap `Conflicted`
+    #[default]
+    Reject,
+    /// The schedule with the higher `priority` sets the menu
+    HighestPriority,
+    /// The rarer schedule sets the menu: a one-off before a rule before monthly,
+    /// weekly and daily schedules, then the shorter occurrence
+    MostSpecific,
+    /// The menu offers the items of every overlapping schedule's preset
+    MergePresets,
+}
+
+/// What the scheduler does with occurrences that ended while it was not running
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// Run only the most recent missed occurrence, so the menu ends up as it would
+    /// have been, and skip the rest
+    #[default]
+    Latest,
+    /// Skip every missed occurrence
+    Skip,
+    /// Run every missed occurrence in the order they were due
+    All,
+}
+
+fn default_database_file() -> String {
+    "platter.db".to_string()
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_conflict_horizon_days() -> u32 {
+    90
+}
+
+fn default_schedule_retry_limit() -> u32 {
+    5
+}
+
+fn default_schedule_retry_delay_seconds() -> u64 {
+    30
+}
+
+fn default_scheduler_lease_seconds() -> u64 {
+    30
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -237,6 +341,19 @@ pub struct MenuConfig {
     pub auto_activate_schedules: bool,
     pub default_item_availability: bool,
     pub track_item_history: bool,
+    #[serde(default)]
+    pub misfire_policy: MisfirePolicy,
+    /// Times an occurrence that hit a transient error is tried again before the
+    /// schedule is marked `Failed`
+    #[serde(default = "default_schedule_retry_limit")]
+    pub schedule_retry_limit: u32,
+    /// Wait before the first retry, doubled for each one after
+    #[serde(default = "default_schedule_retry_delay_seconds")]
+    pub schedule_retry_delay_seconds: u64,
+    /// How long the process running schedules keeps the lease in
+    /// `data/scheduler.lock` without renewing it before another takes over
+    #[serde(default = "default_scheduler_lease_seconds")]
+    pub scheduler_lease_seconds: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -294,6 +411,41 @@ pub struct FeaturesConfig {
     pub enable_analytics: bool,
 }
 
+impl Default for AdminConfig {
+    /// An admin configuration with no users and the built-in super_admin role,
+    /// used on first run before any admin user has been created
+    fn default() -> Self {
+        let mut roles = HashMap::new();
+        roles.insert(
+            "super_admin".to_string(),
+            Role {
+                description: "Full system access - can manage all settings, users, and content"
+                    .to_string(),
+                permissions: vec![
+                    "manage_users".to_string(),
+                    "manage_menu_items".to_string(),
+                    "manage_presets".to_string(),
+                    "manage_schedules".to_string(),
+                    "manage_notices".to_string(),
+                    "manage_settings".to_string(),
+                    "view_logs".to_string(),
+                    "export_data".to_string(),
+                ],
+            },
+        );
+
+        Self {
+            metadata: ConfigMetadata {
+                schema_version: "1.0.0".to_string(),
+                config_name: "Admin Users Configuration".to_string(),
+                last_modified: None,
+            },
+            admin_users: Vec::new(),
+            roles,
+        }
+    }
+}
+
 // Configuration Loading Functions
 impl AdminConfig {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
@@ -326,6 +478,17 @@ impl AdminConfig {
     }
 }
 
+impl MenuScheduleValidation {
+    /// The policy in force: overlaps are always rejected unless they are allowed
+    pub fn effective_conflict_policy(&self) -> ConflictPolicy {
+        if self.allow_overlapping_schedules {
+            self.conflict_policy
+        } else {
+            ConflictPolicy::Reject
+        }
+    }
+}
+
 impl ValidationRules {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
         let content = fs::read_to_string(path)?;
@@ -340,6 +503,26 @@ impl AppSettings {
         let content = fs::read_to_string(path)?;
         let settings: AppSettings = toml::from_str(&content)
             .map_err(|e| ConfigError::TomlParse(e.to_string()))?;
+        settings.validate()?;
         Ok(settings)
     }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.localization.timezone.parse::<Tz>().is_err() {
+            return Err(ConfigError::Validation(format!(
+                "Unknown timezone '{}', expected an IANA name such as Europe/London",
+                self.localization.timezone
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl LocalizationConfig {
+    /// The timezone schedules are entered and repeated in. `timezone` is checked
+    /// when settings are loaded, so UTC is only a fallback for settings built in code.
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
 }
\ No newline at end of file
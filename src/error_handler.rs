@@ -34,6 +34,19 @@ pub enum AppError {
     /// Internal server errors
     #[error("Internal server error: {0}")]
     Internal(String),
+
+    /// Writes against a stale version of a record
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    /// Writes the current state of the records does not allow, such as ones that
+    /// would leave other records pointing at nothing
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Writes sent without an If-Match header
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
 }
 
 impl AppError {
@@ -74,6 +87,24 @@ impl AppError {
                     None
                 },
             },
+            AppError::PreconditionFailed(msg) => ErrorResponse {
+                error: "Precondition Failed".to_string(),
+                message: msg.clone(),
+                error_type: "PRECONDITION_FAILED".to_string(),
+                details: None,
+            },
+            AppError::Conflict(msg) => ErrorResponse {
+                error: "Conflict".to_string(),
+                message: msg.clone(),
+                error_type: "CONFLICT".to_string(),
+                details: None,
+            },
+            AppError::PreconditionRequired(msg) => ErrorResponse {
+                error: "Precondition Required".to_string(),
+                message: msg.clone(),
+                error_type: "PRECONDITION_REQUIRED".to_string(),
+                details: None,
+            },
         }
     }
 }
@@ -87,6 +118,9 @@ impl ResponseError for AppError {
             AppError::Validation(_) => actix_web::http::StatusCode::BAD_REQUEST,
             AppError::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
             AppError::Internal(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::PreconditionFailed(_) => actix_web::http::StatusCode::PRECONDITION_FAILED,
+            AppError::Conflict(_) => actix_web::http::StatusCode::CONFLICT,
+            AppError::PreconditionRequired(_) => actix_web::http::StatusCode::PRECONDITION_REQUIRED,
         };
 
         HttpResponse::build(status_code).json(error_response)
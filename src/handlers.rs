@@ -1,15 +1,27 @@
-use actix_web::{HttpRequest, HttpResponse, Responder, web};
-use chrono::Utc;
+use actix_web::http::header::{self, EntityTag, Header, IfMatch};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, web};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use tera::Tera;
 use uuid::Uuid;
 
-use crate::auth::require_auth;
+use crate::auth::{require_auth, session_username};
+use crate::backup::BackupManager;
+use crate::clock::Clock;
+use crate::config::ConflictPolicy;
 use crate::error_handler::{AppError, ResultExt};
-use crate::storage::{
-    JsonStorage, MenuItem, MenuPreset, MenuSchedule, Notice, ScheduleRecurrence, ScheduleStatus,
-    StorageError,
+use crate::history::{self, Change, Revision, RevisionAction};
+use crate::integrity;
+use crate::leader::{LeaderLock, Lease};
+use crate::migrations::DataKind;
+use crate::recurrence::RecurrenceSet;
+use crate::scheduler::{self, MenuPeriod, ScheduleClash};
+use crate::storage_v2::{
+    MenuItem, MenuPreset, MenuSchedule, Notice, PresetScope, ScheduleRecurrence, ScheduleStatus,
+    Storage, StorageError, Trashable, Versioned,
 };
+use crate::timezone::{self, TimeInput};
 
 #[derive(Debug, Serialize)]
 pub struct ApiError {
@@ -24,6 +36,10 @@ pub enum ApiErrorType {
     Validation(String),
     #[error("Not found: {0}")]
     NotFound(String),
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
 }
 
 impl From<AppError> for ApiErrorType {
@@ -36,6 +52,9 @@ impl From<AppError> for ApiErrorType {
             AppError::Validation(msg) => ApiErrorType::Validation(msg),
             AppError::NotFound(msg) => ApiErrorType::NotFound(msg),
             AppError::Internal(msg) => ApiErrorType::Validation(format!("Internal error: {}", msg)),
+            AppError::PreconditionFailed(msg) => ApiErrorType::PreconditionFailed(msg),
+            AppError::PreconditionRequired(msg) => ApiErrorType::PreconditionRequired(msg),
+            AppError::Conflict(msg) => ApiErrorType::Storage(StorageError::Referenced(msg)),
         }
     }
 }
@@ -44,9 +63,22 @@ impl actix_web::ResponseError for ApiErrorType {
     fn error_response(&self) -> HttpResponse {
         let error_message = self.to_string();
         let status = match self {
+            ApiErrorType::Storage(StorageError::NotFound(_)) => {
+                actix_web::http::StatusCode::NOT_FOUND
+            }
+            ApiErrorType::Storage(StorageError::VersionConflict { .. }) => {
+                actix_web::http::StatusCode::PRECONDITION_FAILED
+            }
+            ApiErrorType::Storage(StorageError::Referenced(_)) => {
+                actix_web::http::StatusCode::CONFLICT
+            }
             ApiErrorType::Storage(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiErrorType::Validation(_) => actix_web::http::StatusCode::BAD_REQUEST,
             ApiErrorType::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
+            ApiErrorType::PreconditionFailed(_) => actix_web::http::StatusCode::PRECONDITION_FAILED,
+            ApiErrorType::PreconditionRequired(_) => {
+                actix_web::http::StatusCode::PRECONDITION_REQUIRED
+            }
         };
 
         HttpResponse::build(status).json(ApiError {
@@ -55,12 +87,210 @@ impl actix_web::ResponseError for ApiErrorType {
     }
 }
 
+/// `ETag` header for a record at `version`
+fn etag(version: u64) -> header::ETag {
+    header::ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// Record the revision for a write that has already been saved.
+///
+/// The write stands even if its history can't be saved, so the failure is logged
+/// and handed back as a `Warning` for the response instead of failing the request.
+fn record_revision<T: Serialize>(
+    storage: &dyn Storage,
+    kind: DataKind,
+    id: Uuid,
+    action: RevisionAction,
+    before: Option<&T>,
+    after: Option<&T>,
+    changed: Change<'_>,
+) -> Option<String> {
+    let e = history::record(storage, kind, id, action, before, after, changed).err()?;
+    log::error!(
+        "{} {} was saved but its history could not be recorded: {}",
+        kind.label(),
+        id,
+        e
+    );
+    Some("199 - \"Saved, but its history could not be recorded\"".to_string())
+}
+
+/// Add the `Warning` header from [`record_revision`], if there is one
+fn with_warning(mut response: HttpResponseBuilder, warning: Option<String>) -> HttpResponseBuilder {
+    if let Some(warning) = warning {
+        response.insert_header((header::WARNING, warning));
+    }
+    response
+}
+
+/// Check a PUT or DELETE's `If-Match` header against the record's current version.
+///
+/// Returns the version the write must expect, so the storage layer still rejects
+/// it if another write lands first.
+fn if_match(req: &HttpRequest, current_version: u64) -> Result<u64, AppError> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Err(AppError::PreconditionRequired(
+            "If-Match header with the record's ETag is required".to_string(),
+        ));
+    }
+    let current = EntityTag::new_strong(current_version.to_string());
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => Ok(current_version),
+        Ok(IfMatch::Items(tags)) if tags.iter().any(|tag| tag.strong_eq(&current)) => {
+            Ok(current_version)
+        }
+        Ok(IfMatch::Items(_)) => Err(AppError::PreconditionFailed(format!(
+            "Record has been modified, current version is {}",
+            current_version
+        ))),
+        Err(_) => Err(AppError::Validation("Invalid If-Match header".to_string())),
+    }
+}
+
+/// Check a schedule's occurrence window, series end and recurrence rule.
+///
+/// An occurrence may not outlast the gap to the next one. A rule gets a DTSTART
+/// if it had none, and the occurrence moves on to the rule's first one so the
+/// schedule never runs on a day the rule leaves out. An empty rule is the same as
+/// none.
+fn fit_occurrence_window(schedule: &mut MenuSchedule, tz: Tz) -> Result<(), AppError> {
+    let length = schedule.end_time - schedule.start_time;
+    if length <= Duration::zero() {
+        return Err(AppError::Validation(
+            "End time must be after start time".to_string(),
+        ));
+    }
+    if schedule
+        .series_end
+        .is_some_and(|series_end| series_end < schedule.start_time)
+    {
+        return Err(AppError::Validation(
+            "Series end must not be before the start time".to_string(),
+        ));
+    }
+
+    let Some(rrule) = schedule
+        .rrule
+        .take()
+        .filter(|rrule| !rrule.trim().is_empty())
+    else {
+        let period = match schedule.recurrence {
+            ScheduleRecurrence::Daily => Some(("a day", Duration::days(1))),
+            ScheduleRecurrence::Weekly => Some(("a week", Duration::weeks(1))),
+            // The shortest month
+            ScheduleRecurrence::Monthly => Some(("28 days", Duration::days(28))),
+            ScheduleRecurrence::Custom => None,
+        };
+        return match period {
+            Some((label, period)) if length > period => Err(AppError::Validation(format!(
+                "A {:?} occurrence cannot last longer than {}",
+                schedule.recurrence, label
+            ))),
+            _ => Ok(()),
+        };
+    };
+    if !matches!(schedule.recurrence, ScheduleRecurrence::Custom) {
+        return Err(AppError::Validation(
+            "RRULE can only be used with Custom recurrence".to_string(),
+        ));
+    }
+
+    let (rrule, set) = RecurrenceSet::anchor(&rrule, schedule.start_time, tz)
+        .map_err(|e| AppError::Validation(format!("Invalid RRULE: {}", e)))?;
+    let mut occurrences = set
+        .occurrences(schedule.start_time, tz)
+        .skip_while(|start| *start < schedule.start_time);
+    let first = occurrences
+        .next()
+        .filter(|start| schedule.series_end.is_none_or(|end| *start <= end))
+        .ok_or_else(|| {
+            AppError::Validation(
+                "RRULE has no occurrences between the start time and series end".to_string(),
+            )
+        })?;
+    if occurrences.next().is_some_and(|next| next < first + length) {
+        return Err(AppError::Validation(
+            "Occurrences of the RRULE overlap, so each must be shorter".to_string(),
+        ));
+    }
+    schedule.rrule = Some(rrule);
+    schedule.start_time = first;
+    schedule.end_time = first + length;
+    Ok(())
+}
+
+/// Exception dates as they are stored: in order, without repeats
+fn exception_dates(dates: Option<Vec<NaiveDate>>) -> Vec<NaiveDate> {
+    let mut dates = dates.unwrap_or_default();
+    dates.sort_unstable();
+    dates.dedup();
+    dates
+}
+
+/// The configured `localization.timezone` that schedule times are entered in
+fn schedule_timezone(storage: &dyn Storage) -> Result<Tz, AppError> {
+    Ok(storage.get_app_settings()?.localization.tz())
+}
+
+/// How overlapping schedules are settled, per `validation.toml`; only `Reject`
+/// turns overlaps away when schedules are saved
+fn schedule_conflict_policy(storage: &dyn Storage) -> Result<ConflictPolicy, AppError> {
+    Ok(storage
+        .get_validation_rules()?
+        .menu_schedules
+        .effective_conflict_policy())
+}
+
+/// Every occurrence of `schedule` that overlaps one of another schedule whose
+/// preset could set the same items, within `conflict_horizon_days` of its first
+/// occurrence or of now if that is later
+fn schedule_conflicts(
+    storage: &dyn Storage,
+    schedule: &MenuSchedule,
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> Result<Vec<ScheduleClash>, AppError> {
+    let horizon = storage
+        .get_validation_rules()?
+        .menu_schedules
+        .conflict_horizon_days;
+    let until = schedule.start_time.max(now) + Duration::days(horizon.into());
+    let presets = storage.get_menu_presets()?;
+    let items = storage.get_menu_items()?;
+    let existing_schedules: Vec<MenuSchedule> = storage
+        .get_menu_schedules()?
+        .into_iter()
+        .filter(|other| scheduler::shares_menu(schedule, other, &presets, &items))
+        .collect();
+    Ok(scheduler::find_conflicts(
+        schedule,
+        &existing_schedules,
+        until,
+        tz,
+    ))
+}
+
+/// The error for saving a schedule that overlaps another while overlaps are not
+/// allowed, naming the first clash
+fn schedule_conflict_error(clash: &ScheduleClash, tz: Tz) -> AppError {
+    AppError::Validation(format!(
+        "Schedule conflict with existing schedule '{}' ({}) on {}",
+        clash.schedule_name,
+        clash.schedule_id,
+        clash
+            .occurrence_start
+            .with_timezone(&tz)
+            .format("%Y-%m-%d %H:%M")
+    ))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateMenuItemRequest {
     pub name: String,
     pub category: String,
     pub description: String,
     pub allergens: Vec<String>,
+    pub tags: Option<Vec<String>>, // Labels presets can be scoped to
     pub is_available: bool,
 }
 
@@ -70,6 +300,7 @@ pub struct UpdateMenuItemRequest {
     pub category: Option<String>,
     pub description: Option<String>,
     pub allergens: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
     pub is_available: Option<bool>,
 }
 
@@ -92,6 +323,7 @@ pub struct CreateMenuPresetRequest {
     pub name: String,
     pub description: String,
     pub menu_item_ids: Vec<uuid::Uuid>,
+    pub scope: Option<PresetScope>, // Whole menu when not given
 }
 
 #[derive(Debug, Deserialize)]
@@ -99,53 +331,195 @@ pub struct UpdateMenuPresetRequest {
     pub name: Option<String>,
     pub description: Option<String>,
     pub menu_item_ids: Option<Vec<uuid::Uuid>>,
+    pub scope: Option<PresetScope>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateMenuScheduleRequest {
     pub preset_id: uuid::Uuid,
+    pub fallback_preset_id: Option<uuid::Uuid>, // Applied when an occurrence ends
     pub name: String,
     pub description: String,
-    pub start_time: chrono::DateTime<chrono::Utc>,
-    pub end_time: chrono::DateTime<chrono::Utc>,
+    pub start_time: TimeInput, // First occurrence; local times are read in the configured timezone
+    pub end_time: TimeInput,
+    pub series_end: Option<TimeInput>,
     pub recurrence: String, // Will be converted to ScheduleRecurrence enum
-    pub status: String,     // Will be converted to ScheduleStatus enum
+    pub rrule: Option<String>,
+    pub priority: Option<i32>, // Settles overlaps under the highest_priority policy; 0 by default
+    pub exception_dates: Option<Vec<NaiveDate>>, // Local dates the schedule does not run on
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateMenuScheduleRequest {
     pub preset_id: Option<uuid::Uuid>,
+    pub fallback_preset_id: Option<String>, // An empty string removes the fallback preset
     pub name: Option<String>,
     pub description: Option<String>,
-    pub start_time: Option<chrono::DateTime<chrono::Utc>>,
-    pub end_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub start_time: Option<TimeInput>,
+    pub end_time: Option<TimeInput>,
+    pub series_end: Option<String>, // An empty string removes the series end
     pub recurrence: Option<String>, // Will be converted to ScheduleRecurrence enum
-    pub status: Option<String>,     // Will be converted to ScheduleStatus enum
+    pub rrule: Option<String>,      // An empty string clears the rule
+    pub priority: Option<i32>,
+    pub exception_dates: Option<Vec<NaiveDate>>, // Replaces the list
+    pub status: Option<String>,                  // Only the current status is accepted
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ValidateScheduleRequest {
     pub preset_id: Option<uuid::Uuid>,
+    pub fallback_preset_id: Option<uuid::Uuid>,
     pub name: Option<String>,
     pub description: Option<String>,
-    pub start_time: chrono::DateTime<chrono::Utc>,
-    pub end_time: chrono::DateTime<chrono::Utc>,
+    pub start_time: TimeInput,
+    pub end_time: TimeInput,
+    pub series_end: Option<TimeInput>,
     pub recurrence: Option<String>,
+    pub rrule: Option<String>,
+    pub priority: Option<i32>,
+    pub exception_dates: Option<Vec<NaiveDate>>,
     pub status: Option<String>,
     pub schedule_id: Option<Uuid>, // For update validation
 }
 
+/// A schedule as the API returns it, with its times also given in the configured
+/// timezone
+#[derive(Debug, Serialize)]
+pub struct ScheduleView {
+    #[serde(flatten)]
+    pub schedule: MenuSchedule,
+    pub timezone: String,
+    pub start_time_local: DateTime<FixedOffset>,
+    pub end_time_local: DateTime<FixedOffset>,
+    pub series_start_local: DateTime<FixedOffset>,
+    pub series_end_local: Option<DateTime<FixedOffset>>,
+}
+
+impl ScheduleView {
+    fn new(schedule: MenuSchedule, tz: Tz) -> Self {
+        ScheduleView {
+            timezone: tz.name().to_string(),
+            start_time_local: schedule.start_time.with_timezone(&tz).fixed_offset(),
+            end_time_local: schedule.end_time.with_timezone(&tz).fixed_offset(),
+            series_start_local: schedule.series_start.with_timezone(&tz).fixed_offset(),
+            series_end_local: schedule
+                .series_end
+                .map(|end| end.with_timezone(&tz).fixed_offset()),
+            schedule,
+        }
+    }
+
+    fn list(schedules: Vec<MenuSchedule>, tz: Tz) -> Vec<Self> {
+        schedules
+            .into_iter()
+            .map(|schedule| ScheduleView::new(schedule, tz))
+            .collect()
+    }
+}
+
+/// A stretch of the simulated menu, with its times also given in the configured
+/// timezone and the available items in full
+#[derive(Debug, Serialize)]
+pub struct MenuPeriodView {
+    #[serde(flatten)]
+    pub period: MenuPeriod,
+    pub start_local: DateTime<FixedOffset>,
+    pub end_local: DateTime<FixedOffset>,
+    pub items: Vec<MenuItem>,
+}
+
+impl MenuPeriodView {
+    fn new(period: MenuPeriod, items: &[MenuItem], tz: Tz) -> Self {
+        MenuPeriodView {
+            start_local: period.start.with_timezone(&tz).fixed_offset(),
+            end_local: period.end.with_timezone(&tz).fixed_offset(),
+            items: items
+                .iter()
+                .filter(|item| period.available_item_ids.contains(&item.id))
+                .cloned()
+                .collect(),
+            period,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuAtQuery {
+    pub time: TimeInput, // Local times are read in the configured timezone
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MenuTimelineQuery {
+    pub date: NaiveDate, // The first local day of the timeline
+    #[serde(default)]
+    pub span: TimelineSpan,
+}
+
+/// How much of the simulated menu a timeline covers
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineSpan {
+    #[default]
+    Day,
+    Week,
+}
+
+/// Tags trimmed and in lower case, sorted, without blanks or duplicates
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+fn normalize_scope(scope: PresetScope) -> PresetScope {
+    let mut categories = Vec::new();
+    for category in scope.categories {
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    PresetScope {
+        categories,
+        tags: normalize_tags(scope.tags),
+    }
+}
+
+/// Reject a preset listing items it would never set, as they fall outside its scope
+fn check_preset_scope(
+    scope: &PresetScope,
+    menu_item_ids: &[Uuid],
+    menu_items: &[MenuItem],
+) -> Result<(), ApiErrorType> {
+    match menu_items
+        .iter()
+        .find(|item| menu_item_ids.contains(&item.id) && !scope.contains(item))
+    {
+        Some(item) => Err(ApiErrorType::Validation(format!(
+            "Menu item '{}' ({}) is outside the preset's scope",
+            item.name, item.id
+        ))),
+        None => Ok(()),
+    }
+}
+
 // Menu Items Handlers
 
 pub async fn list_menu_items(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
 ) -> Result<impl Responder, ApiErrorType> {
     let items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
     Ok(HttpResponse::Ok().json(items))
 }
 
 pub async fn create_menu_item(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     item_data: web::Json<CreateMenuItemRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
     println!(
@@ -167,7 +541,11 @@ pub async fn create_menu_item(
         category,
         description: item_data.description.clone(),
         allergens: item_data.allergens.clone(),
+        tags: normalize_tags(item_data.tags.clone().unwrap_or_default()),
         is_available: item_data.is_available,
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
     };
 
     println!("DEBUG: About to add menu item to storage: {:?}", new_item);
@@ -175,157 +553,292 @@ pub async fn create_menu_item(
         .add_menu_item(new_item.clone())
         .map_err(ApiErrorType::from)?;
     println!("DEBUG: Menu item added to storage successfully");
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuItems,
+        new_item.id,
+        RevisionAction::Created,
+        None,
+        Some(&new_item),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Created(), warning)
+        .insert_header(etag(new_item.version))
+        .json(new_item))
+}
 
-    Ok(HttpResponse::Created().json(new_item))
+pub async fn get_menu_item(
+    storage: web::Data<dyn Storage>,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, ApiErrorType> {
+    let item_id = path.into_inner();
+
+    let item = storage
+        .get_menu_item_by_id(item_id)
+        .map_err(ApiErrorType::Storage)?
+        .ok_or_else(|| {
+            ApiErrorType::NotFound(format!("Menu item with id {} not found", item_id))
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(item.version))
+        .json(item))
 }
 
 pub async fn update_menu_item(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     path: web::Path<Uuid>,
     update_data: web::Json<UpdateMenuItemRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
     let item_id = path.into_inner();
 
     // Get existing item
-    let items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
-    let existing_item = items
-        .iter()
-        .find(|item| item.id == item_id)
+    let existing_item = storage
+        .get_menu_item_by_id(item_id)
+        .map_err(ApiErrorType::Storage)?
         .ok_or_else(|| {
             ApiErrorType::NotFound(format!("Menu item with id {} not found", item_id))
         })?;
+    let expected_version = if_match(&req, existing_item.version)?;
+
+    let mut updated_item = existing_item.clone();
+    apply_menu_item_update(&mut updated_item, &update_data)?;
+
+    updated_item.version = storage
+        .update_menu_item(item_id, updated_item.clone(), expected_version)
+        .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuItems,
+        item_id,
+        RevisionAction::Updated,
+        Some(&existing_item),
+        Some(&updated_item),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(updated_item.version))
+        .json(updated_item))
+}
 
+/// Apply the fields set in `update` to `item`, checking its category
+fn apply_menu_item_update(
+    item: &mut MenuItem,
+    update: &UpdateMenuItemRequest,
+) -> Result<(), ApiErrorType> {
     // Validate category if provided
-    let category = if let Some(category_str) = &update_data.category {
-        match category_str.as_str() {
+    if let Some(category) = &update.category {
+        item.category = match category.as_str() {
             "Mains" => crate::storage::MenuCategory::Mains,
             "Sides" => crate::storage::MenuCategory::Sides,
             "Desserts" => crate::storage::MenuCategory::Desserts,
             "Beverages" => crate::storage::MenuCategory::Beverages,
             _ => return Err(ApiErrorType::Validation("Invalid category".to_string())),
-        }
-    } else {
-        existing_item.category.clone()
-    };
-
-    let updated_item = MenuItem {
-        id: item_id,
-        name: update_data
-            .name
-            .clone()
-            .unwrap_or_else(|| existing_item.name.clone()),
-        category,
-        description: update_data
-            .description
-            .clone()
-            .unwrap_or_else(|| existing_item.description.clone()),
-        allergens: update_data
-            .allergens
-            .clone()
-            .unwrap_or_else(|| existing_item.allergens.clone()),
-        is_available: update_data
-            .is_available
-            .unwrap_or(existing_item.is_available),
-    };
+        };
+    }
 
-    storage
-        .update_menu_item(item_id, updated_item.clone())
-        .map_err(ApiErrorType::from)?;
+    // Update fields
+    if let Some(name) = &update.name {
+        item.name = name.clone();
+    }
+    if let Some(description) = &update.description {
+        item.description = description.clone();
+    }
+    if let Some(allergens) = &update.allergens {
+        item.allergens = allergens.clone();
+    }
+    if let Some(tags) = &update.tags {
+        item.tags = normalize_tags(tags.clone());
+    }
+    if let Some(is_available) = update.is_available {
+        item.is_available = is_available;
+    }
 
-    Ok(HttpResponse::Ok().json(updated_item))
+    Ok(())
 }
 
 pub async fn delete_menu_item(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, ApiErrorType> {
     let item_id = path.into_inner();
 
+    let existing_item = storage
+        .get_menu_item_by_id(item_id)
+        .map_err(ApiErrorType::Storage)?
+        .ok_or_else(|| {
+            ApiErrorType::NotFound(format!("Menu item with id {} not found", item_id))
+        })?;
+    let expected_version = if_match(&req, existing_item.version)?;
+
+    let username = session_username(&session);
+    let deleted = Change::new(&username, clock.now());
     storage
-        .delete_menu_item(item_id)
+        .delete_menu_item(item_id, expected_version, deleted)
         .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuItems,
+        item_id,
+        RevisionAction::Deleted,
+        Some(&existing_item),
+        None,
+        deleted,
+    );
 
-    Ok(HttpResponse::NoContent())
+    Ok(with_warning(HttpResponse::NoContent(), warning))
 }
 
 // Notices Handlers
 
-pub async fn list_notices(storage: web::Data<JsonStorage>) -> Result<impl Responder, ApiErrorType> {
+pub async fn list_notices(storage: web::Data<dyn Storage>) -> Result<impl Responder, ApiErrorType> {
     let notices = storage.get_notices().map_err(ApiErrorType::Storage)?;
     Ok(HttpResponse::Ok().json(notices))
 }
 
 pub async fn create_notice(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     notice_data: web::Json<CreateNoticeRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
-    use chrono::Utc;
-
     let new_notice = Notice {
         id: Uuid::new_v4(),
         title: notice_data.title.clone(),
         content: notice_data.content.clone(),
         is_active: notice_data.is_active,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
+        created_at: clock.now(),
+        updated_at: clock.now(),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
     };
 
     storage
         .add_notice(new_notice.clone())
         .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::Notices,
+        new_notice.id,
+        RevisionAction::Created,
+        None,
+        Some(&new_notice),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Created(), warning)
+        .insert_header(etag(new_notice.version))
+        .json(new_notice))
+}
+
+pub async fn get_notice(
+    storage: web::Data<dyn Storage>,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, ApiErrorType> {
+    let notice_id = path.into_inner();
+
+    let notice = storage
+        .get_notice_by_id(notice_id)
+        .map_err(ApiErrorType::Storage)?
+        .ok_or_else(|| ApiErrorType::NotFound(format!("Notice with id {} not found", notice_id)))?;
 
-    Ok(HttpResponse::Created().json(new_notice))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(notice.version))
+        .json(notice))
 }
 
 pub async fn update_notice(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     path: web::Path<Uuid>,
     update_data: web::Json<UpdateNoticeRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
     let notice_id = path.into_inner();
 
     // Get existing notice
-    let notices = storage.get_notices().map_err(ApiErrorType::Storage)?;
-    let existing_notice = notices
-        .iter()
-        .find(|notice| notice.id == notice_id)
+    let existing_notice = storage
+        .get_notice_by_id(notice_id)
+        .map_err(ApiErrorType::Storage)?
         .ok_or_else(|| ApiErrorType::NotFound(format!("Notice with id {} not found", notice_id)))?;
+    let expected_version = if_match(&req, existing_notice.version)?;
 
-    use chrono::Utc;
-
-    let updated_notice = Notice {
-        id: notice_id,
-        title: update_data
-            .title
-            .clone()
-            .unwrap_or_else(|| existing_notice.title.clone()),
-        content: update_data
-            .content
-            .clone()
-            .unwrap_or_else(|| existing_notice.content.clone()),
-        is_active: update_data.is_active.unwrap_or(existing_notice.is_active),
-        created_at: existing_notice.created_at,
-        updated_at: Utc::now(),
-    };
+    let mut updated_notice = existing_notice.clone();
+    apply_notice_update(&mut updated_notice, &update_data, clock.now());
 
-    storage
-        .update_notice(notice_id, updated_notice.clone())
+    updated_notice.version = storage
+        .update_notice(notice_id, updated_notice.clone(), expected_version)
         .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::Notices,
+        notice_id,
+        RevisionAction::Updated,
+        Some(&existing_notice),
+        Some(&updated_notice),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(updated_notice.version))
+        .json(updated_notice))
+}
 
-    Ok(HttpResponse::Ok().json(updated_notice))
+/// Apply the fields set in `update` to `notice`
+fn apply_notice_update(notice: &mut Notice, update: &UpdateNoticeRequest, now: DateTime<Utc>) {
+    if let Some(title) = &update.title {
+        notice.title = title.clone();
+    }
+    if let Some(content) = &update.content {
+        notice.content = content.clone();
+    }
+    if let Some(is_active) = update.is_active {
+        notice.is_active = is_active;
+    }
+    notice.updated_at = now;
 }
 
 pub async fn delete_notice(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, ApiErrorType> {
     let notice_id = path.into_inner();
 
+    let existing_notice = storage
+        .get_notice_by_id(notice_id)
+        .map_err(ApiErrorType::Storage)?
+        .ok_or_else(|| ApiErrorType::NotFound(format!("Notice with id {} not found", notice_id)))?;
+    let expected_version = if_match(&req, existing_notice.version)?;
+
+    let username = session_username(&session);
+    let deleted = Change::new(&username, clock.now());
     storage
-        .delete_notice(notice_id)
+        .delete_notice(notice_id, expected_version, deleted)
         .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::Notices,
+        notice_id,
+        RevisionAction::Deleted,
+        Some(&existing_notice),
+        None,
+        deleted,
+    );
 
-    Ok(HttpResponse::NoContent())
+    Ok(with_warning(HttpResponse::NoContent(), warning))
 }
 
 // Login page handler
@@ -356,7 +869,7 @@ pub async fn login_page(
 
 // Admin Dashboard Handler
 pub async fn admin_dashboard(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     tera: web::Data<Tera>,
 ) -> Result<HttpResponse, ApiErrorType> {
@@ -399,7 +912,7 @@ pub async fn admin_dashboard(
 // Menu Presets Handlers
 
 pub async fn list_menu_presets(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
 ) -> Result<impl Responder, ApiErrorType> {
     // Check authentication
@@ -412,7 +925,8 @@ pub async fn list_menu_presets(
 }
 
 pub async fn create_menu_preset(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     preset_data: web::Json<CreateMenuPresetRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
@@ -421,8 +935,6 @@ pub async fn create_menu_preset(
         .await
         .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
 
-    use chrono::Utc;
-
     // Validate that menu item IDs exist
     let menu_items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
 
@@ -435,24 +947,42 @@ pub async fn create_menu_preset(
         }
     }
 
+    let scope = normalize_scope(preset_data.scope.clone().unwrap_or_default());
+    check_preset_scope(&scope, &preset_data.menu_item_ids, &menu_items)?;
+
     let new_preset = MenuPreset {
         id: Uuid::new_v4(),
         name: preset_data.name.clone(),
         description: preset_data.description.clone(),
         menu_item_ids: preset_data.menu_item_ids.clone(),
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
+        scope,
+        created_at: clock.now(),
+        updated_at: clock.now(),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
     };
 
     storage
         .add_menu_preset(new_preset.clone())
         .map_err(ApiErrorType::Storage)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuPresets,
+        new_preset.id,
+        RevisionAction::Created,
+        None,
+        Some(&new_preset),
+        Change::new(&session_username(&session), clock.now()),
+    );
 
-    Ok(HttpResponse::Created().json(new_preset))
+    Ok(with_warning(HttpResponse::Created(), warning)
+        .insert_header(etag(new_preset.version))
+        .json(new_preset))
 }
 
 pub async fn get_menu_preset(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, ApiErrorType> {
@@ -463,20 +993,22 @@ pub async fn get_menu_preset(
 
     let preset_id = path.into_inner();
 
-    let presets = storage.get_menu_presets().map_err(ApiErrorType::Storage)?;
-
-    let preset = presets
-        .into_iter()
-        .find(|p| p.id == preset_id)
+    let preset = storage
+        .get_menu_preset_by_id(preset_id)
+        .map_err(ApiErrorType::Storage)?
         .ok_or_else(|| {
             ApiErrorType::NotFound(format!("Menu preset with id {} not found", preset_id))
         })?;
 
-    Ok(HttpResponse::Ok().json(preset))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(preset.version))
+        .json(preset))
 }
 
 pub async fn update_menu_preset(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
     update_data: web::Json<UpdateMenuPresetRequest>,
@@ -486,22 +1018,53 @@ pub async fn update_menu_preset(
         .await
         .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
 
-    use chrono::Utc;
-
     let preset_id = path.into_inner();
 
     // Get existing preset
-    let presets = storage.get_menu_presets().map_err(ApiErrorType::Storage)?;
-
-    let mut existing_preset = presets
-        .into_iter()
-        .find(|p| p.id == preset_id)
+    let mut existing_preset = storage
+        .get_menu_preset_by_id(preset_id)
+        .map_err(ApiErrorType::Storage)?
         .ok_or_else(|| {
             ApiErrorType::NotFound(format!("Menu preset with id {} not found", preset_id))
         })?;
+    let expected_version = if_match(&req, existing_preset.version)?;
+    let previous_preset = existing_preset.clone();
+
+    apply_preset_update(
+        storage.get_ref(),
+        &mut existing_preset,
+        &update_data,
+        clock.now(),
+    )?;
+
+    existing_preset.version = storage
+        .update_menu_preset(preset_id, existing_preset.clone(), expected_version)
+        .map_err(ApiErrorType::Storage)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuPresets,
+        preset_id,
+        RevisionAction::Updated,
+        Some(&previous_preset),
+        Some(&existing_preset),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(existing_preset.version))
+        .json(existing_preset))
+}
 
+/// Apply the fields set in `update` to `preset`, checking its items exist and fall
+/// in its scope
+fn apply_preset_update(
+    storage: &dyn Storage,
+    preset: &mut MenuPreset,
+    update: &UpdateMenuPresetRequest,
+    now: DateTime<Utc>,
+) -> Result<(), ApiErrorType> {
     // Validate menu item IDs if provided
-    if let Some(menu_item_ids) = &update_data.menu_item_ids {
+    if let Some(menu_item_ids) = &update.menu_item_ids {
         let menu_items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
 
         for item_id in menu_item_ids {
@@ -512,27 +1075,32 @@ pub async fn update_menu_preset(
                 )));
             }
         }
-        existing_preset.menu_item_ids = menu_item_ids.clone();
+        preset.menu_item_ids = menu_item_ids.clone();
+    }
+    if let Some(scope) = &update.scope {
+        preset.scope = normalize_scope(scope.clone());
+    }
+    if update.menu_item_ids.is_some() || update.scope.is_some() {
+        let menu_items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
+        check_preset_scope(&preset.scope, &preset.menu_item_ids, &menu_items)?;
     }
 
     // Update fields
-    if let Some(name) = &update_data.name {
-        existing_preset.name = name.clone();
+    if let Some(name) = &update.name {
+        preset.name = name.clone();
     }
-    if let Some(description) = &update_data.description {
-        existing_preset.description = description.clone();
+    if let Some(description) = &update.description {
+        preset.description = description.clone();
     }
-    existing_preset.updated_at = Utc::now();
+    preset.updated_at = now;
 
-    storage
-        .update_menu_preset(preset_id, existing_preset.clone())
-        .map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(existing_preset))
+    Ok(())
 }
 
 pub async fn delete_menu_preset(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, AppError> {
@@ -543,17 +1111,36 @@ pub async fn delete_menu_preset(
 
     let preset_id = path.into_inner();
 
+    let existing_preset = storage
+        .get_menu_preset_by_id(preset_id)
+        .map_err(AppError::from)?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Menu preset with id {} not found", preset_id))
+        })?;
+    let expected_version = if_match(&req, existing_preset.version)?;
+
+    let username = session_username(&session);
+    let deleted = Change::new(&username, clock.now());
     storage
-        .delete_menu_preset(preset_id)
+        .delete_menu_preset(preset_id, expected_version, deleted)
         .map_err(AppError::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuPresets,
+        preset_id,
+        RevisionAction::Deleted,
+        Some(&existing_preset),
+        None,
+        deleted,
+    );
 
-    Ok(HttpResponse::NoContent())
+    Ok(with_warning(HttpResponse::NoContent(), warning))
 }
 
 // Menu Schedules Handlers
 
 pub async fn list_menu_schedules(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
 ) -> Result<impl Responder, AppError> {
     // Check authentication
@@ -562,11 +1149,13 @@ pub async fn list_menu_schedules(
         .map_err(|e| AppError::Validation(format!("Authentication required: {}", e)))?;
 
     let schedules = storage.get_menu_schedules().map_err(AppError::from)?;
-    Ok(HttpResponse::Ok().json(schedules))
+    let tz = schedule_timezone(storage.get_ref())?;
+    Ok(HttpResponse::Ok().json(ScheduleView::list(schedules, tz)))
 }
 
 pub async fn create_menu_schedule(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     schedule_data: web::Json<CreateMenuScheduleRequest>,
 ) -> Result<impl Responder, AppError> {
@@ -575,8 +1164,6 @@ pub async fn create_menu_schedule(
         .await
         .map_err(|e| AppError::Validation(format!("Authentication required: {}", e)))?;
 
-    use chrono::Utc;
-
     // Validate that preset exists
     let presets = storage.get_menu_presets().map_err(AppError::from)?;
 
@@ -589,6 +1176,14 @@ pub async fn create_menu_schedule(
             schedule_data.preset_id
         )));
     }
+    if let Some(fallback_id) = schedule_data.fallback_preset_id
+        && !presets.iter().any(|preset| preset.id == fallback_id)
+    {
+        return Err(AppError::Validation(format!(
+            "Fallback preset with id {} not found",
+            fallback_id
+        )));
+    }
 
     // Convert recurrence string to enum
     let recurrence = match schedule_data.recurrence.as_str() {
@@ -598,65 +1193,69 @@ pub async fn create_menu_schedule(
         "Custom" => ScheduleRecurrence::Custom,
         _ => return Err(AppError::Validation("Invalid recurrence value".to_string())),
     };
+    let tz = schedule_timezone(storage.get_ref())?;
 
-    // Convert status string to enum
-    let status = match schedule_data.status.as_str() {
-        "Active" => ScheduleStatus::Active,
-        "Ended" => ScheduleStatus::Ended,
-        "Pending" => ScheduleStatus::Pending,
-        _ => return Err(AppError::Validation("Invalid status value".to_string())),
-    };
-
-    // Check for schedule conflicts
-    let existing_schedules = storage.get_menu_schedules().map_err(AppError::from)?;
-
-    // Create a temporary schedule for conflict check
-    let temp_schedule = MenuSchedule {
-        id: Uuid::new_v4(), // dummy id
+    let start_time = schedule_data.start_time.resolve(tz);
+    let mut new_schedule = MenuSchedule {
+        id: Uuid::new_v4(),
         preset_id: schedule_data.preset_id,
+        fallback_preset_id: schedule_data.fallback_preset_id,
         name: schedule_data.name.clone(),
         description: schedule_data.description.clone(),
-        start_time: schedule_data.start_time,
-        end_time: schedule_data.end_time,
-        recurrence: recurrence.clone(),
-        status: status.clone(),
+        start_time,
+        end_time: schedule_data.end_time.resolve(tz),
+        series_start: start_time,
+        series_end: schedule_data.series_end.map(|end| end.resolve(tz)),
+        recurrence,
+        rrule: schedule_data.rrule.clone(),
+        exception_dates: exception_dates(schedule_data.exception_dates.clone()),
+        priority: schedule_data.priority.unwrap_or_default(),
+        // Only the scheduler and the pause, resume, retry and acknowledge actions
+        // move a schedule out of Pending
+        status: ScheduleStatus::Pending,
         error_message: None,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
+        conflict_decision: None,
+        retry_count: 0,
+        next_retry_at: None,
+        availability_snapshot: None,
+        created_at: clock.now(),
+        updated_at: clock.now(),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
     };
+    fit_occurrence_window(&mut new_schedule, tz)?;
+    new_schedule.series_start = new_schedule.start_time;
+    scheduler::skip_exception_dates(&mut new_schedule, tz, clock.now());
 
-    if let Some(conflicting) =
-        crate::scheduler::has_schedule_conflict(&temp_schedule, &existing_schedules)
+    // Check for schedule conflicts
+    if schedule_conflict_policy(storage.get_ref())? == ConflictPolicy::Reject
+        && let Some(clash) =
+            schedule_conflicts(storage.get_ref(), &new_schedule, clock.now(), tz)?.first()
     {
-        return Err(AppError::Validation(format!(
-            "Schedule conflict with existing schedule '{}' ({})",
-            conflicting.name, conflicting.id
-        )));
+        return Err(schedule_conflict_error(clash, tz));
     }
 
-    let new_schedule = MenuSchedule {
-        id: Uuid::new_v4(),
-        preset_id: schedule_data.preset_id,
-        name: schedule_data.name.clone(),
-        description: schedule_data.description.clone(),
-        start_time: schedule_data.start_time,
-        end_time: schedule_data.end_time,
-        recurrence,
-        status,
-        error_message: None,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-    };
-
     storage
         .add_menu_schedule(new_schedule.clone())
         .map_err(AppError::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuSchedules,
+        new_schedule.id,
+        RevisionAction::Created,
+        None,
+        Some(&new_schedule),
+        Change::new(&session_username(&session), clock.now()),
+    );
 
-    Ok(HttpResponse::Created().json(new_schedule))
+    Ok(with_warning(HttpResponse::Created(), warning)
+        .insert_header(etag(new_schedule.version))
+        .json(ScheduleView::new(new_schedule, tz)))
 }
 
 pub async fn get_menu_schedule(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, ApiErrorType> {
@@ -667,72 +1266,302 @@ pub async fn get_menu_schedule(
 
     let schedule_id = path.into_inner();
 
-    let schedules = storage
-        .get_menu_schedules()
-        .map_err(ApiErrorType::Storage)?;
-
-    let schedule = schedules
-        .into_iter()
-        .find(|s| s.id == schedule_id)
+    let schedule = storage
+        .get_menu_schedule_by_id(schedule_id)
+        .map_err(ApiErrorType::Storage)?
         .ok_or_else(|| {
             ApiErrorType::NotFound(format!("Menu schedule with id {} not found", schedule_id))
         })?;
 
-    Ok(HttpResponse::Ok().json(schedule))
+    let tz = schedule_timezone(storage.get_ref())?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(schedule.version))
+        .json(ScheduleView::new(schedule, tz)))
 }
 
-pub async fn update_menu_schedule(
-    storage: web::Data<JsonStorage>,
+/// The run log of a schedule, oldest first; runs outlive the schedule in the trash
+pub async fn list_schedule_runs(
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
-    update_data: web::Json<UpdateMenuScheduleRequest>,
-) -> Result<impl Responder, AppError> {
+) -> Result<impl Responder, ApiErrorType> {
     // Check authentication
-    let _user_id = require_auth(&session).await.map_auth_err()?;
-
-    use chrono::Utc;
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
 
-    let schedule_id = path.into_inner();
+    let runs = storage
+        .get_schedule_runs(path.into_inner())
+        .map_err(ApiErrorType::Storage)?;
 
-    // Get existing schedule
-    let schedules = storage.get_menu_schedules().map_storage_err()?;
+    Ok(HttpResponse::Ok().json(runs))
+}
 
-    let mut existing_schedule = schedules
-        .into_iter()
-        .find(|s| s.id == schedule_id)
+/// A schedule an admin is about to retry, acknowledge, pause or resume, and the
+/// version the change expects. `allowed` says which statuses the action applies to
+fn schedule_for_action(
+    req: &HttpRequest,
+    storage: &dyn Storage,
+    schedule_id: Uuid,
+    action: &str,
+    allowed: impl Fn(&ScheduleStatus) -> bool,
+) -> Result<(MenuSchedule, u64), AppError> {
+    let schedule = storage
+        .get_menu_schedule_by_id(schedule_id)?
         .ok_or_else(|| {
             AppError::NotFound(format!("Menu schedule with id {} not found", schedule_id))
         })?;
+    let expected_version = if_match(req, schedule.version)?;
+    if !allowed(&schedule.status) {
+        return Err(AppError::Conflict(format!(
+            "Menu schedule with id {} is {:?} and cannot be {}",
+            schedule_id, schedule.status, action
+        )));
+    }
+    Ok((schedule, expected_version))
+}
 
-    // Validate preset_id if provided
-    if let Some(preset_id) = update_data.preset_id {
-        let presets = storage.get_menu_presets().map_storage_err()?;
+/// Save the change an admin's action made to a schedule
+fn save_schedule_action(
+    storage: &web::Data<dyn Storage>,
+    session: &actix_session::Session,
+    previous: &MenuSchedule,
+    mut schedule: MenuSchedule,
+    expected_version: u64,
+) -> Result<HttpResponse, AppError> {
+    schedule.version =
+        storage.update_menu_schedule(schedule.id, schedule.clone(), expected_version)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuSchedules,
+        schedule.id,
+        RevisionAction::Updated,
+        Some(previous),
+        Some(&schedule),
+        Change::new(&session_username(session), schedule.updated_at),
+    );
 
-        if !presets.iter().any(|preset| preset.id == preset_id) {
-            return Err(AppError::Validation(format!(
-                "Menu preset with id {} not found",
-                preset_id
-            )));
-        }
-        existing_schedule.preset_id = preset_id;
-    }
+    let tz = schedule_timezone(storage.get_ref())?;
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(schedule.version))
+        .json(ScheduleView::new(schedule, tz)))
+}
+
+/// Run a failed schedule's occurrence again straight away
+pub async fn retry_menu_schedule(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let (previous, expected_version) = schedule_for_action(
+        &req,
+        storage.get_ref(),
+        path.into_inner(),
+        "retried",
+        |status| *status == ScheduleStatus::Failed,
+    )?;
+    let mut schedule = previous.clone();
+    scheduler::retry_schedule(&mut schedule, clock.now());
+    save_schedule_action(&storage, &session, &previous, schedule, expected_version)
+}
+
+/// Give up on a failed schedule's occurrence and move on to the next one
+pub async fn acknowledge_menu_schedule(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let (previous, expected_version) = schedule_for_action(
+        &req,
+        storage.get_ref(),
+        path.into_inner(),
+        "acknowledged",
+        |status| *status == ScheduleStatus::Failed,
+    )?;
+    let mut schedule = previous.clone();
+    let tz = schedule_timezone(storage.get_ref())?;
+    scheduler::acknowledge_failure(&storage, &mut schedule, tz, clock.now()).map_storage_err()?;
+    save_schedule_action(&storage, &session, &previous, schedule, expected_version)
+}
+
+/// Suspend a schedule, ending any occurrence that is running, until it is resumed
+pub async fn pause_menu_schedule(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let (previous, expected_version) = schedule_for_action(
+        &req,
+        storage.get_ref(),
+        path.into_inner(),
+        "paused",
+        |status| !matches!(status, ScheduleStatus::Ended | ScheduleStatus::Paused),
+    )?;
+    let mut schedule = previous.clone();
+    scheduler::pause_schedule(&storage, &mut schedule, clock.now()).map_storage_err()?;
+    save_schedule_action(&storage, &session, &previous, schedule, expected_version)
+}
+
+/// Let a paused schedule run again from its next occurrence
+pub async fn resume_menu_schedule(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<Uuid>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let (previous, expected_version) = schedule_for_action(
+        &req,
+        storage.get_ref(),
+        path.into_inner(),
+        "resumed",
+        |status| *status == ScheduleStatus::Paused,
+    )?;
+    let mut schedule = previous.clone();
+    let tz = schedule_timezone(storage.get_ref())?;
+    scheduler::resume_schedule(&mut schedule, tz, clock.now());
+    save_schedule_action(&storage, &session, &previous, schedule, expected_version)
+}
+
+pub async fn update_menu_schedule(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<Uuid>,
+    update_data: web::Json<UpdateMenuScheduleRequest>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let schedule_id = path.into_inner();
+
+    // Get existing schedule
+    let mut existing_schedule = storage
+        .get_menu_schedule_by_id(schedule_id)
+        .map_storage_err()?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Menu schedule with id {} not found", schedule_id))
+        })?;
+    let expected_version = if_match(&req, existing_schedule.version)?;
+    let previous_schedule = existing_schedule.clone();
+
+    let tz = schedule_timezone(storage.get_ref())?;
+    apply_schedule_update(
+        storage.get_ref(),
+        &mut existing_schedule,
+        &update_data,
+        clock.now(),
+        tz,
+    )?;
+
+    existing_schedule.version = storage
+        .update_menu_schedule(schedule_id, existing_schedule.clone(), expected_version)
+        .map_err(AppError::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuSchedules,
+        schedule_id,
+        RevisionAction::Updated,
+        Some(&previous_schedule),
+        Some(&existing_schedule),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(existing_schedule.version))
+        .json(ScheduleView::new(existing_schedule, tz)))
+}
+
+/// Apply the fields set in `update` to `schedule`, validating them as any edit is:
+/// the presets it names, its occurrence window and, under the `reject` policy,
+/// clashes with other schedules
+fn apply_schedule_update(
+    storage: &dyn Storage,
+    schedule: &mut MenuSchedule,
+    update: &UpdateMenuScheduleRequest,
+    now: DateTime<Utc>,
+    tz: Tz,
+) -> Result<(), AppError> {
+    // Validate preset_id if provided
+    if let Some(preset_id) = update.preset_id {
+        let presets = storage.get_menu_presets().map_storage_err()?;
+
+        if !presets.iter().any(|preset| preset.id == preset_id) {
+            return Err(AppError::Validation(format!(
+                "Menu preset with id {} not found",
+                preset_id
+            )));
+        }
+        schedule.preset_id = preset_id;
+    }
+    if let Some(fallback) = &update.fallback_preset_id {
+        schedule.fallback_preset_id = if fallback.is_empty() {
+            None
+        } else {
+            let fallback_id = Uuid::parse_str(fallback).map_err(|_| {
+                AppError::Validation(format!("Invalid fallback preset id '{}'", fallback))
+            })?;
+            if storage
+                .get_menu_preset_by_id(fallback_id)
+                .map_storage_err()?
+                .is_none()
+            {
+                return Err(AppError::Validation(format!(
+                    "Fallback preset with id {} not found",
+                    fallback_id
+                )));
+            }
+            Some(fallback_id)
+        };
+    }
 
     // Update fields
-    if let Some(name) = &update_data.name {
-        existing_schedule.name = name.clone();
+    if let Some(name) = &update.name {
+        schedule.name = name.clone();
+    }
+    if let Some(description) = &update.description {
+        schedule.description = description.clone();
     }
-    if let Some(description) = &update_data.description {
-        existing_schedule.description = description.clone();
+    if let Some(priority) = update.priority {
+        schedule.priority = priority;
     }
-    if let Some(start_time) = update_data.start_time {
-        existing_schedule.start_time = start_time;
+    if let Some(start_time) = update.start_time {
+        schedule.start_time = start_time.resolve(tz);
     }
-    if let Some(end_time) = update_data.end_time {
-        existing_schedule.end_time = end_time;
+    if let Some(end_time) = update.end_time {
+        schedule.end_time = end_time.resolve(tz);
+    }
+    if let Some(series_end) = &update.series_end {
+        schedule.series_end = if series_end.is_empty() {
+            None
+        } else {
+            let series_end =
+                TimeInput::try_from(series_end.clone()).map_err(AppError::Validation)?;
+            Some(series_end.resolve(tz))
+        };
     }
 
     // Convert recurrence string to enum if provided
-    if let Some(recurrence_str) = &update_data.recurrence {
+    if let Some(recurrence_str) = &update.recurrence {
         let recurrence = match recurrence_str.as_str() {
             "Daily" => ScheduleRecurrence::Daily,
             "Weekly" => ScheduleRecurrence::Weekly,
@@ -740,44 +1569,66 @@ pub async fn update_menu_schedule(
             "Custom" => ScheduleRecurrence::Custom,
             _ => return Err(AppError::Validation("Invalid recurrence value".to_string())),
         };
-        existing_schedule.recurrence = recurrence;
+        schedule.recurrence = recurrence;
     }
 
-    // Convert status string to enum if provided
-    if let Some(status_str) = &update_data.status {
-        let status = match status_str.as_str() {
-            "Active" => ScheduleStatus::Active,
-            "Ended" => ScheduleStatus::Ended,
-            "Pending" => ScheduleStatus::Pending,
-            _ => return Err(AppError::Validation("Invalid status value".to_string())),
-        };
-        existing_schedule.status = status;
+    // Schedules that are no longer Custom drop their rule unless a new one is given
+    if let Some(rrule) = &update.rrule {
+        schedule.rrule = Some(rrule.clone());
+    } else if !matches!(schedule.recurrence, ScheduleRecurrence::Custom) {
+        schedule.rrule = None;
     }
-
-    existing_schedule.updated_at = Utc::now();
-
-    // Check for schedule conflicts if start_time or end_time changed
-    if update_data.start_time.is_some() || update_data.end_time.is_some() {
-        let all_schedules = storage.get_menu_schedules().map_storage_err()?;
-        if let Some(conflicting) =
-            crate::scheduler::has_schedule_conflict(&existing_schedule, &all_schedules)
-        {
-            return Err(AppError::Validation(format!(
-                "Schedule conflict with existing schedule '{}' ({})",
-                conflicting.name, conflicting.id
-            )));
-        }
+    if update.rrule.is_some()
+        || update.recurrence.is_some()
+        || update.start_time.is_some()
+        || update.end_time.is_some()
+        || update.series_end.is_some()
+    {
+        fit_occurrence_window(schedule, tz)?;
+    }
+    // A new start, recurrence or rule starts the series again from this occurrence
+    if update.start_time.is_some() || update.recurrence.is_some() || update.rrule.is_some() {
+        schedule.series_start = schedule.start_time;
+    }
+    if update.exception_dates.is_some() {
+        schedule.exception_dates = exception_dates(update.exception_dates.clone());
     }
 
-    storage
-        .update_menu_schedule(schedule_id, existing_schedule.clone())
-        .map_storage_err()?;
-
-    Ok(HttpResponse::Ok().json(existing_schedule))
+    // Status changes go through the scheduler and the schedule actions
+    if update
+        .status
+        .as_ref()
+        .is_some_and(|status| *status != format!("{:?}", schedule.status))
+    {
+        return Err(AppError::Validation(
+            "A schedule's status can only be changed with pause, resume, retry or acknowledge"
+                .to_string(),
+        ));
+    }
+    // A pending occurrence that is now on an exception date gives way to the next
+    scheduler::skip_exception_dates(schedule, tz, now);
+
+    schedule.updated_at = now;
+
+    // Check for schedule conflicts if any occurrence may have moved
+    let timing_changed = update.start_time.is_some()
+        || update.end_time.is_some()
+        || update.series_end.is_some()
+        || update.recurrence.is_some()
+        || update.rrule.is_some();
+    if timing_changed
+        && schedule_conflict_policy(storage)? == ConflictPolicy::Reject
+        && let Some(clash) = schedule_conflicts(storage, schedule, now, tz)?.first()
+    {
+        return Err(schedule_conflict_error(clash, tz));
+    }
+    Ok(())
 }
 
 pub async fn delete_menu_schedule(
-    storage: web::Data<JsonStorage>,
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     path: web::Path<Uuid>,
 ) -> Result<impl Responder, AppError> {
@@ -786,43 +1637,67 @@ pub async fn delete_menu_schedule(
 
     let schedule_id = path.into_inner();
 
+    let existing_schedule = storage
+        .get_menu_schedule_by_id(schedule_id)
+        .map_err(AppError::from)?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Menu schedule with id {} not found", schedule_id))
+        })?;
+    let expected_version = if_match(&req, existing_schedule.version)?;
+
+    let username = session_username(&session);
+    let deleted = Change::new(&username, clock.now());
     storage
-        .delete_menu_schedule(schedule_id)
-        .map_storage_err()?;
+        .delete_menu_schedule(schedule_id, expected_version, deleted)
+        .map_err(AppError::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        DataKind::MenuSchedules,
+        schedule_id,
+        RevisionAction::Deleted,
+        Some(&existing_schedule),
+        None,
+        deleted,
+    );
 
-    Ok(HttpResponse::NoContent())
+    Ok(with_warning(HttpResponse::NoContent(), warning))
 }
 
 pub async fn get_upcoming_schedules(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
 ) -> Result<impl Responder, AppError> {
     // Check authentication
     let _user_id = require_auth(&session).await.map_auth_err()?;
 
-    use chrono::Utc;
-
     let schedules = storage.get_menu_schedules().map_storage_err()?;
 
     // Filter for upcoming schedules (start time is in the future)
     let upcoming_schedules: Vec<MenuSchedule> = schedules
         .into_iter()
-        .filter(|schedule| schedule.start_time > Utc::now())
+        .filter(|schedule| schedule.start_time > clock.now())
         .collect();
 
-    Ok(HttpResponse::Ok().json(upcoming_schedules))
+    let tz = schedule_timezone(storage.get_ref())?;
+    Ok(HttpResponse::Ok().json(ScheduleView::list(upcoming_schedules, tz)))
 }
 
 pub async fn validate_schedule(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     validation_data: web::Json<ValidateScheduleRequest>,
 ) -> Result<impl Responder, AppError> {
     // Check authentication
     let _user_id = require_auth(&session).await.map_auth_err()?;
 
+    let tz = schedule_timezone(storage.get_ref())?;
+    let start_time = validation_data.start_time.resolve(tz);
+    let end_time = validation_data.end_time.resolve(tz);
+
     // Validate that end time is after start time
-    if validation_data.end_time <= validation_data.start_time {
+    if end_time <= start_time {
         return Err(AppError::Validation(
             "End time must be after start time".to_string(),
         ));
@@ -839,6 +1714,17 @@ pub async fn validate_schedule(
             )));
         }
     }
+    if let Some(fallback_id) = validation_data.fallback_preset_id
+        && storage
+            .get_menu_preset_by_id(fallback_id)
+            .map_storage_err()?
+            .is_none()
+    {
+        return Err(AppError::Validation(format!(
+            "Fallback preset with id {} not found",
+            fallback_id
+        )));
+    }
 
     // Validate name if provided
     if let Some(name) = &validation_data.name
@@ -874,9 +1760,6 @@ pub async fn validate_schedule(
         }
     }
 
-    // Check for schedule conflicts
-    let existing_schedules = storage.get_menu_schedules().map_storage_err()?;
-
     // Create a temporary schedule for conflict check
     let recurrence = if let Some(rec) = &validation_data.recurrence {
         match rec.as_str() {
@@ -889,7 +1772,6 @@ pub async fn validate_schedule(
     } else {
         ScheduleRecurrence::Custom // default
     };
-
     let status = if let Some(stat) = &validation_data.status {
         match stat.as_str() {
             "Active" => ScheduleStatus::Active,
@@ -901,52 +1783,193 @@ pub async fn validate_schedule(
         ScheduleStatus::Pending // default
     };
 
-    let temp_schedule = MenuSchedule {
+    let mut temp_schedule = MenuSchedule {
         id: validation_data.schedule_id.unwrap_or(Uuid::new_v4()),
         preset_id: validation_data.preset_id.unwrap_or(Uuid::new_v4()), // dummy if not provided
+        fallback_preset_id: validation_data.fallback_preset_id,
         name: validation_data.name.clone().unwrap_or_default(),
         description: validation_data.description.clone().unwrap_or_default(),
-        start_time: validation_data.start_time,
-        end_time: validation_data.end_time,
+        start_time,
+        end_time,
+        series_start: start_time,
+        series_end: validation_data.series_end.map(|end| end.resolve(tz)),
         recurrence,
+        rrule: validation_data.rrule.clone(),
+        exception_dates: exception_dates(validation_data.exception_dates.clone()),
+        priority: validation_data.priority.unwrap_or_default(),
         status,
         error_message: None,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
+        conflict_decision: None,
+        retry_count: 0,
+        next_retry_at: None,
+        availability_snapshot: None,
+        created_at: clock.now(),
+        updated_at: clock.now(),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
     };
 
-    let conflicting = crate::scheduler::has_schedule_conflict(&temp_schedule, &existing_schedules);
-    let conflicts = if let Some(conf) = conflicting {
-        vec![conf.id]
-    } else {
-        Vec::new()
-    };
+    fit_occurrence_window(&mut temp_schedule, tz)?;
+
+    // Check for schedule conflicts
+    let conflicts = schedule_conflicts(storage.get_ref(), &temp_schedule, clock.now(), tz)?;
 
     #[derive(Debug, Serialize)]
     struct ValidationResponse {
         is_valid: bool,
-        conflicts: Vec<Uuid>,
+        conflicts: Vec<ScheduleClash>,
         message: Option<String>,
     }
 
     let has_conflicts = !conflicts.is_empty();
+    let policy = schedule_conflict_policy(storage.get_ref())?;
     let response = ValidationResponse {
-        is_valid: !has_conflicts,
+        is_valid: !has_conflicts || policy != ConflictPolicy::Reject,
         conflicts,
-        message: if has_conflicts {
-            Some("Schedule conflicts with existing schedules".to_string())
-        } else {
-            None
+        message: match (has_conflicts, policy) {
+            (false, _) => None,
+            (true, ConflictPolicy::Reject) => {
+                Some("Schedule conflicts with existing schedules".to_string())
+            }
+            (true, _) => Some(
+                "Schedule overlaps existing schedules; the conflict policy decides which menu is shown"
+                    .to_string(),
+            ),
         },
     };
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Which of the processes sharing the data directory runs schedules, as seen
+/// from the lock file
+pub async fn get_scheduler_status(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    lock: web::Data<LeaderLock>,
+    session: actix_session::Session,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    #[derive(Debug, Serialize)]
+    struct SchedulerStatus {
+        instance_id: String,
+        is_leader: bool,
+        scheduling_enabled: bool,
+        leader: Option<Lease>,
+        lease_expired: bool,
+    }
+
+    let leader = lock.current_lease()?;
+    let response = SchedulerStatus {
+        instance_id: lock.instance_id().to_string(),
+        is_leader: lock.is_leader(),
+        scheduling_enabled: storage.get_app_settings()?.menu.enable_scheduling,
+        lease_expired: leader
+            .as_ref()
+            .is_some_and(|lease| lease.expires_at <= clock.now()),
+        leader,
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Simulate the menu from now until `until` with the stored items, presets and
+/// schedules
+fn simulated_menu(
+    storage: &dyn Storage,
+    now: DateTime<Utc>,
+    until: DateTime<Utc>,
+    tz: Tz,
+) -> Result<(Vec<MenuPeriod>, Vec<MenuItem>), AppError> {
+    let items = storage.get_menu_items()?;
+    let periods = scheduler::simulate_menu(
+        &items,
+        &storage.get_menu_presets()?,
+        &storage.get_menu_schedules()?,
+        schedule_conflict_policy(storage)?,
+        now,
+        until,
+        tz,
+    );
+    Ok((periods, items))
+}
+
+/// What the public menu will show at a given time if the schedules run as they stand
+pub async fn get_menu_at(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    query: web::Query<MenuAtQuery>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let tz = schedule_timezone(storage.get_ref())?;
+    let time = query.time.resolve(tz);
+    if time < clock.now() {
+        return Err(AppError::Validation(
+            "The menu can only be simulated from now on".to_string(),
+        ));
+    }
+
+    // Simulate a day past the time so the period it falls in has its real end
+    let (periods, items) =
+        simulated_menu(storage.get_ref(), clock.now(), time + Duration::days(1), tz)?;
+    let period = periods
+        .into_iter()
+        .find(|period| period.start <= time && time < period.end)
+        .ok_or_else(|| AppError::Validation(format!("Cannot simulate the menu at {}", time)))?;
+
+    Ok(HttpResponse::Ok().json(MenuPeriodView::new(period, &items, tz)))
+}
+
+/// The simulated menu over a local day or week, as the periods over which it
+/// stays the same
+pub async fn get_menu_timeline(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    query: web::Query<MenuTimelineQuery>,
+) -> Result<impl Responder, AppError> {
+    // Check authentication
+    let _user_id = require_auth(&session).await.map_auth_err()?;
+
+    let tz = schedule_timezone(storage.get_ref())?;
+    let days = match query.span {
+        TimelineSpan::Day => 1,
+        TimelineSpan::Week => 7,
+    };
+    let start = timezone::resolve(tz, query.date.and_time(NaiveTime::MIN));
+    let end = timezone::resolve(
+        tz,
+        (query.date + Duration::days(days)).and_time(NaiveTime::MIN),
+    );
+    if end <= clock.now() {
+        return Err(AppError::Validation(
+            "The menu can only be simulated from now on".to_string(),
+        ));
+    }
+
+    let (periods, items) = simulated_menu(storage.get_ref(), clock.now(), end, tz)?;
+    let timeline: Vec<MenuPeriodView> = periods
+        .into_iter()
+        .filter(|period| period.end > start)
+        .map(|mut period| {
+            period.start = period.start.max(start);
+            MenuPeriodView::new(period, &items, tz)
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(timeline))
+}
+
 // Public Menu Display Handler
 pub async fn menu_page(
     req: HttpRequest,
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     tera: web::Data<Tera>,
 ) -> Result<HttpResponse, ApiErrorType> {
     println!("DEBUG: menu_page handler called");
@@ -1010,7 +2033,7 @@ pub async fn menu_page(
 // Individual Menu Item Page Handler
 pub async fn menu_item_page(
     req: HttpRequest,
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     tera: web::Data<Tera>,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse, ApiErrorType> {
@@ -1028,8 +2051,9 @@ pub async fn menu_item_page(
         referrer
     );
 
-    let menu_items = storage.get_menu_items().map_err(ApiErrorType::Storage)?;
-    let item = menu_items.iter().find(|item| item.id == item_id);
+    let item = storage
+        .get_menu_item_by_id(item_id)
+        .map_err(ApiErrorType::Storage)?;
 
     if let Some(item) = item {
         let mut context = tera::Context::new();
@@ -1051,7 +2075,7 @@ pub async fn menu_item_page(
 
 // Menu Schedules Page Handler
 pub async fn menu_schedules_page(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     tera: web::Data<Tera>,
 ) -> Result<HttpResponse, ApiErrorType> {
@@ -1075,6 +2099,14 @@ pub async fn menu_schedules_page(
     let mut context = tera::Context::new();
     context.insert("presets", &presets);
     context.insert("schedules", &schedules);
+    context.insert(
+        "timezone",
+        &storage
+            .get_app_settings()
+            .map_err(ApiErrorType::Storage)?
+            .localization
+            .timezone,
+    );
 
     // Add session data to template context
     if let Ok(Some(username)) = session.get::<String>("username") {
@@ -1097,100 +2129,8 @@ pub async fn menu_schedules_page(
 
 // Menu Presets Page Handler
 
-// Reload Handlers
-pub async fn reload_menu_items(
-    storage: web::Data<JsonStorage>,
-    session: actix_session::Session,
-) -> Result<impl Responder, ApiErrorType> {
-    // Check authentication
-    let _user_id = require_auth(&session)
-        .await
-        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
-
-    // Reload menu items from storage
-    storage.load_menu_items().map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "success",
-        "message": "Menu items reloaded successfully"
-    })))
-}
-
-pub async fn reload_notices(
-    storage: web::Data<JsonStorage>,
-    session: actix_session::Session,
-) -> Result<impl Responder, ApiErrorType> {
-    // Check authentication
-    let _user_id = require_auth(&session)
-        .await
-        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
-
-    // Reload notices from storage
-    storage.load_notices().map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "success",
-        "message": "Notices reloaded successfully"
-    })))
-}
-
-pub async fn reload_admin_users(
-    storage: web::Data<JsonStorage>,
-    session: actix_session::Session,
-) -> Result<impl Responder, ApiErrorType> {
-    // Check authentication
-    let _user_id = require_auth(&session)
-        .await
-        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
-
-    // Reload admin users from storage
-    storage.load_admin_users().map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "success",
-        "message": "Admin users reloaded successfully"
-    })))
-}
-
-pub async fn reload_menu_presets(
-    storage: web::Data<JsonStorage>,
-    session: actix_session::Session,
-) -> Result<impl Responder, ApiErrorType> {
-    // Check authentication
-    let _user_id = require_auth(&session)
-        .await
-        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
-
-    // Reload menu presets from storage
-    storage.load_menu_presets().map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "success",
-        "message": "Menu presets reloaded successfully"
-    })))
-}
-
-pub async fn reload_menu_schedules(
-    storage: web::Data<JsonStorage>,
-    session: actix_session::Session,
-) -> Result<impl Responder, ApiErrorType> {
-    // Check authentication
-    let _user_id = require_auth(&session)
-        .await
-        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
-
-    // Reload menu schedules from storage
-    storage
-        .load_menu_schedules()
-        .map_err(ApiErrorType::Storage)?;
-
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "success",
-        "message": "Menu schedules reloaded successfully"
-    })))
-}
 pub async fn menu_presets_page(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
     tera: web::Data<Tera>,
 ) -> Result<HttpResponse, ApiErrorType> {
@@ -1259,7 +2199,7 @@ pub async fn not_found_page(
 
 // Menu Export Handler
 pub async fn export_menu_items(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
     session: actix_session::Session,
 ) -> Result<impl Responder, ApiErrorType> {
     // Check authentication
@@ -1291,7 +2231,8 @@ pub struct ImportMenuItemsRequest {
 }
 
 pub async fn import_menu_items(
-    storage: web::Data<JsonStorage>,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
     session: actix_session::Session,
     import_data: web::Json<ImportMenuItemsRequest>,
 ) -> Result<impl Responder, ApiErrorType> {
@@ -1343,13 +2284,36 @@ pub async fn import_menu_items(
         }
 
         // Check if item already exists by ID
-        if let Some(_existing) = existing_items.iter().find(|i| i.id == item.id) {
+        if let Some(existing) = existing_items.iter().find(|i| i.id == item.id) {
             // Update existing item
+            // The exported version acts as If-Match, so items edited since the export are skipped
             match storage
-                .update_menu_item(item.id, item.clone())
+                .update_menu_item(item.id, item.clone(), item.version)
                 .map_err(ApiErrorType::from)
             {
-                Ok(_) => updated_count += 1,
+                Ok(version) => {
+                    let updated = MenuItem {
+                        version,
+                        ..item.clone()
+                    };
+                    if record_revision(
+                        storage.get_ref(),
+                        DataKind::MenuItems,
+                        item.id,
+                        RevisionAction::Updated,
+                        Some(existing),
+                        Some(&updated),
+                        Change::new(&session_username(&session), clock.now()),
+                    )
+                    .is_some()
+                    {
+                        errors.push(format!(
+                            "Item '{}' was saved but its history could not be recorded",
+                            item.name
+                        ));
+                    }
+                    updated_count += 1;
+                }
                 Err(e) => {
                     errors.push(format!("Failed to update item '{}': {}", item.name, e));
                     skipped_count += 1;
@@ -1361,7 +2325,29 @@ pub async fn import_menu_items(
                 .add_menu_item(item.clone())
                 .map_err(ApiErrorType::from)
             {
-                Ok(_) => imported_count += 1,
+                Ok(_) => {
+                    let created = MenuItem {
+                        version: 1,
+                        ..item.clone()
+                    };
+                    if record_revision(
+                        storage.get_ref(),
+                        DataKind::MenuItems,
+                        item.id,
+                        RevisionAction::Created,
+                        None,
+                        Some(&created),
+                        Change::new(&session_username(&session), clock.now()),
+                    )
+                    .is_some()
+                    {
+                        errors.push(format!(
+                            "Item '{}' was saved but its history could not be recorded",
+                            item.name
+                        ));
+                    }
+                    imported_count += 1;
+                }
                 Err(e) => {
                     errors.push(format!("Failed to import item '{}': {}", item.name, e));
                     skipped_count += 1;
@@ -1380,3 +2366,915 @@ pub async fn import_menu_items(
         "total_processed": import_data.items.len()
     })))
 }
+
+// Backup Handlers
+pub async fn list_backups(
+    storage: web::Data<dyn Storage>,
+    session: actix_session::Session,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let settings = storage.get_app_settings().map_err(ApiErrorType::Storage)?;
+    let backups = BackupManager::new(&settings.storage)
+        .list_backups()
+        .map_err(ApiErrorType::Storage)?;
+
+    Ok(HttpResponse::Ok().json(backups))
+}
+
+pub async fn create_backup(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let settings = storage.get_app_settings().map_err(ApiErrorType::Storage)?;
+    let backup = BackupManager::new(&settings.storage)
+        .create_backup(storage.get_ref(), clock.now())
+        .map_err(ApiErrorType::Storage)?;
+
+    Ok(HttpResponse::Created().json(backup))
+}
+
+pub async fn download_backup(
+    storage: web::Data<dyn Storage>,
+    session: actix_session::Session,
+    path: web::Path<String>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let name = path.into_inner();
+    let settings = storage.get_app_settings().map_err(ApiErrorType::Storage)?;
+    let content = BackupManager::new(&settings.storage)
+        .read_backup(&name)
+        .map_err(backup_error)?;
+
+    // Return JSON with download headers
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", name),
+        ))
+        .body(content))
+}
+
+pub async fn restore_backup(
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<String>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let name = path.into_inner();
+    let settings = storage.get_app_settings().map_err(ApiErrorType::Storage)?;
+    BackupManager::new(&settings.storage)
+        .restore_backup(
+            storage.get_ref(),
+            &name,
+            Change::new(&session_username(&session), clock.now()),
+        )
+        .map_err(backup_error)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": format!("Backup {} restored successfully", name)
+    })))
+}
+
+/// Report a malformed backup name or an unsupported backup as a bad request
+/// rather than a storage failure
+fn backup_error(error: StorageError) -> ApiErrorType {
+    match error {
+        StorageError::Validation(msg) => ApiErrorType::Validation(msg),
+        unsupported @ StorageError::UnsupportedSchemaVersion { .. } => {
+            ApiErrorType::Validation(unsupported.to_string())
+        }
+        other => ApiErrorType::Storage(other),
+    }
+}
+
+// Trash Handlers
+pub async fn list_trash(
+    storage: web::Data<dyn Storage>,
+    session: actix_session::Session,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let trash = storage.get_trash().map_err(ApiErrorType::Storage)?;
+
+    Ok(HttpResponse::Ok().json(trash))
+}
+
+pub async fn restore_from_trash(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<(String, Uuid)>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let (kind, id) = path.into_inner();
+    let kind = collection_kind(&kind)?;
+    let trashed = trashed_record(storage.get_ref(), kind, id)?;
+    let expected_version = if_match(&req, trashed["version"].as_u64().unwrap_or_default())?;
+
+    let version = storage
+        .restore_from_trash(kind, id, expected_version)
+        .map_err(ApiErrorType::from)?;
+    let mut restored = trashed.clone();
+    restored["version"] = serde_json::json!(version);
+    restored["deleted_at"] = serde_json::Value::Null;
+    restored["deleted_by"] = serde_json::Value::Null;
+    let warning = record_revision(
+        storage.get_ref(),
+        kind,
+        id,
+        RevisionAction::Restored,
+        Some(&trashed),
+        Some(&restored),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(version))
+        .json(serde_json::json!({
+            "status": "success",
+            "message": format!("{} with id {} restored", kind.label(), id)
+        })))
+}
+
+pub async fn purge_from_trash(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<(String, Uuid)>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let (kind, id) = path.into_inner();
+    let kind = collection_kind(&kind)?;
+    let trashed = trashed_record(storage.get_ref(), kind, id)?;
+    let expected_version = if_match(&req, trashed["version"].as_u64().unwrap_or_default())?;
+
+    storage
+        .purge_from_trash(kind, id, expected_version)
+        .map_err(ApiErrorType::from)?;
+    let warning = record_revision(
+        storage.get_ref(),
+        kind,
+        id,
+        RevisionAction::Purged,
+        Some(&trashed),
+        None,
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::NoContent(), warning))
+}
+
+/// The collection named by the `{kind}` segment of a trash or history URL
+fn collection_kind(kind: &str) -> Result<DataKind, ApiErrorType> {
+    match kind {
+        "items" => Ok(DataKind::MenuItems),
+        "notices" => Ok(DataKind::Notices),
+        "presets" => Ok(DataKind::MenuPresets),
+        "schedules" => Ok(DataKind::MenuSchedules),
+        other => Err(ApiErrorType::Validation(format!(
+            "Unknown collection '{}', expected items, notices, presets or schedules",
+            other
+        ))),
+    }
+}
+
+/// A trashed record as JSON, for checking `If-Match` and recording history
+fn trashed_record(
+    storage: &dyn Storage,
+    kind: DataKind,
+    id: Uuid,
+) -> Result<serde_json::Value, ApiErrorType> {
+    let trash = storage.get_trash().map_err(ApiErrorType::Storage)?;
+    let record = match kind {
+        DataKind::MenuItems => trash
+            .menu_items
+            .into_iter()
+            .find(|r| r.id == id)
+            .map(serde_json::to_value),
+        DataKind::Notices => trash
+            .notices
+            .into_iter()
+            .find(|r| r.id == id)
+            .map(serde_json::to_value),
+        DataKind::MenuPresets => trash
+            .menu_presets
+            .into_iter()
+            .find(|r| r.id == id)
+            .map(serde_json::to_value),
+        DataKind::MenuSchedules => trash
+            .menu_schedules
+            .into_iter()
+            .find(|r| r.id == id)
+            .map(serde_json::to_value),
+    };
+    record
+        .transpose()
+        .map_err(|e| ApiErrorType::Storage(StorageError::from(e)))?
+        .ok_or_else(|| {
+            ApiErrorType::NotFound(format!(
+                "{} with id {} not found in the trash",
+                kind.label(),
+                id
+            ))
+        })
+}
+
+// History Handlers
+pub async fn list_revisions(
+    storage: web::Data<dyn Storage>,
+    session: actix_session::Session,
+    path: web::Path<(String, Uuid)>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let (kind, id) = path.into_inner();
+    let revisions = storage
+        .get_revisions(collection_kind(&kind)?, id)
+        .map_err(ApiErrorType::Storage)?;
+
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+pub async fn revert_to_revision(
+    req: HttpRequest,
+    storage: web::Data<dyn Storage>,
+    clock: web::Data<dyn Clock>,
+    session: actix_session::Session,
+    path: web::Path<(String, Uuid, Uuid)>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let (kind, id, revision_id) = path.into_inner();
+    let kind = collection_kind(&kind)?;
+    let revision = storage
+        .get_revisions(kind, id)
+        .map_err(ApiErrorType::Storage)?
+        .into_iter()
+        .find(|r| r.id == revision_id)
+        .ok_or_else(|| {
+            ApiErrorType::NotFound(format!(
+                "Revision {} of {} {} not found",
+                revision_id,
+                kind.label(),
+                id
+            ))
+        })?;
+
+    let (current, reverted) = match kind {
+        DataKind::MenuItems => revert_record(
+            &req,
+            &revision,
+            storage.get_menu_item_by_id(id)?,
+            |current, reverted| {
+                let mut item = current.clone();
+                apply_menu_item_update(&mut item, &menu_item_reversion(reverted))?;
+                Ok(item)
+            },
+            |record, version| storage.update_menu_item(id, record, version),
+        )?,
+        DataKind::Notices => revert_record(
+            &req,
+            &revision,
+            storage.get_notice_by_id(id)?,
+            |current, reverted| {
+                let mut notice = current.clone();
+                let update = UpdateNoticeRequest {
+                    title: Some(reverted.title),
+                    content: Some(reverted.content),
+                    is_active: Some(reverted.is_active),
+                };
+                apply_notice_update(&mut notice, &update, clock.now());
+                Ok(notice)
+            },
+            |record, version| storage.update_notice(id, record, version),
+        )?,
+        DataKind::MenuPresets => revert_record(
+            &req,
+            &revision,
+            storage.get_menu_preset_by_id(id)?,
+            |current, reverted| {
+                let mut preset = current.clone();
+                let update = UpdateMenuPresetRequest {
+                    name: Some(reverted.name),
+                    description: Some(reverted.description),
+                    menu_item_ids: Some(reverted.menu_item_ids),
+                    scope: Some(reverted.scope),
+                };
+                apply_preset_update(storage.get_ref(), &mut preset, &update, clock.now())?;
+                Ok(preset)
+            },
+            |record, version| storage.update_menu_preset(id, record, version),
+        )?,
+        DataKind::MenuSchedules => {
+            let tz = schedule_timezone(storage.get_ref())?;
+            revert_record(
+                &req,
+                &revision,
+                storage.get_menu_schedule_by_id(id)?,
+                |current, reverted| {
+                    let mut schedule = current.clone();
+                    let update = schedule_reversion(current, reverted);
+                    apply_schedule_update(
+                        storage.get_ref(),
+                        &mut schedule,
+                        &update,
+                        clock.now(),
+                        tz,
+                    )?;
+                    Ok(schedule)
+                },
+                |record, version| storage.update_menu_schedule(id, record, version),
+            )?
+        }
+    };
+    let warning = record_revision(
+        storage.get_ref(),
+        kind,
+        id,
+        RevisionAction::Reverted,
+        Some(&current),
+        Some(&reverted),
+        Change::new(&session_username(&session), clock.now()),
+    );
+
+    Ok(with_warning(HttpResponse::Ok(), warning)
+        .insert_header(etag(reverted["version"].as_u64().unwrap_or_default()))
+        .json(reverted))
+}
+
+/// Bring a live record back to the state saved in `revision`, returning the record
+/// before and after as JSON. `revise` builds the new record from the current one
+/// and the saved one
+fn revert_record<T>(
+    req: &HttpRequest,
+    revision: &Revision,
+    current: Option<T>,
+    revise: impl FnOnce(&T, T) -> Result<T, ApiErrorType>,
+    update: impl FnOnce(T, u64) -> Result<u64, StorageError>,
+) -> Result<(serde_json::Value, serde_json::Value), ApiErrorType>
+where
+    T: Serialize + serde::de::DeserializeOwned + Clone + Versioned + Trashable,
+{
+    let current = current.ok_or_else(|| {
+        ApiErrorType::NotFound(format!(
+            "{} with id {} not found",
+            revision.kind.label(),
+            revision.record_id
+        ))
+    })?;
+    let expected_version = if_match(req, current.version())?;
+
+    let saved: T = serde_json::from_value(revision.record.clone()).map_err(|e| {
+        ApiErrorType::Validation(format!(
+            "Revision {} cannot be restored: {}",
+            revision.id, e
+        ))
+    })?;
+    let mut reverted = revise(&current, saved)?;
+    // A revision taken when the record was deleted still restores it as a live record
+    reverted.set_deleted(None);
+    let version = update(reverted.clone(), expected_version)?;
+    reverted.set_version(version);
+
+    let to_json = |record: &T| {
+        serde_json::to_value(record).map_err(|e| ApiErrorType::Storage(StorageError::from(e)))
+    };
+    Ok((to_json(&current)?, to_json(&reverted)?))
+}
+
+/// The edit that takes an item back to the details saved in `saved`. Availability
+/// is left out, as the running schedules set it and put it back when they end
+fn menu_item_reversion(saved: MenuItem) -> UpdateMenuItemRequest {
+    UpdateMenuItemRequest {
+        name: Some(saved.name),
+        category: Some(format!("{:?}", saved.category)),
+        description: Some(saved.description),
+        allergens: Some(saved.allergens),
+        tags: Some(saved.tags),
+        is_available: None,
+    }
+}
+
+/// The edit that takes `current` back to the settings saved in `saved`, leaving the
+/// scheduler's state alone. The timing is only sent when the series itself differs,
+/// since the scheduler moves `start_time` on with every occurrence
+fn schedule_reversion(current: &MenuSchedule, saved: MenuSchedule) -> UpdateMenuScheduleRequest {
+    let length = |s: &MenuSchedule| s.end_time - s.start_time;
+    let series_changed = current.series_start != saved.series_start
+        || length(current) != length(&saved)
+        || current.series_end != saved.series_end
+        || format!("{:?}", current.recurrence) != format!("{:?}", saved.recurrence)
+        || current.rrule != saved.rrule;
+    let (start_time, end_time, series_end, recurrence, rrule) = if series_changed {
+        (
+            Some(TimeInput::Instant(saved.series_start)),
+            Some(TimeInput::Instant(saved.series_start + length(&saved))),
+            Some(
+                saved
+                    .series_end
+                    .map(|end| end.to_rfc3339())
+                    .unwrap_or_default(),
+            ),
+            Some(format!("{:?}", saved.recurrence)),
+            saved.rrule.clone(),
+        )
+    } else {
+        (None, None, None, None, None)
+    };
+
+    UpdateMenuScheduleRequest {
+        preset_id: Some(saved.preset_id),
+        fallback_preset_id: Some(
+            saved
+                .fallback_preset_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        ),
+        name: Some(saved.name),
+        description: Some(saved.description),
+        start_time,
+        end_time,
+        series_end,
+        recurrence,
+        rrule,
+        priority: Some(saved.priority),
+        exception_dates: Some(saved.exception_dates),
+        status: None,
+    }
+}
+
+// Reference Handlers
+pub async fn list_references(
+    storage: web::Data<dyn Storage>,
+    session: actix_session::Session,
+    path: web::Path<(String, Uuid)>,
+) -> Result<impl Responder, ApiErrorType> {
+    // Check authentication
+    let _user_id = require_auth(&session)
+        .await
+        .map_err(|e| ApiErrorType::Validation(format!("Authentication required: {}", e)))?;
+
+    let (kind, id) = path.into_inner();
+    let references = integrity::references_to(storage.get_ref(), collection_kind(&kind)?, id)
+        .map_err(ApiErrorType::Storage)?;
+
+    Ok(HttpResponse::Ok().json(references))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::config::{AppSettings, ValidationRules};
+    use crate::memory_storage::MemoryStorage;
+    use crate::storage_v2::HybridStorage;
+    use actix_session::storage::CookieSessionStore;
+    use actix_session::{SessionExt, SessionMiddleware};
+    use actix_web::body::MessageBody;
+    use actix_web::cookie::Key;
+    use actix_web::dev::{Service, ServiceFactory, ServiceRequest, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test as actix_test};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn open() -> MemoryStorage {
+        MemoryStorage::new(
+            AppSettings::load("config/settings.toml").unwrap(),
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )
+    }
+
+    /// The API routes under test, with every request signed in as alice
+    fn signed_in_app(
+        storage: Arc<dyn Storage>,
+        clock: Arc<dyn Clock>,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        App::new()
+            .app_data(web::Data::from(storage))
+            .app_data(web::Data::from(clock))
+            .wrap_fn(|req, service| {
+                let session = req.get_session();
+                session.insert("user_id", Uuid::nil()).unwrap();
+                session.insert("username", "alice").unwrap();
+                service.call(req)
+            })
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .route("/api/menu/at", web::get().to(get_menu_at))
+            .route("/api/items", web::post().to(create_menu_item))
+            .route("/api/items/{id}", web::get().to(get_menu_item))
+            .route("/api/items/{id}", web::put().to(update_menu_item))
+            .route("/api/items/{id}", web::delete().to(delete_menu_item))
+            .route("/api/schedules", web::post().to(create_menu_schedule))
+            .route("/api/schedules/{id}", web::put().to(update_menu_schedule))
+    }
+
+    fn menu_item(name: &str, is_available: bool) -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: crate::storage::MenuCategory::Mains,
+            description: String::new(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    fn lunch_schedule(preset_id: Uuid, start: DateTime<Utc>) -> MenuSchedule {
+        MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id,
+            fallback_preset_id: None,
+            name: "Lunch Service".to_string(),
+            description: "Weekday lunch".to_string(),
+            start_time: start,
+            end_time: start + Duration::hours(2),
+            series_start: start,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: start,
+            updated_at: start,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[actix_web::test]
+    async fn test_menu_at_a_future_time_follows_pending_schedules() {
+        let storage = open();
+        let fish_pie = menu_item("Fish Pie", false);
+        storage.add_menu_item(fish_pie.clone()).unwrap();
+        let lunch = preset("Lunch", vec![fish_pie.id]);
+        storage.add_menu_preset(lunch.clone()).unwrap();
+        let now = at("2025-11-03T09:00:00Z");
+        storage
+            .add_menu_schedule(lunch_schedule(lunch.id, at("2025-11-04T12:00:00Z")))
+            .unwrap();
+        let app = actix_test::init_service(signed_in_app(
+            Arc::new(storage),
+            Arc::new(ManualClock::new(now)),
+        ))
+        .await;
+
+        let menu_at = |time: &str| {
+            actix_test::TestRequest::get()
+                .uri(&format!("/api/menu/at?time={}", time))
+                .to_request()
+        };
+        let lunchtime: serde_json::Value =
+            actix_test::call_and_read_body_json(&app, menu_at("2025-11-04T12:30:00Z")).await;
+        let items = lunchtime["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], fish_pie.id.to_string());
+
+        // Before the occurrence the menu is as it stands now
+        let morning: serde_json::Value =
+            actix_test::call_and_read_body_json(&app, menu_at("2025-11-04T10:00:00Z")).await;
+        assert_eq!(morning["items"], serde_json::json!([]));
+    }
+
+    #[actix_web::test]
+    async fn test_item_writes_need_the_current_etag_in_if_match() {
+        let storage = Arc::new(open());
+        let fish_pie = menu_item("Fish Pie", true);
+        storage.add_menu_item(fish_pie.clone()).unwrap();
+        let app = actix_test::init_service(signed_in_app(
+            storage.clone(),
+            Arc::new(ManualClock::new(at("2025-11-03T09:00:00Z"))),
+        ))
+        .await;
+        let uri = format!("/api/items/{}", fish_pie.id);
+        let rename = |if_match: Option<&str>| {
+            let request = actix_test::TestRequest::put()
+                .uri(&uri)
+                .set_json(serde_json::json!({ "name": "Cod and Prawn Pie" }));
+            match if_match {
+                Some(tag) => request.insert_header((header::IF_MATCH, tag)),
+                None => request,
+            }
+            .to_request()
+        };
+
+        let fetched =
+            actix_test::call_service(&app, actix_test::TestRequest::get().uri(&uri).to_request())
+                .await;
+        assert_eq!(fetched.status(), StatusCode::OK);
+        assert_eq!(fetched.headers().get(header::ETAG).unwrap(), "\"1\"");
+
+        let missing = actix_test::call_service(&app, rename(None)).await;
+        assert_eq!(missing.status(), StatusCode::PRECONDITION_REQUIRED);
+        let stale = actix_test::call_service(&app, rename(Some("\"0\""))).await;
+        assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+        let unchanged = storage.get_menu_item_by_id(fish_pie.id).unwrap().unwrap();
+        assert_eq!(
+            (unchanged.name.as_str(), unchanged.version),
+            ("Fish Pie", 1)
+        );
+
+        let renamed = actix_test::call_service(&app, rename(Some("\"1\""))).await;
+        assert_eq!(renamed.status(), StatusCode::OK);
+        assert_eq!(renamed.headers().get(header::ETAG).unwrap(), "\"2\"");
+        let body: MenuItem = actix_test::read_body_json(renamed).await;
+        assert_eq!((body.name.as_str(), body.version), ("Cod and Prawn Pie", 2));
+
+        // The old ETag is stale now, for deletes as well as updates
+        let delete = |tag: &str| {
+            actix_test::TestRequest::delete()
+                .uri(&uri)
+                .insert_header((header::IF_MATCH, tag.to_string()))
+                .to_request()
+        };
+        let stale = actix_test::call_service(&app, delete("\"1\"")).await;
+        assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+        let deleted = actix_test::call_service(&app, delete("\"2\"")).await;
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+        assert!(storage.get_menu_item_by_id(fish_pie.id).unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_schedule_status_is_left_to_the_scheduler_and_actions() {
+        let storage = Arc::new(open());
+        let lunch = preset("Lunch", Vec::new());
+        storage.add_menu_preset(lunch.clone()).unwrap();
+        let app = actix_test::init_service(signed_in_app(
+            storage.clone(),
+            Arc::new(ManualClock::new(at("2025-11-03T09:00:00Z"))),
+        ))
+        .await;
+
+        let created = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::post()
+                .uri("/api/schedules")
+                .set_json(serde_json::json!({
+                    "preset_id": lunch.id,
+                    "name": "Lunch",
+                    "description": "Weekday lunch",
+                    "start_time": "2025-11-04T12:00:00Z",
+                    "end_time": "2025-11-04T14:00:00Z",
+                    "recurrence": "Daily",
+                    "status": "Active"
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let schedule: MenuSchedule = actix_test::read_body_json(created).await;
+        assert_eq!(schedule.status, ScheduleStatus::Pending);
+
+        let update = |body: serde_json::Value| {
+            actix_test::TestRequest::put()
+                .uri(&format!("/api/schedules/{}", schedule.id))
+                .insert_header((header::IF_MATCH, "\"1\""))
+                .set_json(body)
+                .to_request()
+        };
+        let paused =
+            actix_test::call_service(&app, update(serde_json::json!({ "status": "Paused" }))).await;
+        assert_eq!(paused.status(), StatusCode::BAD_REQUEST);
+        let stored = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (stored.status, stored.version),
+            (ScheduleStatus::Pending, 1)
+        );
+
+        // Sending the status it already has is not a change
+        let renamed = actix_test::call_service(
+            &app,
+            update(serde_json::json!({ "name": "Term Lunch", "status": "Pending" })),
+        )
+        .await;
+        assert_eq!(renamed.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_saved_write_succeeds_with_a_warning_when_history_fails() {
+        let root = std::env::temp_dir().join(format!("platter-handlers-{}", Uuid::new_v4()));
+        let (data_dir, config_dir) = (root.join("data"), root.join("config"));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::copy("config/settings.toml", config_dir.join("settings.toml")).unwrap();
+        std::fs::copy("config/validation.toml", config_dir.join("validation.toml")).unwrap();
+        let clock: Arc<dyn Clock> = Arc::new(ManualClock::new(at("2025-11-03T09:00:00Z")));
+        let storage = Arc::new(
+            HybridStorage::new(
+                data_dir.to_str().unwrap(),
+                config_dir.to_str().unwrap(),
+                clock.clone(),
+            )
+            .unwrap(),
+        );
+        std::fs::create_dir(data_dir.join("history.jsonl")).unwrap();
+        let app = actix_test::init_service(signed_in_app(storage.clone(), clock)).await;
+
+        let created = actix_test::call_service(
+            &app,
+            actix_test::TestRequest::post()
+                .uri("/api/items")
+                .set_json(serde_json::json!({
+                    "name": "Fish Pie",
+                    "category": "Mains",
+                    "description": "Smoked haddock and prawns",
+                    "allergens": ["fish"],
+                    "is_available": true
+                }))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(created.status(), StatusCode::CREATED);
+        assert!(created.headers().contains_key(header::WARNING));
+        let item: MenuItem = actix_test::read_body_json(created).await;
+        assert!(storage.get_menu_item_by_id(item.id).unwrap().is_some());
+    }
+
+    fn preset(name: &str, menu_item_ids: Vec<Uuid>) -> MenuPreset {
+        let now = Utc::now();
+        MenuPreset {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: String::new(),
+            menu_item_ids,
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_schedule_revert_keeps_the_running_occurrence() {
+        let storage = open();
+        let now = Utc::now();
+        let lunch = preset("Lunch", Vec::new());
+        storage.add_menu_preset(lunch.clone()).unwrap();
+        let saved = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: lunch.id,
+            fallback_preset_id: None,
+            name: "Lunch Service".to_string(),
+            description: "Weekday lunch".to_string(),
+            start_time: now - Duration::days(3),
+            end_time: now - Duration::days(3) + Duration::hours(2),
+            series_start: now - Duration::days(3),
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        // Since the revision the schedule was renamed and its occurrence began
+        let mut current = saved.clone();
+        current.name = "Renamed".to_string();
+        current.start_time = now - Duration::minutes(30);
+        current.end_time = current.start_time + Duration::hours(2);
+        current.status = ScheduleStatus::Active;
+        current.availability_snapshot = Some(BTreeMap::from([(Uuid::new_v4(), true)]));
+
+        let mut schedule = current.clone();
+        let update = schedule_reversion(&current, saved);
+        apply_schedule_update(&storage, &mut schedule, &update, now, chrono_tz::UTC).unwrap();
+
+        assert_eq!(schedule.name, "Lunch Service");
+        assert_eq!(schedule.status, ScheduleStatus::Active);
+        assert_eq!(schedule.start_time, current.start_time);
+        assert_eq!(
+            schedule.availability_snapshot,
+            current.availability_snapshot
+        );
+    }
+
+    #[test]
+    fn test_item_revert_leaves_availability_to_the_schedules() {
+        let saved = MenuItem {
+            id: Uuid::new_v4(),
+            name: "Fish Pie".to_string(),
+            category: crate::storage::MenuCategory::Mains,
+            description: "Haddock under mash".to_string(),
+            allergens: vec!["Fish".to_string()],
+            tags: vec!["lunch".to_string()],
+            is_available: false,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        // Since the revision milk was added and a schedule put the item on
+        let mut current = saved.clone();
+        current.allergens.push("Milk".to_string());
+        current.category = crate::storage::MenuCategory::Sides;
+        current.is_available = true;
+
+        let mut item = current.clone();
+        apply_menu_item_update(&mut item, &menu_item_reversion(saved.clone())).unwrap();
+
+        assert_eq!(item.allergens, saved.allergens);
+        assert_eq!(item.category, saved.category);
+        assert!(item.is_available);
+    }
+
+    #[test]
+    fn test_preset_revert_is_validated_like_an_update() {
+        let storage = open();
+        // The saved preset lists an item that has since gone
+        let saved = preset("Lunch", vec![Uuid::new_v4()]);
+        let mut current = saved.clone();
+        current.menu_item_ids.clear();
+
+        let update = UpdateMenuPresetRequest {
+            name: Some(saved.name),
+            description: Some(saved.description),
+            menu_item_ids: Some(saved.menu_item_ids),
+            scope: Some(saved.scope),
+        };
+        let error = apply_preset_update(&storage, &mut current, &update, Utc::now()).unwrap_err();
+
+        assert!(matches!(error, ApiErrorType::Validation(_)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::migrations::DataKind;
+use crate::storage_v2::{Storage, StorageError};
+
+/// Who is recorded for changes the scheduler makes on its own
+pub const SCHEDULER_USER: &str = "scheduler";
+
+/// What a revision did to its record
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionAction {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+    Purged,
+    Reverted,
+}
+
+/// One field whose value differs between two revisions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// An entry in the append-only change log of a menu item, notice, preset or schedule
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Revision {
+    pub id: Uuid,
+    pub kind: DataKind,
+    pub record_id: Uuid,
+    /// The record's version once the change was made
+    pub version: u64,
+    pub action: RevisionAction,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+    /// The whole record after the change, or as it last was for deletes and purges
+    pub record: Value,
+}
+
+/// Who made a change, and when by the clock they read
+#[derive(Debug, Clone, Copy)]
+pub struct Change<'a> {
+    pub by: &'a str,
+    pub at: DateTime<Utc>,
+}
+
+impl<'a> Change<'a> {
+    pub fn new(by: &'a str, at: DateTime<Utc>) -> Self {
+        Change { by, at }
+    }
+}
+
+/// Append a revision for a write that has just succeeded, if `track_item_history`
+/// is enabled.
+///
+/// `before` is `None` for creates and `after` is `None` for deletes and purges.
+/// The history is the audit trail for allergen changes, so a failure is returned
+/// for the caller to report rather than leaving a silent gap.
+pub fn record<T: Serialize>(
+    storage: &dyn Storage,
+    kind: DataKind,
+    record_id: Uuid,
+    action: RevisionAction,
+    before: Option<&T>,
+    after: Option<&T>,
+    changed: Change<'_>,
+) -> Result<(), StorageError> {
+    if !storage.get_app_settings()?.menu.track_item_history {
+        return Ok(());
+    }
+    let before = before.map(serde_json::to_value).transpose()?;
+    let after = after.map(serde_json::to_value).transpose()?;
+    let record = after.clone().or_else(|| before.clone()).unwrap_or_default();
+    let mut version = record["version"].as_u64().unwrap_or_default();
+    if action == RevisionAction::Deleted {
+        // Moving a record to the trash bumps its version
+        version += 1;
+    }
+
+    storage.append_revision(Revision {
+        id: Uuid::new_v4(),
+        kind,
+        record_id,
+        version,
+        action,
+        changed_by: changed.by.to_string(),
+        changed_at: changed.at,
+        changes: diff(before.as_ref(), after.as_ref()),
+        record,
+    })
+    .inspect_err(|e| {
+        error!(
+            "Failed to record {:?} revision of {} {}: {}",
+            action,
+            kind.label(),
+            record_id,
+            e
+        )
+    })
+}
+
+/// Fields that differ between two serialized records, in field name order.
+///
+/// The concurrency `version` is left out since every revision changes it.
+fn diff(before: Option<&Value>, after: Option<&Value>) -> Vec<FieldChange> {
+    // Deletes and purges keep the last state in the revision rather than listing
+    // every field as removed
+    let Some(after) = after.and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| name.as_str() != "version")
+        .filter_map(|name| {
+            let old = before.get(name).cloned().unwrap_or(Value::Null);
+            let new = after.get(name).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange {
+                field: name.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppSettings, ValidationRules};
+    use crate::memory_storage::MemoryStorage;
+    use crate::storage::{MenuCategory, MenuItem};
+    use serde_json::json;
+
+    fn open(track_item_history: bool) -> MemoryStorage {
+        let mut settings = AppSettings::load("config/settings.toml").unwrap();
+        settings.menu.track_item_history = track_item_history;
+        MemoryStorage::new(
+            settings,
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )
+    }
+
+    fn sample_item() -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: "Fish Pie".to_string(),
+            category: MenuCategory::Mains,
+            description: "Haddock and prawns under mash".to_string(),
+            allergens: vec!["Fish".to_string(), "Crustaceans".to_string()],
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_revisions_record_who_changed_what() {
+        let storage = open(true);
+        let created = sample_item();
+        let mut updated = created.clone();
+        updated.allergens.push("Milk".to_string());
+        updated.version = 2;
+
+        let kind = DataKind::MenuItems;
+        let id = created.id;
+        record(
+            &storage,
+            kind,
+            id,
+            RevisionAction::Created,
+            None,
+            Some(&created),
+            Change::new("alice", Utc::now()),
+        )
+        .unwrap();
+        let (before, after) = (Some(&created), Some(&updated));
+        record(
+            &storage,
+            kind,
+            id,
+            RevisionAction::Updated,
+            before,
+            after,
+            Change::new("bob", Utc::now()),
+        )
+        .unwrap();
+        record(
+            &storage,
+            kind,
+            id,
+            RevisionAction::Deleted,
+            after,
+            None,
+            Change::new("carol", Utc::now()),
+        )
+        .unwrap();
+
+        let revisions = storage.get_revisions(kind, id).unwrap();
+        let summary: Vec<_> = revisions
+            .iter()
+            .map(|r| (r.action, r.version, r.changed_by.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (RevisionAction::Created, 1, "alice"),
+                (RevisionAction::Updated, 2, "bob"),
+                (RevisionAction::Deleted, 3, "carol"),
+            ]
+        );
+        assert_eq!(revisions[1].changes.len(), 1);
+        assert_eq!(revisions[1].changes[0].field, "allergens");
+        assert_eq!(revisions[2].record["allergens"][2], "Milk");
+    }
+
+    #[test]
+    fn test_nothing_is_recorded_when_history_is_disabled() {
+        let storage = open(false);
+        let item = sample_item();
+
+        record(
+            &storage,
+            DataKind::MenuItems,
+            item.id,
+            RevisionAction::Created,
+            None,
+            Some(&item),
+            Change::new("alice", Utc::now()),
+        )
+        .unwrap();
+
+        assert!(
+            storage
+                .get_revisions(DataKind::MenuItems, item.id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_diff_lists_only_changed_fields() {
+        let before = json!({
+            "name": "Fish Pie",
+            "allergens": ["Fish", "Milk"],
+            "is_available": true,
+            "version": 3,
+        });
+        let after = json!({
+            "name": "Fish Pie",
+            "allergens": ["Fish", "Milk", "Celery"],
+            "is_available": true,
+            "version": 4,
+        });
+
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            vec![FieldChange {
+                field: "allergens".to_string(),
+                old: json!(["Fish", "Milk"]),
+                new: json!(["Fish", "Milk", "Celery"]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_created_records_diff_against_nothing() {
+        let after = json!({ "name": "Chips", "version": 1 });
+
+        let changes = diff(None, Some(&after));
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old, Value::Null);
+        assert_eq!(changes[0].new, json!("Chips"));
+        assert!(diff(Some(&after), None).is_empty());
+    }
+}
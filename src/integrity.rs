@@ -0,0 +1,651 @@
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::config::ReferencePolicy;
+use crate::history::{self, Change, RevisionAction};
+use crate::migrations::DataKind;
+use crate::scheduler;
+use crate::storage::{MenuPreset, MenuSchedule, ScheduleStatus};
+use crate::storage_v2::{Storage, StorageError, next_version, not_found};
+
+/// A live record that points at another one
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Referrer {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Everything that would be left dangling if a record were deleted.
+///
+/// Schedules are listed for menu items too, since they run the item's presets.
+#[derive(Debug, Serialize, Default)]
+pub struct References {
+    pub presets: Vec<Referrer>,
+    pub schedules: Vec<Referrer>,
+}
+
+/// A live record that points at one which is missing or in the trash
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub kind: DataKind,
+    pub record_id: Uuid,
+    pub message: String,
+}
+
+/// Serialises the writes that add or remove references, so that the records a
+/// preset or schedule was checked against are still there when it is written.
+///
+/// A thread may hold it more than once, as a cascading delete updates the
+/// referrers through the same storage.
+#[derive(Default)]
+pub struct ReferenceLock {
+    holder: Mutex<Option<(ThreadId, usize)>>,
+    released: Condvar,
+}
+
+pub struct ReferenceGuard<'a> {
+    lock: &'a ReferenceLock,
+}
+
+impl ReferenceLock {
+    pub fn hold(&self) -> Result<ReferenceGuard<'_>, StorageError> {
+        let me = thread::current().id();
+        let mut holder = self.holder.lock().map_err(|_| StorageError::PoisonError)?;
+        loop {
+            match holder.as_mut() {
+                None => *holder = Some((me, 1)),
+                Some((id, depth)) if *id == me => *depth += 1,
+                Some(_) => {
+                    holder = self
+                        .released
+                        .wait(holder)
+                        .map_err(|_| StorageError::PoisonError)?;
+                    continue;
+                }
+            }
+            return Ok(ReferenceGuard { lock: self });
+        }
+    }
+}
+
+impl Drop for ReferenceGuard<'_> {
+    fn drop(&mut self) {
+        let Ok(mut holder) = self.lock.holder.lock() else {
+            return;
+        };
+        if let Some((_, depth)) = holder.as_mut() {
+            *depth -= 1;
+            if *depth == 0 {
+                *holder = None;
+                self.lock.released.notify_one();
+            }
+        }
+    }
+}
+
+/// Presets and schedules that depend on the record `id` of `kind`
+pub fn references_to(
+    storage: &dyn Storage,
+    kind: DataKind,
+    id: Uuid,
+) -> Result<References, StorageError> {
+    let presets = match kind {
+        DataKind::MenuItems => presets_using(storage, id)?,
+        DataKind::MenuPresets => Vec::new(),
+        DataKind::Notices | DataKind::MenuSchedules => return Ok(References::default()),
+    };
+    let preset_ids: HashSet<Uuid> = match kind {
+        DataKind::MenuPresets => HashSet::from([id]),
+        _ => presets.iter().map(|p| p.id).collect(),
+    };
+    let schedules = storage
+        .get_menu_schedules()?
+        .into_iter()
+        .filter(|s| presets_of(s).any(|id| preset_ids.contains(&id)))
+        .map(|s| Referrer {
+            id: s.id,
+            name: s.name,
+        })
+        .collect();
+
+    Ok(References {
+        presets: presets
+            .into_iter()
+            .map(|p| Referrer {
+                id: p.id,
+                name: p.name,
+            })
+            .collect(),
+        schedules,
+    })
+}
+
+/// Reject a preset that lists menu items which do not exist.
+///
+/// Only items added since `previous` are checked, so a preset that was already
+/// broken on disk can still be edited.
+pub fn check_preset(
+    storage: &dyn Storage,
+    preset: &MenuPreset,
+    previous: Option<&MenuPreset>,
+) -> Result<(), StorageError> {
+    for item_id in &preset.menu_item_ids {
+        let existing = previous.is_some_and(|p| p.menu_item_ids.contains(item_id));
+        if !existing && storage.get_menu_item_by_id(*item_id)?.is_none() {
+            return Err(StorageError::Referenced(format!(
+                "Menu preset '{}' lists menu item {}, which does not exist",
+                preset.name, item_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reject a schedule whose preset or fallback preset does not exist, unless it is
+/// unchanged since `previous`
+pub fn check_schedule(
+    storage: &dyn Storage,
+    schedule: &MenuSchedule,
+    previous: Option<&MenuSchedule>,
+) -> Result<(), StorageError> {
+    for preset_id in presets_of(schedule) {
+        let existing = previous.is_some_and(|s| presets_of(s).any(|id| id == preset_id));
+        if !existing && storage.get_menu_preset_by_id(preset_id)?.is_none() {
+            return Err(StorageError::Referenced(format!(
+                "Menu schedule '{}' uses menu preset {}, which does not exist",
+                schedule.name, preset_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reject restoring a preset or schedule whose references were deleted meanwhile
+pub fn check_restore(storage: &dyn Storage, kind: DataKind, id: Uuid) -> Result<(), StorageError> {
+    let trash = storage.get_trash()?;
+    match kind {
+        DataKind::MenuPresets => match trash.menu_presets.iter().find(|p| p.id == id) {
+            Some(preset) => check_preset(storage, preset, None),
+            None => Ok(()),
+        },
+        DataKind::MenuSchedules => match trash.menu_schedules.iter().find(|s| s.id == id) {
+            Some(schedule) => check_schedule(storage, schedule, None),
+            None => Ok(()),
+        },
+        DataKind::MenuItems | DataKind::Notices => Ok(()),
+    }
+}
+
+/// Delete a menu item with `delete`, honouring `on_delete_referenced` for the
+/// presets that list it.
+///
+/// Cascading takes the item off the presets before it is deleted, so a write that
+/// fails part way leaves nothing pointing at a deleted item.
+pub fn delete_menu_item(
+    storage: &dyn Storage,
+    id: Uuid,
+    expected_version: u64,
+    deleted: Change<'_>,
+    delete: impl FnOnce() -> Result<(), StorageError>,
+) -> Result<(), StorageError> {
+    let label = DataKind::MenuItems.label();
+    let item = storage
+        .get_menu_item_by_id(id)?
+        .ok_or_else(|| not_found(label, id, false))?;
+    next_version(label, &item, expected_version)?;
+    let presets = presets_using(storage, id)?;
+    if !presets.is_empty() && policy(storage)? == ReferencePolicy::Reject {
+        return Err(StorageError::Referenced(format!(
+            "Menu item {} is used by menu presets {}",
+            id,
+            names(presets.iter().map(|p| (p.id, p.name.as_str())))
+        )));
+    }
+
+    for preset in presets {
+        let mut updated = preset.clone();
+        updated.menu_item_ids.retain(|item_id| *item_id != id);
+        updated.updated_at = deleted.at;
+        updated.version = storage.update_menu_preset(preset.id, updated.clone(), preset.version)?;
+        history::record(
+            storage,
+            DataKind::MenuPresets,
+            preset.id,
+            RevisionAction::Updated,
+            Some(&preset),
+            Some(&updated),
+            deleted,
+        )?;
+    }
+    delete()
+}
+
+/// Delete a menu preset with `delete`, honouring `on_delete_referenced` for the
+/// schedules that run it or fall back to it.
+///
+/// Cascading moves the schedules that run it to the trash, handing the menu back
+/// as at the end of an occurrence if one is running, and takes it off the
+/// schedules that only fall back to it. The preset itself goes last, so a write
+/// that fails part way leaves nothing pointing at a deleted preset.
+pub fn delete_menu_preset(
+    storage: &dyn Storage,
+    id: Uuid,
+    expected_version: u64,
+    deleted: Change<'_>,
+    delete: impl FnOnce() -> Result<(), StorageError>,
+) -> Result<(), StorageError> {
+    let label = DataKind::MenuPresets.label();
+    let preset = storage
+        .get_menu_preset_by_id(id)?
+        .ok_or_else(|| not_found(label, id, false))?;
+    next_version(label, &preset, expected_version)?;
+    let schedules: Vec<MenuSchedule> = storage
+        .get_menu_schedules()?
+        .into_iter()
+        .filter(|s| presets_of(s).any(|preset_id| preset_id == id))
+        .collect();
+    if !schedules.is_empty() && policy(storage)? == ReferencePolicy::Reject {
+        return Err(StorageError::Referenced(format!(
+            "Menu preset {} is used by menu schedules {}",
+            id,
+            names(schedules.iter().map(|s| (s.id, s.name.as_str())))
+        )));
+    }
+
+    for schedule in schedules {
+        // Handing over a running occurrence may have passed the menu to this one
+        let Some(mut schedule) = storage.get_menu_schedule_by_id(schedule.id)? else {
+            continue;
+        };
+        if schedule.preset_id != id {
+            update_schedule(storage, &schedule, deleted, |s| s.fallback_preset_id = None)?;
+            continue;
+        }
+        if schedule.status == ScheduleStatus::Active {
+            scheduler::hand_over_menu(storage, &schedule, deleted.at)?;
+            schedule = update_schedule(storage, &schedule, deleted, |s| {
+                s.availability_snapshot = None
+            })?;
+        }
+        storage.delete_menu_schedule(schedule.id, schedule.version, deleted)?;
+        history::record(
+            storage,
+            DataKind::MenuSchedules,
+            schedule.id,
+            RevisionAction::Deleted,
+            Some(&schedule),
+            None,
+            deleted,
+        )?;
+    }
+    delete()
+}
+
+/// Every live preset or schedule that points at a missing or trashed record
+pub fn scan(storage: &dyn Storage) -> Result<Vec<Finding>, StorageError> {
+    let item_ids: HashSet<Uuid> = storage.get_menu_items()?.iter().map(|i| i.id).collect();
+    let presets = storage.get_menu_presets()?;
+    let preset_ids: HashSet<Uuid> = presets.iter().map(|p| p.id).collect();
+
+    let mut findings = Vec::new();
+    for preset in &presets {
+        for item_id in preset
+            .menu_item_ids
+            .iter()
+            .filter(|id| !item_ids.contains(id))
+        {
+            findings.push(Finding {
+                kind: DataKind::MenuPresets,
+                record_id: preset.id,
+                message: format!(
+                    "preset '{}' ({}) lists missing menu item {}",
+                    preset.name, preset.id, item_id
+                ),
+            });
+        }
+    }
+    for schedule in storage.get_menu_schedules()? {
+        for preset_id in presets_of(&schedule).filter(|id| !preset_ids.contains(id)) {
+            findings.push(Finding {
+                kind: DataKind::MenuSchedules,
+                record_id: schedule.id,
+                message: format!(
+                    "schedule '{}' ({}) uses missing menu preset {}",
+                    schedule.name, schedule.id, preset_id
+                ),
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// The `data_integrity_check` summary for one collection's findings
+pub fn summary(findings: &[Finding], kind: DataKind) -> String {
+    let messages: Vec<&str> = findings
+        .iter()
+        .filter(|f| f.kind == kind)
+        .map(|f| f.message.as_str())
+        .collect();
+    if messages.is_empty() {
+        "passed".to_string()
+    } else {
+        format!("failed: {}", messages.join("; "))
+    }
+}
+
+fn presets_using(storage: &dyn Storage, item_id: Uuid) -> Result<Vec<MenuPreset>, StorageError> {
+    Ok(storage
+        .get_menu_presets()?
+        .into_iter()
+        .filter(|p| p.menu_item_ids.contains(&item_id))
+        .collect())
+}
+
+/// The preset a schedule runs and the one it falls back to, if any
+fn presets_of(schedule: &MenuSchedule) -> impl Iterator<Item = Uuid> {
+    std::iter::once(schedule.preset_id).chain(schedule.fallback_preset_id)
+}
+
+/// Write `change` to a schedule as part of a cascade, returning the updated schedule
+fn update_schedule(
+    storage: &dyn Storage,
+    schedule: &MenuSchedule,
+    changed: Change<'_>,
+    change: impl FnOnce(&mut MenuSchedule),
+) -> Result<MenuSchedule, StorageError> {
+    let mut updated = schedule.clone();
+    change(&mut updated);
+    updated.updated_at = changed.at;
+    updated.version =
+        storage.update_menu_schedule(schedule.id, updated.clone(), schedule.version)?;
+    history::record(
+        storage,
+        DataKind::MenuSchedules,
+        schedule.id,
+        RevisionAction::Updated,
+        Some(schedule),
+        Some(&updated),
+        changed,
+    )?;
+    Ok(updated)
+}
+
+fn policy(storage: &dyn Storage) -> Result<ReferencePolicy, StorageError> {
+    Ok(storage.get_app_settings()?.storage.on_delete_referenced)
+}
+
+fn names<'a>(records: impl Iterator<Item = (Uuid, &'a str)>) -> String {
+    records
+        .map(|(id, name)| format!("'{}' ({})", name, id))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppSettings, ValidationRules};
+    use crate::memory_storage::MemoryStorage;
+    use crate::storage::{MenuCategory, MenuItem, PresetScope, ScheduleRecurrence, ScheduleStatus};
+    use chrono::{Duration, Utc};
+
+    fn open(policy: ReferencePolicy) -> MemoryStorage {
+        let mut settings = AppSettings::load("config/settings.toml").unwrap();
+        settings.storage.on_delete_referenced = policy;
+        MemoryStorage::new(
+            settings,
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )
+    }
+
+    /// A menu item, a preset listing it and a schedule running the preset
+    fn seed(storage: &MemoryStorage) -> (MenuItem, MenuPreset, MenuSchedule) {
+        let now = Utc::now();
+        let item = MenuItem {
+            id: Uuid::new_v4(),
+            name: "Fish Pie".to_string(),
+            category: MenuCategory::Mains,
+            description: "Haddock and prawns under mash".to_string(),
+            allergens: vec!["Fish".to_string()],
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Friday Lunch".to_string(),
+            description: "Fish on Fridays".to_string(),
+            menu_item_ids: vec![item.id],
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let schedule = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: preset.id,
+            fallback_preset_id: None,
+            name: "Lunch Service".to_string(),
+            description: "Weekday lunch".to_string(),
+            start_time: now,
+            end_time: now + Duration::hours(2),
+            series_start: now,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_item(item.clone()).unwrap();
+        storage.add_menu_preset(preset.clone()).unwrap();
+        storage.add_menu_schedule(schedule.clone()).unwrap();
+        (item, preset, schedule)
+    }
+
+    #[test]
+    fn test_references_list_presets_and_their_schedules() {
+        let storage = open(ReferencePolicy::Reject);
+        let (item, preset, schedule) = seed(&storage);
+
+        let references = references_to(&storage, DataKind::MenuItems, item.id).unwrap();
+
+        assert_eq!(references.presets[0].id, preset.id);
+        assert_eq!(references.schedules[0].id, schedule.id);
+        let references = references_to(&storage, DataKind::MenuPresets, preset.id).unwrap();
+        assert!(references.presets.is_empty());
+        assert_eq!(references.schedules[0].name, "Lunch Service");
+    }
+
+    #[test]
+    fn test_reject_policy_keeps_referenced_records() {
+        let storage = open(ReferencePolicy::Reject);
+        let (item, preset, _) = seed(&storage);
+
+        let error = storage
+            .delete_menu_item(item.id, 1, Change::new("alice", Utc::now()))
+            .unwrap_err();
+        assert!(matches!(error, StorageError::Referenced(_)));
+        assert!(error.to_string().contains("'Friday Lunch'"));
+        assert!(
+            storage
+                .delete_menu_preset(preset.id, 1, Change::new("alice", Utc::now()))
+                .is_err()
+        );
+
+        assert!(storage.get_menu_item_by_id(item.id).unwrap().is_some());
+        assert!(storage.get_menu_preset_by_id(preset.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_cascade_policy_cleans_up_referrers() {
+        let storage = open(ReferencePolicy::Cascade);
+        let (item, preset, schedule) = seed(&storage);
+
+        storage
+            .delete_menu_item(item.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+        let preset = storage.get_menu_preset_by_id(preset.id).unwrap().unwrap();
+        assert!(preset.menu_item_ids.is_empty());
+        assert_eq!(preset.version, 2);
+
+        storage
+            .delete_menu_preset(preset.id, 2, Change::new("alice", Utc::now()))
+            .unwrap();
+        let trash = storage.get_trash().unwrap();
+        assert_eq!(trash.menu_schedules[0].id, schedule.id);
+        assert_eq!(trash.menu_schedules[0].deleted_by.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_failed_cascade_delete_leaves_referrers_alone() {
+        let storage = open(ReferencePolicy::Cascade);
+        let (item, preset, _) = seed(&storage);
+
+        let error = storage
+            .delete_menu_item(item.id, 7, Change::new("alice", Utc::now()))
+            .unwrap_err();
+
+        assert!(matches!(error, StorageError::VersionConflict { .. }));
+        let preset = storage.get_menu_preset_by_id(preset.id).unwrap().unwrap();
+        assert_eq!(preset.menu_item_ids, vec![item.id]);
+        assert_eq!(preset.version, 1);
+    }
+
+    #[test]
+    fn test_cascade_hands_back_the_menu_of_a_running_schedule() {
+        let storage = open(ReferencePolicy::Cascade);
+        let (item, preset, mut schedule) = seed(&storage);
+        // The occurrence made the item available, having found it withdrawn
+        schedule.status = ScheduleStatus::Active;
+        schedule.availability_snapshot = Some([(item.id, false)].into());
+        storage
+            .update_menu_schedule(schedule.id, schedule.clone(), 1)
+            .unwrap();
+
+        storage
+            .delete_menu_preset(preset.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+
+        let item = storage.get_menu_item_by_id(item.id).unwrap().unwrap();
+        assert!(!item.is_available);
+        let trash = storage.get_trash().unwrap();
+        assert_eq!(trash.menu_schedules[0].id, schedule.id);
+        assert_eq!(trash.menu_schedules[0].availability_snapshot, None);
+        assert_eq!(trash.menu_presets[0].id, preset.id);
+    }
+
+    #[test]
+    fn test_reference_lock_is_reentrant_but_exclusive() {
+        let lock = std::sync::Arc::new(ReferenceLock::default());
+        let outer = lock.hold().unwrap();
+        let inner = lock.hold().unwrap();
+        drop(inner);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let other = {
+            let lock = lock.clone();
+            std::thread::spawn(move || {
+                let _guard = lock.hold().unwrap();
+                sender.send(()).unwrap();
+            })
+        };
+        let wait = std::time::Duration::from_millis(50);
+        assert!(receiver.recv_timeout(wait).is_err());
+        drop(outer);
+        assert!(receiver.recv_timeout(wait * 20).is_ok());
+        other.join().unwrap();
+    }
+
+    #[test]
+    fn test_deleting_a_fallback_preset_only_clears_the_fallback() {
+        let storage = open(ReferencePolicy::Cascade);
+        let (_, preset, mut schedule) = seed(&storage);
+        let (_, fallback, other_schedule) = seed(&storage);
+        storage
+            .delete_menu_schedule(other_schedule.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+        schedule.fallback_preset_id = Some(fallback.id);
+        storage
+            .update_menu_schedule(schedule.id, schedule.clone(), 1)
+            .unwrap();
+        let references = references_to(&storage, DataKind::MenuPresets, fallback.id).unwrap();
+        assert_eq!(references.schedules[0].id, schedule.id);
+
+        storage
+            .delete_menu_preset(fallback.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+
+        let schedule = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(schedule.preset_id, preset.id);
+        assert_eq!(schedule.fallback_preset_id, None);
+        assert_eq!(schedule.version, 3);
+    }
+
+    #[test]
+    fn test_writes_pointing_at_missing_records_are_rejected() {
+        let storage = open(ReferencePolicy::Reject);
+        let (item, mut preset, mut schedule) = seed(&storage);
+
+        preset.menu_item_ids.push(Uuid::new_v4());
+        assert!(storage.update_menu_preset(preset.id, preset, 1).is_err());
+        schedule.preset_id = Uuid::new_v4();
+        assert!(
+            storage
+                .update_menu_schedule(schedule.id, schedule, 1)
+                .is_err()
+        );
+
+        // A schedule cannot come back out of the trash without its preset
+        let (_, other_preset, other_schedule) = seed(&storage);
+        storage
+            .delete_menu_schedule(other_schedule.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+        storage
+            .delete_menu_preset(other_preset.id, 1, Change::new("alice", Utc::now()))
+            .unwrap();
+        assert!(
+            storage
+                .restore_from_trash(DataKind::MenuSchedules, other_schedule.id, 2)
+                .is_err()
+        );
+        assert!(scan(&storage).unwrap().is_empty());
+        assert!(storage.get_menu_item_by_id(item.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_summary_only_lists_findings_for_its_collection() {
+        let findings = vec![Finding {
+            kind: DataKind::MenuSchedules,
+            record_id: Uuid::new_v4(),
+            message: "schedule 'Lunch Service' uses missing menu preset".to_string(),
+        }];
+
+        assert_eq!(summary(&findings, DataKind::MenuPresets), "passed");
+        assert_eq!(
+            summary(&findings, DataKind::MenuSchedules),
+            "failed: schedule 'Lunch Service' uses missing menu preset"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::storage_v2::StorageError;
+
+/// The lock file in the data directory naming the process that runs schedules
+pub const LOCK_FILE: &str = "scheduler.lock";
+
+/// A claim on running the scheduler, good until `expires_at` unless renewed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Lease {
+    /// The instance holding the lease, unique to each process
+    pub holder: String,
+    pub pid: u32,
+    pub acquired_at: DateTime<Utc>,
+    pub renewed_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Leadership among the processes sharing a data directory, such as the old and
+/// new server during a rolling restart.
+///
+/// The leader holds a lease in the lock file and renews it on every heartbeat.
+/// The others stand by and take the lease over once it expires, so a leader that
+/// dies or hangs hands over within one lease.
+pub struct LeaderLock {
+    path: PathBuf,
+    instance_id: String,
+    lease_duration: Duration,
+    /// When the lease this instance last wrote runs out, while it is the leader
+    held_until: watch::Sender<Option<DateTime<Utc>>>,
+}
+
+/// What became of an attempt to take or renew the lease
+enum Claim {
+    /// This instance holds the lease until the given time
+    Held(DateTime<Utc>),
+    /// Another instance holds an unexpired lease
+    Lost,
+    /// Another instance is updating the lease, so it was left alone
+    Contended,
+}
+
+impl LeaderLock {
+    pub fn new(data_dir: impl AsRef<Path>, lease_seconds: u64) -> Self {
+        LeaderLock {
+            path: data_dir.as_ref().join(LOCK_FILE),
+            instance_id: Uuid::new_v4().to_string(),
+            lease_duration: Duration::seconds(lease_seconds.clamp(3, 24 * 60 * 60) as i64),
+            held_until: watch::Sender::new(None),
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// How often the leader renews its lease: three times a lease, so one slow
+    /// heartbeat does not lose it
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.lease_duration / 3
+    }
+
+    /// Whether this instance held the lease at its last heartbeat
+    pub fn is_leader(&self) -> bool {
+        self.held_until.borrow().is_some()
+    }
+
+    /// Whether this instance holds a lease that has not run out by `now`. The
+    /// scheduler checks this before it writes, so a leader whose heartbeat fell
+    /// behind never writes alongside the instance that took over
+    pub fn holds_lease(&self, now: DateTime<Utc>) -> bool {
+        self.held_until.borrow().is_some_and(|until| now < until)
+    }
+
+    /// Wait until a heartbeat gives this instance a lease that has not run out by `now`
+    pub async fn wait_for_lease(&self, now: DateTime<Utc>) {
+        let mut held_until = self.held_until.subscribe();
+        // The sender lives as long as the lock, so this only returns once held
+        let _ = held_until
+            .wait_for(|until| until.is_some_and(|until| now < until))
+            .await;
+    }
+
+    /// The lease in the lock file, whoever holds it and whether or not it expired
+    pub fn current_lease(&self) -> Result<Option<Lease>, StorageError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Take the lease if there is none or it expired, or renew it if this instance
+    /// holds it. Returns whether this instance is the leader until the next heartbeat.
+    ///
+    /// A leader that cannot renew because another instance is updating the lock
+    /// file, or because of an I/O error, stays the leader until its lease runs out
+    /// rather than stepping down while nobody else can take over.
+    pub fn try_acquire(&self, now: DateTime<Utc>) -> Result<bool, StorageError> {
+        let claim = self.claim(now);
+        self.held_until.send_modify(|held_until| match claim {
+            Ok(Claim::Held(until)) => *held_until = Some(until),
+            Ok(Claim::Lost) => *held_until = None,
+            Ok(Claim::Contended) | Err(_) => {
+                if held_until.is_some_and(|until| now >= until) {
+                    *held_until = None;
+                }
+            }
+        });
+        claim.map(|_| self.is_leader())
+    }
+
+    fn claim(&self, now: DateTime<Utc>) -> Result<Claim, StorageError> {
+        let Some(_guard) = UpdateGuard::take(&self.update_path())? else {
+            return Ok(Claim::Contended);
+        };
+        let current = match self.current_lease() {
+            Ok(lease) => lease,
+            Err(StorageError::Json(e)) => {
+                warn!("Replacing unreadable scheduler lock file: {}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let acquired_at = match current {
+            Some(lease) if lease.holder == self.instance_id => lease.acquired_at,
+            Some(lease) if lease.expires_at > now => return Ok(Claim::Lost),
+            _ => now,
+        };
+        let expires_at = now + self.lease_duration;
+        self.write(&Lease {
+            holder: self.instance_id.clone(),
+            pid: std::process::id(),
+            acquired_at,
+            renewed_at: now,
+            expires_at,
+        })?;
+        Ok(Claim::Held(expires_at))
+    }
+
+    /// Give up the lease if this instance holds it, so a standby can take over
+    /// straight away instead of waiting for it to expire
+    pub fn release(&self) -> Result<(), StorageError> {
+        self.held_until.send_replace(None);
+        let Some(_guard) = UpdateGuard::take(&self.update_path())? else {
+            return Ok(());
+        };
+        if self
+            .current_lease()?
+            .is_some_and(|lease| lease.holder == self.instance_id)
+        {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the lock file in one rename, so nobody reads half a lease
+    fn write(&self, lease: &Lease) -> Result<(), StorageError> {
+        let temp_path = self
+            .path
+            .with_extension(format!("lock.{}.tmp", self.instance_id));
+        fs::write(&temp_path, serde_json::to_vec_pretty(lease)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    fn update_path(&self) -> PathBuf {
+        self.path.with_extension("lock.update")
+    }
+}
+
+/// An exclusive lock on a file next to the lease, held while one process reads
+/// and rewrites the lease, so two standbys cannot both see it expire and both take
+/// it over. The lock goes with the process, so one that dies part way through
+/// never leaves it behind; the file itself stays. Dropping the guard releases it.
+struct UpdateGuard {
+    _file: File,
+}
+
+impl UpdateGuard {
+    /// Lock the guard file, or return `None` if another process holds it
+    fn take(path: &Path) -> Result<Option<Self>, StorageError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(UpdateGuard { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("platter-leader-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_one_instance_holds_the_lease_until_it_expires() {
+        let dir = data_dir();
+        let (first, second) = (LeaderLock::new(&dir, 30), LeaderLock::new(&dir, 30));
+        let start = Utc::now();
+
+        assert!(first.try_acquire(start).unwrap());
+        assert!(!second.try_acquire(start).unwrap());
+        assert!(first.is_leader() && !second.is_leader());
+
+        // Heartbeats keep the lease with the leader
+        let renewed = start + Duration::seconds(20);
+        assert!(first.try_acquire(renewed).unwrap());
+        assert!(!second.try_acquire(start + Duration::seconds(40)).unwrap());
+        let lease = first.current_lease().unwrap().unwrap();
+        assert_eq!(lease.holder, first.instance_id());
+        assert_eq!(lease.acquired_at, start);
+        assert_eq!(lease.expires_at, renewed + Duration::seconds(30));
+
+        // Once the leader stops renewing, the standby takes over and the old
+        // leader steps down at its next heartbeat
+        let takeover = renewed + Duration::seconds(31);
+        assert!(second.try_acquire(takeover).unwrap());
+        assert!(!first.try_acquire(takeover).unwrap());
+        assert!(!first.is_leader());
+        assert_eq!(
+            second.current_lease().unwrap().unwrap().holder,
+            second.instance_id()
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_released_lease_is_taken_over_at_once() {
+        let dir = data_dir();
+        let (first, second) = (LeaderLock::new(&dir, 30), LeaderLock::new(&dir, 30));
+        let now = Utc::now();
+        assert!(first.try_acquire(now).unwrap());
+
+        // Only the holder can release the lease
+        second.release().unwrap();
+        assert!(first.current_lease().unwrap().is_some());
+        first.release().unwrap();
+        assert!(!first.is_leader());
+        assert_eq!(first.current_lease().unwrap(), None);
+        assert!(second.try_acquire(now).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_leader_keeps_its_lease_while_it_cannot_renew_it() {
+        let dir = data_dir();
+        let (leader, standby) = (LeaderLock::new(&dir, 30), LeaderLock::new(&dir, 30));
+        let start = Utc::now();
+        assert!(leader.try_acquire(start).unwrap());
+
+        // A standby is part way through checking the lease when the leader renews
+        let checking = File::create(leader.update_path()).unwrap();
+        checking.lock().unwrap();
+        assert!(leader.try_acquire(start + Duration::seconds(10)).unwrap());
+        assert!(leader.is_leader());
+        assert!(leader.holds_lease(start + Duration::seconds(29)));
+        assert!(!standby.try_acquire(start + Duration::seconds(10)).unwrap());
+
+        // Nor does an I/O error make it step down before its lease runs out
+        drop(checking);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(leader.try_acquire(start + Duration::seconds(20)).is_err());
+        assert!(leader.is_leader());
+
+        // Once it has, the leader steps down for the standby to take over
+        assert!(leader.try_acquire(start + Duration::seconds(30)).is_err());
+        assert!(!leader.is_leader());
+        assert!(!leader.holds_lease(start + Duration::seconds(30)));
+    }
+
+    #[test]
+    fn test_lease_cannot_be_taken_while_another_instance_updates_it() {
+        let dir = data_dir();
+        let lock = LeaderLock::new(&dir, 30);
+        let checking = File::create(lock.update_path()).unwrap();
+        checking.lock().unwrap();
+        assert!(!lock.try_acquire(Utc::now()).unwrap());
+
+        // The guard file left once the other instance is done does not block it
+        drop(checking);
+        assert!(lock.update_path().exists());
+        assert!(lock.try_acquire(Utc::now()).unwrap());
+
+        // An unreadable lock file is replaced rather than blocking every instance
+        fs::write(dir.join(LOCK_FILE), b"not a lease").unwrap();
+        assert!(lock.try_acquire(Utc::now()).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
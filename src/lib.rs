@@ -1,10 +1,31 @@
 //! Platter - Dining Hall Management System
 //!
-//! This library provides core functionality for the Platter application,
-//! including accessibility validation for WCAG 2.1 Level AA compliance.
+//! This library provides core functionality for the Platter application:
+//! storage, the menu scheduler, the HTTP handlers and accessibility validation
+//! for WCAG 2.1 Level AA compliance.
 
 pub mod accessibility;
+pub mod auth;
+pub mod backup;
+pub mod clock;
 pub mod config;
+pub mod error_handler;
+pub mod handlers;
+pub mod history;
+pub mod integrity;
+pub mod leader;
+pub mod memory_storage;
+pub mod migrations;
+pub mod recurrence;
+pub mod runs;
+pub mod scheduler;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
+pub mod storage;
+pub mod storage_v2;
+pub mod timezone;
+pub mod trash;
+pub mod watcher;
 
 // Re-export commonly used types
 pub use accessibility::{
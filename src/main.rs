@@ -1,11 +1,3 @@
-mod auth;
-mod error_handler;
-mod handlers;
-mod scheduler;
-mod storage;
-
-use crate::auth::create_default_admin;
-use crate::scheduler::start_scheduler;
 use actix_cors::Cors;
 use actix_files::Files;
 use actix_session::SessionMiddleware;
@@ -13,10 +5,19 @@ use actix_session::storage::CookieSessionStore;
 use actix_web::cookie::Key;
 use actix_web::middleware::Logger;
 use actix_web::{App, HttpResponse, HttpServer, web};
+use platter::auth::{self, create_default_admin};
+use platter::backup::start_backup_task;
+use platter::clock::{Clock, SystemClock};
+use platter::handlers;
+use platter::leader::LeaderLock;
+use platter::scheduler::start_scheduler;
+use platter::storage_v2::{Storage, open_storage};
+use platter::trash::start_trash_purge_task;
+use platter::watcher::start_file_watcher;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
-use storage::JsonStorage;
+use std::sync::Arc;
 use tera::Tera;
 
 fn check_data_directory_permissions() -> Result<(), Box<dyn Error>> {
@@ -61,34 +62,59 @@ async fn main() -> Result<(), Box<dyn Error>> {
         std::process::exit(1);
     }
 
-    log::info!("Initializing JSON storage system...");
-    log::debug!("About to call JsonStorage::new()");
+    log::info!("Initializing storage system...");
+    log::debug!("About to call open_storage()");
+
+    // Everything that depends on the time reads it from the same clock
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
 
-    // Initialize storage with file paths
-    let storage = JsonStorage::new(
-        "data/menu_items.json",
-        "data/notices.json",
-        "data/admin_users.json",
-        "data/menu_presets.json",
-        "data/menu_schedules.json",
-    )?;
-    log::debug!("JsonStorage::new() completed successfully");
+    // Initialize the configured backend, upgrading any legacy data files in place
+    let storage = open_storage("data", "config", clock.clone())?;
+    log::debug!("open_storage() completed successfully");
     log::info!("Storage initialized successfully!");
 
     // Wrap storage in web::Data for Actix-web
     log::debug!("Wrapping storage in web::Data");
-    let storage_data = web::Data::new(storage);
+    let storage_data: web::Data<dyn Storage> = web::Data::from(storage);
     log::debug!("Storage wrapped successfully");
 
+    let clock: web::Data<dyn Clock> = web::Data::from(clock);
+
     // Create default admin user if none exists
     log::debug!("About to call create_default_admin()");
-    create_default_admin(storage_data.clone()).await?;
+    create_default_admin(storage_data.clone(), clock.clone()).await?;
     log::debug!("create_default_admin() completed successfully");
 
+    // Start automatic backups
+    start_backup_task(storage_data.clone(), clock.clone()).await;
+
+    // Empty the trash of records past their retention period
+    start_trash_purge_task(storage_data.clone(), clock.clone()).await;
+
+    // Reload data and config files when they are edited on disk
+    start_file_watcher(storage_data.clone(), "data", "config")?;
+
+    // Only the process holding the scheduler lease runs schedules
+    let menu_settings = storage_data.get_app_settings()?.menu;
+    let leader_lock = web::Data::new(LeaderLock::new(
+        "data",
+        menu_settings.scheduler_lease_seconds,
+    ));
+
     // Start the scheduler service
-    log::debug!("Starting scheduler service");
-    start_scheduler(storage_data.clone()).await;
-    log::debug!("Scheduler service started");
+    if menu_settings.enable_scheduling {
+        log::debug!("Starting scheduler service");
+        start_scheduler(
+            storage_data.clone(),
+            clock.clone(),
+            clock.clone(),
+            leader_lock.clone(),
+        )
+        .await;
+        log::debug!("Scheduler service started");
+    } else {
+        log::info!("Menu scheduling is disabled in settings.toml");
+    }
 
     // Initialize Tera templates
     log::debug!("Initializing Tera templates");
@@ -110,11 +136,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
     log::debug!("About to configure HttpServer");
     log::info!("Starting Actix-web server on http://localhost:8080");
 
+    let server_lock = leader_lock.clone();
     HttpServer::new(move || {
         log::debug!("Inside HttpServer closure");
 
         App::new()
             .app_data(storage_data.clone())
+            .app_data(clock.clone())
+            .app_data(server_lock.clone())
             .app_data(tera_data.clone())
             .wrap(Logger::default())
             .wrap(
@@ -142,10 +171,6 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 "/api/items/{id}",
                 web::delete().to(handlers::delete_menu_item),
             )
-            .route(
-                "/api/items/reload",
-                web::post().to(handlers::reload_menu_items),
-            )
             .route(
                 "/api/items/export",
                 web::get().to(handlers::export_menu_items),
@@ -154,26 +179,50 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 "/api/items/import",
                 web::post().to(handlers::import_menu_items),
             )
+            .route("/api/items/{id}", web::get().to(handlers::get_menu_item))
+            // Menu simulation routes
+            .route("/api/menu/at", web::get().to(handlers::get_menu_at))
+            .route(
+                "/api/menu/timeline",
+                web::get().to(handlers::get_menu_timeline),
+            )
             // Notices routes
             .route("/api/notices", web::get().to(handlers::list_notices))
             .route("/api/notices", web::post().to(handlers::create_notice))
+            .route("/api/notices/{id}", web::get().to(handlers::get_notice))
             .route("/api/notices/{id}", web::put().to(handlers::update_notice))
             .route(
                 "/api/notices/{id}",
                 web::delete().to(handlers::delete_notice),
             )
+            // Trash routes
+            .route("/api/trash", web::get().to(handlers::list_trash))
+            .route(
+                "/api/trash/{kind}/{id}/restore",
+                web::post().to(handlers::restore_from_trash),
+            )
+            .route(
+                "/api/trash/{kind}/{id}",
+                web::delete().to(handlers::purge_from_trash),
+            )
+            // History routes
+            .route(
+                "/api/history/{kind}/{id}",
+                web::get().to(handlers::list_revisions),
+            )
+            .route(
+                "/api/history/{kind}/{id}/revert/{revision_id}",
+                web::post().to(handlers::revert_to_revision),
+            )
+            // Reference routes
             .route(
-                "/api/notices/reload",
-                web::post().to(handlers::reload_notices),
+                "/api/references/{kind}/{id}",
+                web::get().to(handlers::list_references),
             )
             // Authentication routes
             .route("/admin/login", web::post().to(auth::login_handler))
             .route("/admin/login", web::get().to(handlers::login_page))
             .route("/admin/logout", web::post().to(auth::logout_handler))
-            .route(
-                "/admin/users/reload",
-                web::post().to(handlers::reload_admin_users),
-            )
             // Admin dashboard route
             .route("/admin", web::get().to(handlers::admin_dashboard))
             // Menu presets routes
@@ -191,10 +240,6 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 "/api/presets/{id}",
                 web::delete().to(handlers::delete_menu_preset),
             )
-            .route(
-                "/api/presets/reload",
-                web::post().to(handlers::reload_menu_presets),
-            )
             // Menu schedules routes
             .route(
                 "/api/schedules",
@@ -216,6 +261,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 "/api/schedules/{id}",
                 web::delete().to(handlers::delete_menu_schedule),
             )
+            .route(
+                "/api/schedules/{id}/runs",
+                web::get().to(handlers::list_schedule_runs),
+            )
+            .route(
+                "/api/schedules/{id}/retry",
+                web::post().to(handlers::retry_menu_schedule),
+            )
+            .route(
+                "/api/schedules/{id}/acknowledge",
+                web::post().to(handlers::acknowledge_menu_schedule),
+            )
+            .route(
+                "/api/schedules/{id}/pause",
+                web::post().to(handlers::pause_menu_schedule),
+            )
+            .route(
+                "/api/schedules/{id}/resume",
+                web::post().to(handlers::resume_menu_schedule),
+            )
             .route(
                 "/api/schedules/upcoming",
                 web::get().to(handlers::get_upcoming_schedules),
@@ -225,8 +290,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 web::post().to(handlers::validate_schedule),
             )
             .route(
-                "/api/schedules/reload",
-                web::post().to(handlers::reload_menu_schedules),
+                "/api/scheduler/status",
+                web::get().to(handlers::get_scheduler_status),
+            )
+            // Backup routes
+            .route("/api/backups", web::get().to(handlers::list_backups))
+            .route("/api/backups", web::post().to(handlers::create_backup))
+            .route(
+                "/api/backups/{name}",
+                web::get().to(handlers::download_backup),
+            )
+            .route(
+                "/api/backups/{name}/restore",
+                web::post().to(handlers::restore_backup),
             )
             // Menu schedules page
             .route(
@@ -256,6 +332,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
     .await?;
     log::debug!("Server started successfully");
 
+    // Let a standby take over the schedules without waiting for the lease to expire
+    if let Err(e) = leader_lock.release() {
+        log::warn!("Failed to release the scheduler lease: {}", e);
+    }
+
     Ok(())
 }
 
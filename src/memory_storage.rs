@@ -0,0 +1,597 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::config::{AdminUser, AppSettings, ValidationRules};
+use crate::history::{Change, Revision};
+use crate::integrity;
+use crate::migrations::DataKind;
+use crate::runs::ScheduleRun;
+use crate::storage_v2::{
+    HasId, MenuItem, MenuPreset, MenuSchedule, Notice, ScheduleChanges, Storage, StorageError,
+    StorageSnapshot, Trash, Trashable, Versioned, next_version, not_found, supersede_versions,
+};
+
+/// A collection held only in process memory, in insertion order
+struct MemoryCollection<T> {
+    label: &'static str,
+    records: RwLock<Vec<T>>,
+}
+
+impl<T: HasId + Versioned + Trashable + Clone> MemoryCollection<T> {
+    fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            records: RwLock::new(Vec::new()),
+        }
+    }
+
+    fn all(&self) -> Result<Vec<T>, StorageError> {
+        self.matching(|r| !r.is_trashed())
+    }
+
+    fn get(&self, id: Uuid) -> Result<Option<T>, StorageError> {
+        let records = self.records.read().map_err(|_| StorageError::PoisonError)?;
+        Ok(records
+            .iter()
+            .find(|r| r.get_id() == id && !r.is_trashed())
+            .cloned())
+    }
+
+    fn trashed(&self) -> Result<Vec<T>, StorageError> {
+        self.matching(|r| r.is_trashed())
+    }
+
+    fn matching(&self, keep: impl Fn(&T) -> bool) -> Result<Vec<T>, StorageError> {
+        let records = self.records.read().map_err(|_| StorageError::PoisonError)?;
+        Ok(records.iter().filter(|r| keep(r)).cloned().collect())
+    }
+
+    fn insert(&self, mut record: T) -> Result<(), StorageError> {
+        let mut records = self
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let id = record.get_id();
+        if records.iter().any(|r| r.get_id() == id) {
+            return Err(StorageError::Validation(format!(
+                "{} with id {} already exists",
+                self.label, id
+            )));
+        }
+        record.set_version(1);
+        records.push(record);
+        Ok(())
+    }
+
+    fn update(&self, id: Uuid, mut record: T, expected_version: u64) -> Result<u64, StorageError> {
+        let mut records = self
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&records, id, false)?;
+        let version = next_version(self.label, &records[idx], expected_version)?;
+        record.set_version(version);
+        records[idx] = record;
+        Ok(version)
+    }
+
+    /// Move a record to the trash, or back out of it when `deleted` is `None`
+    fn set_trashed(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Option<Change<'_>>,
+    ) -> Result<u64, StorageError> {
+        let mut records = self
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&records, id, deleted.is_none())?;
+        let version = next_version(self.label, &records[idx], expected_version)?;
+        records[idx].set_version(version);
+        records[idx].set_deleted(deleted.map(|change| (change.at, change.by.to_string())));
+        Ok(version)
+    }
+
+    fn purge(&self, id: Uuid, expected_version: u64) -> Result<(), StorageError> {
+        let mut records = self
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&records, id, true)?;
+        next_version(self.label, &records[idx], expected_version)?;
+        records.remove(idx);
+        Ok(())
+    }
+
+    fn purge_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut records = self
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let before = records.len();
+        records.retain(|r| r.deleted_at().is_none_or(|deleted_at| deleted_at >= cutoff));
+        Ok(before - records.len())
+    }
+
+    /// Index of a live record, or of a trashed one when `in_trash` is set
+    fn position(&self, records: &[T], id: Uuid, in_trash: bool) -> Result<usize, StorageError> {
+        records
+            .iter()
+            .position(|r| r.get_id() == id && r.is_trashed() == in_trash)
+            .ok_or_else(|| not_found(self.label, id, in_trash))
+    }
+}
+
+/// Ephemeral storage for tests and throwaway instances; nothing survives a restart
+pub struct MemoryStorage {
+    menu_items: MemoryCollection<MenuItem>,
+    notices: MemoryCollection<Notice>,
+    menu_presets: MemoryCollection<MenuPreset>,
+    menu_schedules: MemoryCollection<MenuSchedule>,
+    admin_users: RwLock<Vec<AdminUser>>,
+    revisions: RwLock<Vec<Revision>>,
+    schedule_runs: RwLock<Vec<ScheduleRun>>,
+    schedule_changes: ScheduleChanges,
+    references: integrity::ReferenceLock,
+    app_settings: AppSettings,
+    validation_rules: ValidationRules,
+}
+
+impl MemoryStorage {
+    pub fn new(app_settings: AppSettings, validation_rules: ValidationRules) -> Self {
+        log::warn!("Using in-memory storage, data will be lost on shutdown");
+        Self {
+            menu_items: MemoryCollection::new("Menu item"),
+            notices: MemoryCollection::new("Notice"),
+            menu_presets: MemoryCollection::new("Menu preset"),
+            menu_schedules: MemoryCollection::new("Menu schedule"),
+            admin_users: RwLock::new(Vec::new()),
+            revisions: RwLock::new(Vec::new()),
+            schedule_runs: RwLock::new(Vec::new()),
+            schedule_changes: ScheduleChanges::default(),
+            references: integrity::ReferenceLock::default(),
+            app_settings,
+            validation_rules,
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    // Public getters
+    fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError> {
+        self.menu_items.all()
+    }
+
+    fn get_menu_item_by_id(&self, id: Uuid) -> Result<Option<MenuItem>, StorageError> {
+        self.menu_items.get(id)
+    }
+
+    fn get_notices(&self) -> Result<Vec<Notice>, StorageError> {
+        self.notices.all()
+    }
+
+    fn get_notice_by_id(&self, id: Uuid) -> Result<Option<Notice>, StorageError> {
+        self.notices.get(id)
+    }
+
+    fn get_menu_presets(&self) -> Result<Vec<MenuPreset>, StorageError> {
+        self.menu_presets.all()
+    }
+
+    fn get_menu_preset_by_id(&self, id: Uuid) -> Result<Option<MenuPreset>, StorageError> {
+        self.menu_presets.get(id)
+    }
+
+    fn get_menu_schedules(&self) -> Result<Vec<MenuSchedule>, StorageError> {
+        self.menu_schedules.all()
+    }
+
+    fn get_menu_schedule_by_id(&self, id: Uuid) -> Result<Option<MenuSchedule>, StorageError> {
+        self.menu_schedules.get(id)
+    }
+
+    fn get_admin_users(&self) -> Result<Vec<AdminUser>, StorageError> {
+        let users = self
+            .admin_users
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(users.clone())
+    }
+
+    fn get_admin_user_by_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<AdminUser>, StorageError> {
+        let users = self
+            .admin_users
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(users.iter().find(|user| user.username == username).cloned())
+    }
+
+    fn get_app_settings(&self) -> Result<AppSettings, StorageError> {
+        Ok(self.app_settings.clone())
+    }
+
+    fn get_validation_rules(&self) -> Result<ValidationRules, StorageError> {
+        Ok(self.validation_rules.clone())
+    }
+
+    // Menu items
+    fn add_menu_item(&self, item: MenuItem) -> Result<(), StorageError> {
+        self.menu_items.insert(item)
+    }
+
+    fn update_menu_item(
+        &self,
+        id: Uuid,
+        updated_item: MenuItem,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.menu_items.update(id, updated_item, expected_version)
+    }
+
+    fn delete_menu_item(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_item(self, id, expected_version, deleted, || {
+            self.menu_items
+                .set_trashed(id, expected_version, Some(deleted))
+                .map(|_| ())
+        })
+    }
+
+    // Notices
+    fn add_notice(&self, notice: Notice) -> Result<(), StorageError> {
+        self.notices.insert(notice)
+    }
+
+    fn update_notice(
+        &self,
+        id: Uuid,
+        updated_notice: Notice,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.notices.update(id, updated_notice, expected_version)
+    }
+
+    fn delete_notice(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.notices
+            .set_trashed(id, expected_version, Some(deleted))
+            .map(|_| ())
+    }
+
+    // Menu presets
+    fn add_menu_preset(&self, preset: MenuPreset) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_preset(self, &preset, None)?;
+        self.menu_presets.insert(preset)
+    }
+
+    fn update_menu_preset(
+        &self,
+        id: Uuid,
+        updated_preset: MenuPreset,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.menu_presets.get(id)?;
+        integrity::check_preset(self, &updated_preset, previous.as_ref())?;
+        self.menu_presets
+            .update(id, updated_preset, expected_version)
+    }
+
+    fn delete_menu_preset(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_preset(self, id, expected_version, deleted, || {
+            self.menu_presets
+                .set_trashed(id, expected_version, Some(deleted))
+                .map(|_| ())
+        })
+    }
+
+    // Menu schedules
+    fn add_menu_schedule(&self, schedule: MenuSchedule) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_schedule(self, &schedule, None)?;
+        self.menu_schedules.insert(schedule)?;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    fn update_menu_schedule(
+        &self,
+        id: Uuid,
+        updated_schedule: MenuSchedule,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.menu_schedules.get(id)?;
+        integrity::check_schedule(self, &updated_schedule, previous.as_ref())?;
+        let version = self
+            .menu_schedules
+            .update(id, updated_schedule, expected_version)?;
+        self.schedule_changes.notify();
+        Ok(version)
+    }
+
+    fn delete_menu_schedule(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.menu_schedules
+            .set_trashed(id, expected_version, Some(deleted))?;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    // Trash
+    fn get_trash(&self) -> Result<Trash, StorageError> {
+        Ok(Trash {
+            menu_items: self.menu_items.trashed()?,
+            notices: self.notices.trashed()?,
+            menu_presets: self.menu_presets.trashed()?,
+            menu_schedules: self.menu_schedules.trashed()?,
+        })
+    }
+
+    fn restore_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_restore(self, kind, id)?;
+        let version = match kind {
+            DataKind::MenuItems => self.menu_items.set_trashed(id, expected_version, None),
+            DataKind::Notices => self.notices.set_trashed(id, expected_version, None),
+            DataKind::MenuPresets => self.menu_presets.set_trashed(id, expected_version, None),
+            DataKind::MenuSchedules => self.menu_schedules.set_trashed(id, expected_version, None),
+        }?;
+        if kind == DataKind::MenuSchedules {
+            self.schedule_changes.notify();
+        }
+        Ok(version)
+    }
+
+    fn purge_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<(), StorageError> {
+        match kind {
+            DataKind::MenuItems => self.menu_items.purge(id, expected_version),
+            DataKind::Notices => self.notices.purge(id, expected_version),
+            DataKind::MenuPresets => self.menu_presets.purge(id, expected_version),
+            DataKind::MenuSchedules => self.menu_schedules.purge(id, expected_version),
+        }
+    }
+
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        Ok(self.menu_items.purge_before(cutoff)?
+            + self.notices.purge_before(cutoff)?
+            + self.menu_presets.purge_before(cutoff)?
+            + self.menu_schedules.purge_before(cutoff)?)
+    }
+
+    // Schedule changes
+    fn subscribe_schedule_changes(&self) -> watch::Receiver<()> {
+        self.schedule_changes.subscribe()
+    }
+
+    // Change history
+    fn append_revision(&self, revision: Revision) -> Result<(), StorageError> {
+        let mut revisions = self
+            .revisions
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        revisions.push(revision);
+        Ok(())
+    }
+
+    fn get_revisions(
+        &self,
+        kind: DataKind,
+        record_id: Uuid,
+    ) -> Result<Vec<Revision>, StorageError> {
+        let revisions = self
+            .revisions
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(revisions
+            .iter()
+            .filter(|r| r.kind == kind && r.record_id == record_id)
+            .cloned()
+            .collect())
+    }
+
+    // Schedule runs
+    fn append_schedule_run(&self, run: ScheduleRun) -> Result<(), StorageError> {
+        let mut runs = self
+            .schedule_runs
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        runs.push(run);
+        Ok(())
+    }
+
+    fn get_schedule_runs(&self, schedule_id: Uuid) -> Result<Vec<ScheduleRun>, StorageError> {
+        let runs = self
+            .schedule_runs
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(runs
+            .iter()
+            .filter(|run| run.schedule_id == schedule_id)
+            .cloned()
+            .collect())
+    }
+
+    // Admin users
+    fn add_admin_user(&self, user: AdminUser) -> Result<(), StorageError> {
+        let mut users = self
+            .admin_users
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        if users.iter().any(|u| u.username == user.username) {
+            return Err(StorageError::Validation(format!(
+                "Duplicate username found: {}",
+                user.username
+            )));
+        }
+        users.push(user);
+        Ok(())
+    }
+
+    // Backups
+    fn snapshot(&self, now: DateTime<Utc>) -> Result<StorageSnapshot, StorageError> {
+        let menu_items = self
+            .menu_items
+            .records
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let notices = self
+            .notices
+            .records
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let menu_presets = self
+            .menu_presets
+            .records
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let menu_schedules = self
+            .menu_schedules
+            .records
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(StorageSnapshot::new(
+            now,
+            menu_items.clone(),
+            notices.clone(),
+            menu_presets.clone(),
+            menu_schedules.clone(),
+        ))
+    }
+
+    fn restore_snapshot(&self, mut snapshot: StorageSnapshot) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        let mut menu_items = self
+            .menu_items
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut notices = self
+            .notices
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut menu_presets = self
+            .menu_presets
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut menu_schedules = self
+            .menu_schedules
+            .records
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        supersede_versions(&mut snapshot.menu_items, &menu_items);
+        supersede_versions(&mut snapshot.notices, &notices);
+        supersede_versions(&mut snapshot.menu_presets, &menu_presets);
+        supersede_versions(&mut snapshot.menu_schedules, &menu_schedules);
+        *menu_items = snapshot.menu_items;
+        *notices = snapshot.notices;
+        *menu_presets = snapshot.menu_presets;
+        *menu_schedules = snapshot.menu_schedules;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MenuCategory;
+
+    fn open() -> MemoryStorage {
+        MemoryStorage::new(
+            AppSettings::load("config/settings.toml").unwrap(),
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )
+    }
+
+    fn sample_item(name: &str) -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: MenuCategory::Sides,
+            description: "A test dish".to_string(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_crud_round_trip() {
+        let storage = open();
+        let first = sample_item("Chips");
+        let second = sample_item("Peas");
+        storage.add_menu_item(first.clone()).unwrap();
+        storage.add_menu_item(second.clone()).unwrap();
+
+        let mut renamed = second.clone();
+        renamed.name = "Mushy Peas".to_string();
+        storage.update_menu_item(second.id, renamed, 1).unwrap();
+        storage
+            .delete_menu_item(first.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Mushy Peas");
+        assert!(storage.get_menu_item_by_id(first.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_and_duplicate_records_are_rejected() {
+        let storage = open();
+        let item = sample_item("Gravy");
+        storage.add_menu_item(item.clone()).unwrap();
+
+        assert!(matches!(
+            storage.add_menu_item(item.clone()),
+            Err(StorageError::Validation(_))
+        ));
+        assert!(matches!(
+            storage.delete_menu_item(Uuid::new_v4(), 1, Change::new("admin", Utc::now())),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
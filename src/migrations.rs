@@ -0,0 +1,507 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::storage_v2::{SCHEMA_VERSION, StorageError};
+
+/// Version reported for pre-envelope data files that are a bare JSON array
+pub const LEGACY_SCHEMA_VERSION: &str = "0.0.0";
+
+/// The record type held by a data file, for migrations that only touch one collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataKind {
+    MenuItems,
+    Notices,
+    MenuPresets,
+    MenuSchedules,
+}
+
+impl DataKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            DataKind::MenuItems => "Menu item",
+            DataKind::Notices => "Notice",
+            DataKind::MenuPresets => "Menu preset",
+            DataKind::MenuSchedules => "Menu schedule",
+        }
+    }
+}
+
+/// One upgrade step between consecutive schema versions
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub description: &'static str,
+    pub apply: fn(&mut Value, DataKind) -> Result<(), StorageError>,
+}
+
+/// Every migration, oldest first. Each step's `to` must be the next step's `from`,
+/// and the last step must end at `SCHEMA_VERSION`.
+///
+/// Every `from` version needs fixtures in `tests/fixtures/migrations/<from>/`.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: LEGACY_SCHEMA_VERSION,
+        to: "1.0.0",
+        description: "wrap bare record arrays in the versioned envelope",
+        apply: wrap_legacy_array,
+    },
+    Migration {
+        from: "1.0.0",
+        to: "1.1.0",
+        description: "start every record at concurrency version 1",
+        apply: add_record_versions,
+    },
+    Migration {
+        from: "1.1.0",
+        to: "1.2.0",
+        description: "add empty trash fields to every record",
+        apply: add_trash_fields,
+    },
+    Migration {
+        from: "1.2.0",
+        to: "1.3.0",
+        description: "add an empty recurrence rule to every schedule",
+        apply: add_schedule_rrule,
+    },
+    Migration {
+        from: "1.3.0",
+        to: "1.4.0",
+        description: "split each schedule's window into an occurrence and a series",
+        apply: add_series_window,
+    },
+    Migration {
+        from: "1.4.0",
+        to: "1.5.0",
+        description: "add a fallback preset and availability snapshot to every schedule",
+        apply: add_schedule_restore,
+    },
+    Migration {
+        from: "1.5.0",
+        to: "1.6.0",
+        description: "give every schedule the default priority and no conflict decision",
+        apply: add_schedule_priority,
+    },
+    Migration {
+        from: "1.6.0",
+        to: "1.7.0",
+        description: "give every schedule an empty retry count and no retry time",
+        apply: add_schedule_retries,
+    },
+    Migration {
+        from: "1.7.0",
+        to: "1.8.0",
+        description: "give every schedule an empty list of exception dates",
+        apply: add_schedule_exceptions,
+    },
+    Migration {
+        from: "1.8.0",
+        to: "1.9.0",
+        description: "give every menu item no tags and every preset the whole menu as its scope",
+        apply: add_preset_scopes,
+    },
+];
+
+/// Upgrade a raw data file to `SCHEMA_VERSION`, one step at a time.
+///
+/// Returns the version the file was stored at if anything changed. Files written by
+/// a newer binary are refused rather than guessed at.
+pub fn migrate(
+    value: &mut Value,
+    kind: DataKind,
+    source: &str,
+) -> Result<Option<String>, StorageError> {
+    let stored = stored_version(value, source)?;
+    let current = parse_version(SCHEMA_VERSION, source)?;
+
+    match parse_version(&stored, source)?.cmp(&current) {
+        Ordering::Equal => return Ok(None),
+        Ordering::Greater => {
+            return Err(StorageError::UnsupportedSchemaVersion {
+                path: source.to_string(),
+                found: stored,
+                supported: SCHEMA_VERSION.to_string(),
+            });
+        }
+        Ordering::Less => {}
+    }
+
+    let mut version = stored.clone();
+    while parse_version(&version, source)? != current {
+        let from = parse_version(&version, source)?;
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| parse_version(m.from, source).ok() == Some(from))
+            .ok_or_else(|| {
+                StorageError::Validation(format!(
+                    "{}: no migration from schema version {}",
+                    source, version
+                ))
+            })?;
+        log::info!(
+            "Migrating {} from schema {} to {}: {}",
+            source,
+            step.from,
+            step.to,
+            step.description
+        );
+        (step.apply)(value, kind)?;
+        value["schema_version"] = Value::String(step.to.to_string());
+        version = step.to.to_string();
+    }
+    Ok(Some(stored))
+}
+
+/// Upgrade every collection inside a backup snapshot to `SCHEMA_VERSION`
+pub fn migrate_snapshot(snapshot: &mut Value, source: &str) -> Result<(), StorageError> {
+    let stored = stored_version(snapshot, source)?;
+    for (key, kind) in [
+        ("menu_items", DataKind::MenuItems),
+        ("notices", DataKind::Notices),
+        ("menu_presets", DataKind::MenuPresets),
+        ("menu_schedules", DataKind::MenuSchedules),
+    ] {
+        let mut envelope = json!({
+            "schema_version": stored,
+            "items": snapshot[key].take(),
+        });
+        migrate(&mut envelope, kind, source)?;
+        snapshot[key] = envelope["items"].take();
+    }
+    snapshot["schema_version"] = Value::String(SCHEMA_VERSION.to_string());
+    Ok(())
+}
+
+fn stored_version(value: &Value, source: &str) -> Result<String, StorageError> {
+    if value.is_array() {
+        return Ok(LEGACY_SCHEMA_VERSION.to_string());
+    }
+    value
+        .get("schema_version")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| StorageError::Validation(format!("{}: missing schema_version", source)))
+}
+
+fn parse_version(version: &str, source: &str) -> Result<(u32, u32, u32), StorageError> {
+    let invalid =
+        || StorageError::Validation(format!("{}: invalid schema version {}", source, version));
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => Ok((major, minor, patch)),
+        _ => Err(invalid()),
+    }
+}
+
+// 0.0.0 -> 1.0.0
+fn wrap_legacy_array(value: &mut Value, _kind: DataKind) -> Result<(), StorageError> {
+    let items = value.take();
+    *value = json!({
+        "last_updated": Utc::now().to_rfc3339(),
+        "generated_by": "platter-migration",
+        "metadata": { "data_integrity_check": "passed" },
+        "items": items,
+    });
+    Ok(())
+}
+
+// 1.0.0 -> 1.1.0
+fn add_record_versions(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("version").or_insert(json!(1));
+        }
+    }
+    Ok(())
+}
+
+// 1.1.0 -> 1.2.0
+fn add_trash_fields(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("deleted_at").or_insert(Value::Null);
+            fields.entry("deleted_by").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+// 1.2.0 -> 1.3.0
+fn add_schedule_rrule(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("rrule").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+// 1.3.0 -> 1.4.0
+fn add_series_window(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        let Some(fields) = record.as_object_mut() else {
+            continue;
+        };
+        // The old pair was both the first occurrence and the whole series
+        let start = fields.get("start_time").cloned().unwrap_or(Value::Null);
+        let end = fields.get("end_time").cloned().unwrap_or(Value::Null);
+        fields.entry("series_start").or_insert(start.clone());
+        fields.entry("series_end").or_insert(end.clone());
+
+        // A custom schedule without a rule runs once, over the whole window
+        let repeats = fields.get("recurrence").and_then(Value::as_str) != Some("Custom")
+            || fields.get("rrule").is_some_and(Value::is_string);
+        let time = |value: &Value| {
+            value
+                .as_str()
+                .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+        };
+        let (Some(start), Some(end)) = (time(&start), time(&end)) else {
+            continue;
+        };
+        if !repeats || end - start <= Duration::days(1) {
+            continue;
+        }
+
+        // Occurrences of a longer window would overlap each other, so each one keeps
+        // the time of day the window spans, e.g. 12:00 to 14:00 from 12:00 on the
+        // first day to 14:00 on the last
+        let length =
+            Duration::seconds((end - start).num_seconds() % Duration::days(1).num_seconds());
+        if length.is_zero() {
+            return Err(StorageError::Validation(format!(
+                "Menu schedule '{}' ({}) runs whole days from {} to {}, so the length of \
+                 each occurrence is unknown; set its end_time to the end of its first \
+                 occurrence and start again",
+                fields
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+                fields.get("id").and_then(Value::as_str).unwrap_or_default(),
+                start.to_rfc3339(),
+                end.to_rfc3339()
+            )));
+        }
+        fields.insert(
+            "end_time".to_string(),
+            json!((start + length).with_timezone(&Utc).to_rfc3339()),
+        );
+    }
+    Ok(())
+}
+
+// 1.4.0 -> 1.5.0
+fn add_schedule_restore(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("fallback_preset_id").or_insert(Value::Null);
+            fields.entry("availability_snapshot").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+// 1.5.0 -> 1.6.0
+fn add_schedule_priority(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("priority").or_insert(json!(0));
+            fields.entry("conflict_decision").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+// 1.6.0 -> 1.7.0
+fn add_schedule_retries(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("retry_count").or_insert(json!(0));
+            fields.entry("next_retry_at").or_insert(Value::Null);
+        }
+    }
+    Ok(())
+}
+
+// 1.7.0 -> 1.8.0
+fn add_schedule_exceptions(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    if kind != DataKind::MenuSchedules {
+        return Ok(());
+    }
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry("exception_dates").or_insert(json!([]));
+        }
+    }
+    Ok(())
+}
+
+// 1.8.0 -> 1.9.0
+fn add_preset_scopes(value: &mut Value, kind: DataKind) -> Result<(), StorageError> {
+    let (field, default) = match kind {
+        DataKind::MenuItems => ("tags", json!([])),
+        DataKind::MenuPresets => ("scope", json!({ "categories": [], "tags": [] })),
+        _ => return Ok(()),
+    };
+    for record in records_mut(value, kind)? {
+        if let Some(fields) = record.as_object_mut() {
+            fields.entry(field).or_insert_with(|| default.clone());
+        }
+    }
+    Ok(())
+}
+
+fn records_mut(value: &mut Value, kind: DataKind) -> Result<&mut Vec<Value>, StorageError> {
+    value["items"].as_array_mut().ok_or_else(|| {
+        StorageError::Validation(format!("{} data file has no items array", kind.label()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_v2::{JsonDataFile, MenuItem, MenuPreset, MenuSchedule, Notice};
+    use serde::de::DeserializeOwned;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const FIXTURE_FILES: [(&str, DataKind); 4] = [
+        ("menu_items.json", DataKind::MenuItems),
+        ("notices.json", DataKind::Notices),
+        ("menu_presets.json", DataKind::MenuPresets),
+        ("menu_schedules.json", DataKind::MenuSchedules),
+    ];
+
+    fn fixture_dir(version: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/migrations")
+            .join(version)
+    }
+
+    fn migrate_fixture<T: DeserializeOwned>(version: &str, file: &str, kind: DataKind) -> usize {
+        let path = fixture_dir(version).join(file);
+        let mut value: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let records_before = value
+            .as_array()
+            .or_else(|| value["items"].as_array())
+            .map(Vec::len)
+            .unwrap();
+
+        migrate(&mut value, kind, &path.to_string_lossy()).unwrap();
+
+        let data_file: JsonDataFile<T> = serde_json::from_value(value)
+            .unwrap_or_else(|e| panic!("{} does not load after migration: {}", path.display(), e));
+        assert_eq!(data_file.schema_version, SCHEMA_VERSION);
+        assert_eq!(data_file.items.len(), records_before, "{}", path.display());
+        records_before
+    }
+
+    #[test]
+    fn test_migrations_form_a_chain_ending_at_current_version() {
+        let last = MIGRATIONS.last().unwrap();
+        assert_eq!(last.to, SCHEMA_VERSION);
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[0].to, pair[1].from);
+        }
+    }
+
+    #[test]
+    fn test_every_fixture_version_migrates_to_current() {
+        let versions = MIGRATIONS
+            .iter()
+            .map(|m| m.from)
+            .chain(std::iter::once(SCHEMA_VERSION));
+        for version in versions {
+            assert!(
+                fixture_dir(version).is_dir(),
+                "missing fixtures for schema {}",
+                version
+            );
+            for (file, kind) in FIXTURE_FILES {
+                let records = match kind {
+                    DataKind::MenuItems => migrate_fixture::<MenuItem>(version, file, kind),
+                    DataKind::Notices => migrate_fixture::<Notice>(version, file, kind),
+                    DataKind::MenuPresets => migrate_fixture::<MenuPreset>(version, file, kind),
+                    DataKind::MenuSchedules => migrate_fixture::<MenuSchedule>(version, file, kind),
+                };
+                assert!(records > 0, "{}/{} has no records", version, file);
+            }
+        }
+    }
+
+    fn migrate_long_windows(file: &str) -> Result<Value, StorageError> {
+        let path = fixture_dir("1.3.0").join(file);
+        let mut value: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        migrate(&mut value, DataKind::MenuSchedules, &path.to_string_lossy())?;
+        Ok(value)
+    }
+
+    #[test]
+    fn test_long_legacy_windows_keep_their_time_of_day_and_series_end() {
+        let value = migrate_long_windows("long_window_schedules.json").unwrap();
+        let data_file: JsonDataFile<MenuSchedule> = serde_json::from_value(value).unwrap();
+        let [daily, overnight, one_off] = &data_file.items[..] else {
+            panic!("expected three schedules");
+        };
+        let time = |text: &str| DateTime::parse_from_rfc3339(text).unwrap();
+
+        assert_eq!(daily.end_time - daily.start_time, Duration::hours(2));
+        assert_eq!(daily.series_end, Some(time("2025-12-12T14:00:00Z").into()));
+        assert_eq!(overnight.end_time, time("2025-09-13T02:00:00Z"));
+        assert_eq!(
+            overnight.series_end,
+            Some(time("2025-10-04T02:00:00Z").into())
+        );
+        assert_eq!(one_off.end_time, time("2025-09-14T18:00:00Z"));
+    }
+
+    #[test]
+    fn test_legacy_windows_of_whole_days_are_refused() {
+        let error = migrate_long_windows("whole_day_schedules.json").unwrap_err();
+
+        assert!(matches!(error, StorageError::Validation(_)));
+        assert!(error.to_string().contains("'Exam Weeks'"));
+    }
+
+    #[test]
+    fn test_files_from_a_newer_binary_are_refused() {
+        let mut value = json!({ "schema_version": "99.0.0", "items": [] });
+
+        let result = migrate(&mut value, DataKind::Notices, "notices.json");
+
+        assert!(matches!(
+            result,
+            Err(StorageError::UnsupportedSchemaVersion { found, .. }) if found == "99.0.0"
+        ));
+    }
+
+    #[test]
+    fn test_current_files_are_left_alone() {
+        let mut value = json!({ "schema_version": SCHEMA_VERSION, "items": [] });
+        let before = value.clone();
+
+        assert_eq!(
+            migrate(&mut value, DataKind::Notices, "notices.json").unwrap(),
+            None
+        );
+        assert_eq!(value, before);
+    }
+}
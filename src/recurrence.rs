@@ -0,0 +1,756 @@
+//! RFC 5545 recurrence rules for `Custom` menu schedules.
+//!
+//! Only the subset the dining hall timetable needs is supported: FREQ (DAILY,
+//! WEEKLY, MONTHLY or YEARLY), INTERVAL, COUNT, UNTIL and BYDAY, with optional
+//! DTSTART and EXDATE lines. Anything else is rejected rather than ignored, so a
+//! rule never quietly runs on days the admin did not ask for.
+//!
+//! Rules repeat in the wall-clock time of the configured timezone, so a rule
+//! starting at 07:30 stays at 07:30 across daylight saving changes.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use crate::timezone;
+
+/// How a DTSTART line is written when a rule is anchored to a schedule
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Expansion gives up after this many periods (about 27 years of days), so a
+/// rule that can never match does not loop forever
+const MAX_PERIODS: u32 = 10_000;
+
+/// Why a recurrence rule was rejected
+#[derive(Debug, Error, PartialEq)]
+pub enum RuleError {
+    #[error("no RRULE line found")]
+    MissingRule,
+    #[error("unknown property '{0}', expected DTSTART, RRULE or EXDATE")]
+    UnknownProperty(String),
+    #[error("{0} is given more than once")]
+    DuplicateProperty(String),
+    #[error("unsupported parameter '{0}', only VALUE and TZID are allowed")]
+    UnsupportedParameter(String),
+    #[error("unknown TZID '{0}', expected an IANA name such as Europe/London")]
+    UnknownTimezone(String),
+    #[error("'{0}' is not of the form NAME=VALUE")]
+    MalformedPart(String),
+    #[error("unsupported RRULE part '{0}', expected FREQ, INTERVAL, COUNT, UNTIL or BYDAY")]
+    UnknownPart(String),
+    #[error("RRULE part {0} is given more than once")]
+    DuplicatePart(String),
+    #[error("RRULE is missing FREQ")]
+    MissingFreq,
+    #[error("FREQ must be DAILY, WEEKLY, MONTHLY or YEARLY, not '{0}'")]
+    UnsupportedFreq(String),
+    #[error("{part} must be a whole number greater than zero, not '{value}'")]
+    InvalidNumber { part: &'static str, value: String },
+    #[error("COUNT and UNTIL cannot both be set")]
+    CountWithUntil,
+    #[error("'{0}' is not a BYDAY value such as MO, TU or 1MO")]
+    InvalidDay(String),
+    #[error("BYDAY ordinal in '{0}' must be between 1 and 5 or -5 and -1")]
+    OrdinalOutOfRange(String),
+    #[error("BYDAY values with an ordinal such as '{0}' are only allowed with FREQ=MONTHLY")]
+    OrdinalNotMonthly(String),
+    #[error("BYDAY is not supported with FREQ=YEARLY")]
+    ByDayWithYearly,
+    #[error("{property} value '{value}' is not a date (YYYYMMDD) or date-time (YYYYMMDDTHHMMSSZ)")]
+    InvalidDate {
+        property: &'static str,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A BYDAY entry, e.g. `TU` or `-1FR` for the last Friday of the month
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ByDay {
+    ordinal: Option<i8>,
+    weekday: Weekday,
+}
+
+/// A DTSTART, UNTIL or EXDATE value. Dates cover the whole day, and dates and
+/// floating times (without `Z` or a TZID) are in the configured timezone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Moment {
+    Date(NaiveDate),
+    Floating(NaiveDateTime),
+    Utc(DateTime<Utc>),
+}
+
+impl Moment {
+    /// The wall-clock time this starts at in `tz`
+    fn first(self, tz: Tz) -> NaiveDateTime {
+        match self {
+            Moment::Date(date) => date.and_time(NaiveTime::MIN),
+            Moment::Floating(local) => local,
+            Moment::Utc(time) => timezone::local(tz, time),
+        }
+    }
+
+    /// The last instant this covers in `tz`
+    fn last(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            Moment::Date(date) => {
+                timezone::resolve(tz, date.and_hms_opt(23, 59, 59).unwrap_or_default())
+            }
+            Moment::Floating(local) => timezone::resolve(tz, local),
+            Moment::Utc(time) => time,
+        }
+    }
+
+    fn covers(self, local: NaiveDateTime, time: DateTime<Utc>) -> bool {
+        match self {
+            Moment::Date(date) => local.date() == date,
+            Moment::Floating(moment) => moment == local,
+            Moment::Utc(moment) => moment == time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<Moment>,
+    by_day: Vec<ByDay>,
+}
+
+/// A parsed RRULE with its optional DTSTART and EXDATE lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecurrenceSet {
+    dtstart: Option<Moment>,
+    rule: Rule,
+    exdates: Vec<Moment>,
+}
+
+impl RecurrenceSet {
+    /// Parse a rule such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU`, or DTSTART, RRULE and
+    /// EXDATE lines separated by newlines.
+    ///
+    /// Times ending in `Z` are UTC and DTSTART and EXDATE may name a TZID; any other
+    /// time, and any date, is read in the timezone passed to [`Self::occurrences`].
+    pub fn parse(text: &str) -> Result<RecurrenceSet, RuleError> {
+        let mut dtstart = None;
+        let mut rule = None;
+        let mut exdates = Vec::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            // A line without a property name is the rule itself
+            let (name, value) = line.split_once(':').unwrap_or(("RRULE", line));
+            let mut params = name.split(';');
+            let name = params.next().unwrap_or_default().to_ascii_uppercase();
+            let mut tzid = None;
+            for param in params {
+                let upper = param.to_ascii_uppercase();
+                if upper.starts_with("TZID=") {
+                    let zone = &param["TZID=".len()..];
+                    tzid = Some(
+                        zone.parse::<Tz>()
+                            .map_err(|_| RuleError::UnknownTimezone(zone.to_string()))?,
+                    );
+                } else if !matches!(upper.as_str(), "VALUE=DATE" | "VALUE=DATE-TIME") {
+                    return Err(RuleError::UnsupportedParameter(param.to_string()));
+                }
+            }
+
+            let duplicate = match name.as_str() {
+                "DTSTART" => dtstart
+                    .replace(parse_moment("DTSTART", value, tzid)?)
+                    .is_some(),
+                "RRULE" => rule.replace(parse_rule(value)?).is_some(),
+                "EXDATE" => {
+                    for date in value.split(',') {
+                        exdates.push(parse_moment("EXDATE", date.trim(), tzid)?);
+                    }
+                    false
+                }
+                _ => return Err(RuleError::UnknownProperty(name)),
+            };
+            if duplicate {
+                return Err(RuleError::DuplicateProperty(name));
+            }
+        }
+
+        Ok(RecurrenceSet {
+            dtstart,
+            rule: rule.ok_or(RuleError::MissingRule)?,
+            exdates,
+        })
+    }
+
+    /// Parse `text` and pin it to `start` with a DTSTART line in `tz` unless it has
+    /// its own, so COUNT and INTERVAL keep counting from the same day however often
+    /// the schedule is moved on
+    pub fn anchor(
+        text: &str,
+        start: DateTime<Utc>,
+        tz: Tz,
+    ) -> Result<(String, RecurrenceSet), RuleError> {
+        let mut set = RecurrenceSet::parse(text)?;
+        let text = text.trim();
+        if set.dtstart.is_some() {
+            return Ok((text.to_string(), set));
+        }
+
+        set.dtstart = Some(Moment::Utc(start));
+        let dtstart = format!(
+            "DTSTART;TZID={}:{}",
+            tz.name(),
+            timezone::local(tz, start).format(LOCAL_FORMAT)
+        );
+        let text = if text.contains(':') {
+            format!("{}\n{}", dtstart, text)
+        } else {
+            format!("{}\nRRULE:{}", dtstart, text)
+        };
+        Ok((text, set))
+    }
+
+    /// Every occurrence in order, starting from DTSTART or else `default_start`, and
+    /// repeating in the wall-clock time of `tz`.
+    ///
+    /// COUNT includes the dates EXDATE removes, as RFC 5545 applies exclusions last.
+    pub fn occurrences(&self, default_start: DateTime<Utc>, tz: Tz) -> Occurrences<'_> {
+        Occurrences {
+            set: self,
+            tz,
+            start: self.dtstart.map_or_else(
+                || timezone::local(tz, default_start),
+                |start| start.first(tz),
+            ),
+            until: self.rule.until.map(|until| until.last(tz)),
+            period: 0,
+            generated: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn is_excluded(&self, local: NaiveDateTime, time: DateTime<Utc>) -> bool {
+        self.exdates.iter().any(|exdate| exdate.covers(local, time))
+    }
+}
+
+/// Iterator over the occurrences of a [`RecurrenceSet`]
+pub struct Occurrences<'a> {
+    set: &'a RecurrenceSet,
+    tz: Tz,
+    start: NaiveDateTime,
+    until: Option<DateTime<Utc>>,
+    period: u32,
+    generated: u32,
+    pending: VecDeque<NaiveDateTime>,
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<DateTime<Utc>> {
+        let rule = &self.set.rule;
+        loop {
+            if let Some(local) = self.pending.pop_front() {
+                let time = timezone::resolve(self.tz, local);
+                let counted_out = rule.count.is_some_and(|count| self.generated >= count);
+                let past_until = self.until.is_some_and(|until| time > until);
+                if counted_out || past_until {
+                    self.done = true;
+                    self.pending.clear();
+                    return None;
+                }
+                self.generated += 1;
+                if self.set.is_excluded(local, time) {
+                    continue;
+                }
+                return Some(time);
+            }
+
+            if self.done || self.period >= MAX_PERIODS {
+                return None;
+            }
+            let start = self.start;
+            self.pending = rule
+                .candidates(start, self.period)
+                .into_iter()
+                .filter(|time| *time >= start)
+                .collect();
+            self.period += 1;
+        }
+    }
+}
+
+impl Rule {
+    /// Wall-clock occurrences in the `period`th interval after `start`, in order,
+    /// before COUNT, UNTIL and EXDATE are applied
+    fn candidates(&self, start: NaiveDateTime, period: u32) -> Vec<NaiveDateTime> {
+        let step = period.saturating_mul(self.interval);
+        let date = start.date();
+        let dates: Vec<NaiveDate> = match self.frequency {
+            Frequency::Daily => date
+                .checked_add_days(Days::new(step.into()))
+                .filter(|day| self.by_day.is_empty() || self.on_weekday(day.weekday()))
+                .into_iter()
+                .collect(),
+            Frequency::Weekly => {
+                let week = date
+                    .checked_sub_days(Days::new(date.weekday().num_days_from_monday().into()))
+                    .and_then(|monday| monday.checked_add_days(Days::new(u64::from(step) * 7)));
+                let Some(week) = week else {
+                    return Vec::new();
+                };
+                let mut weekdays: Vec<u32> = if self.by_day.is_empty() {
+                    vec![date.weekday().num_days_from_monday()]
+                } else {
+                    self.by_day
+                        .iter()
+                        .map(|day| day.weekday.num_days_from_monday())
+                        .collect()
+                };
+                weekdays.sort_unstable();
+                weekdays.dedup();
+                weekdays
+                    .into_iter()
+                    .filter_map(|offset| week.checked_add_days(Days::new(offset.into())))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let Some(first) = date
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(step)))
+                else {
+                    return Vec::new();
+                };
+                if self.by_day.is_empty() {
+                    // Months without the start day, like 31 in April, are skipped
+                    first.with_day(date.day()).into_iter().collect()
+                } else {
+                    self.days_in_month(first)
+                }
+            }
+            Frequency::Yearly => i32::try_from(step)
+                .ok()
+                .and_then(|step| date.year().checked_add(step))
+                .and_then(|year| NaiveDate::from_ymd_opt(year, date.month(), date.day()))
+                .into_iter()
+                .collect(),
+        };
+
+        dates
+            .into_iter()
+            .map(|day| day.and_time(start.time()))
+            .collect()
+    }
+
+    fn on_weekday(&self, weekday: Weekday) -> bool {
+        self.by_day.iter().any(|day| day.weekday == weekday)
+    }
+
+    /// Days of the month starting at `first` that match BYDAY, e.g. `1MO` or `-1FR`
+    fn days_in_month(&self, first: NaiveDate) -> Vec<NaiveDate> {
+        let length = first
+            .checked_add_months(Months::new(1))
+            .map(|next| next.signed_duration_since(first).num_days())
+            .unwrap_or(28) as u32;
+        (1..=length)
+            .filter_map(|day| first.with_day(day))
+            .filter(|date| {
+                let from_start = ((date.day() - 1) / 7 + 1) as i8;
+                let from_end = -(((length - date.day()) / 7 + 1) as i8);
+                self.by_day.iter().any(|day| {
+                    day.weekday == date.weekday()
+                        && day
+                            .ordinal
+                            .is_none_or(|ordinal| ordinal == from_start || ordinal == from_end)
+                })
+            })
+            .collect()
+    }
+}
+
+fn parse_rule(value: &str) -> Result<Rule, RuleError> {
+    let mut frequency = None;
+    let mut interval = None;
+    let mut count = None;
+    let mut until = None;
+    let mut by_day = None;
+
+    for part in value
+        .split(';')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (name, value) = part
+            .split_once('=')
+            .ok_or_else(|| RuleError::MalformedPart(part.to_string()))?;
+        let name = name.trim().to_ascii_uppercase();
+        let value = value.trim();
+        let duplicate = match name.as_str() {
+            "FREQ" => frequency.replace(parse_frequency(value)?).is_some(),
+            "INTERVAL" => interval
+                .replace(parse_positive("INTERVAL", value)?)
+                .is_some(),
+            "COUNT" => count.replace(parse_positive("COUNT", value)?).is_some(),
+            "UNTIL" => until.replace(parse_moment("UNTIL", value, None)?).is_some(),
+            "BYDAY" => by_day.replace(parse_by_day(value)?).is_some(),
+            _ => return Err(RuleError::UnknownPart(name)),
+        };
+        if duplicate {
+            return Err(RuleError::DuplicatePart(name));
+        }
+    }
+
+    let frequency = frequency.ok_or(RuleError::MissingFreq)?;
+    if count.is_some() && until.is_some() {
+        return Err(RuleError::CountWithUntil);
+    }
+    let by_day: Vec<ByDay> = by_day.unwrap_or_default();
+    if frequency == Frequency::Yearly && !by_day.is_empty() {
+        return Err(RuleError::ByDayWithYearly);
+    }
+    if frequency != Frequency::Monthly
+        && let Some(day) = by_day.iter().find(|day| day.ordinal.is_some())
+    {
+        return Err(RuleError::OrdinalNotMonthly(format!(
+            "{}{}",
+            day.ordinal.unwrap_or_default(),
+            weekday_code(day.weekday)
+        )));
+    }
+
+    Ok(Rule {
+        frequency,
+        interval: interval.unwrap_or(1),
+        count,
+        until,
+        by_day,
+    })
+}
+
+fn parse_frequency(value: &str) -> Result<Frequency, RuleError> {
+    match value.to_ascii_uppercase().as_str() {
+        "DAILY" => Ok(Frequency::Daily),
+        "WEEKLY" => Ok(Frequency::Weekly),
+        "MONTHLY" => Ok(Frequency::Monthly),
+        "YEARLY" => Ok(Frequency::Yearly),
+        _ => Err(RuleError::UnsupportedFreq(value.to_string())),
+    }
+}
+
+fn parse_positive(part: &'static str, value: &str) -> Result<u32, RuleError> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|number| *number > 0)
+        .ok_or_else(|| RuleError::InvalidNumber {
+            part,
+            value: value.to_string(),
+        })
+}
+
+fn parse_by_day(value: &str) -> Result<Vec<ByDay>, RuleError> {
+    value
+        .split(',')
+        .map(|entry| {
+            let entry = entry.trim();
+            let invalid = || RuleError::InvalidDay(entry.to_string());
+            let split = entry.len().checked_sub(2).ok_or_else(invalid)?;
+            if !entry.is_char_boundary(split) {
+                return Err(invalid());
+            }
+            let (ordinal, code) = entry.split_at(split);
+            let weekday = match code.to_ascii_uppercase().as_str() {
+                "MO" => Weekday::Mon,
+                "TU" => Weekday::Tue,
+                "WE" => Weekday::Wed,
+                "TH" => Weekday::Thu,
+                "FR" => Weekday::Fri,
+                "SA" => Weekday::Sat,
+                "SU" => Weekday::Sun,
+                _ => return Err(invalid()),
+            };
+            let ordinal = if ordinal.is_empty() {
+                None
+            } else {
+                let ordinal: i8 = ordinal.parse().map_err(|_| invalid())?;
+                if ordinal == 0 || !(-5..=5).contains(&ordinal) {
+                    return Err(RuleError::OrdinalOutOfRange(entry.to_string()));
+                }
+                Some(ordinal)
+            };
+            Ok(ByDay { ordinal, weekday })
+        })
+        .collect()
+}
+
+/// Parse a date or date-time, reading floating times in `tzid` when one is given
+fn parse_moment(
+    property: &'static str,
+    value: &str,
+    tzid: Option<Tz>,
+) -> Result<Moment, RuleError> {
+    if let Some(utc) = value.strip_suffix('Z')
+        && let Ok(time) = NaiveDateTime::parse_from_str(utc, LOCAL_FORMAT)
+    {
+        return Ok(Moment::Utc(time.and_utc()));
+    }
+    if let Ok(local) = NaiveDateTime::parse_from_str(value, LOCAL_FORMAT) {
+        return Ok(match tzid {
+            Some(tz) => Moment::Utc(timezone::resolve(tz, local)),
+            None => Moment::Floating(local),
+        });
+    }
+    if value.len() == 8
+        && let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d")
+    {
+        return Ok(Moment::Date(date));
+    }
+    Err(RuleError::InvalidDate {
+        property,
+        value: value.to_string(),
+    })
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn expand(rule: &str, start: &str, limit: usize) -> Vec<String> {
+        RecurrenceSet::parse(rule)
+            .unwrap()
+            .occurrences(at(start), Tz::UTC)
+            .take(limit)
+            .map(|time| time.format("%a %Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_weekdays_only() {
+        // 2025-01-03 is a Friday
+        assert_eq!(
+            expand("FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR", "2025-01-03T11:30:00Z", 4),
+            vec![
+                "Fri 2025-01-03 11:30",
+                "Mon 2025-01-06 11:30",
+                "Tue 2025-01-07 11:30",
+                "Wed 2025-01-08 11:30",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_other_tuesday() {
+        // Starting on a Wednesday, the first Tuesday is in the next fortnight
+        assert_eq!(
+            expand("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU", "2025-01-01T12:00:00Z", 3),
+            vec![
+                "Tue 2025-01-14 12:00",
+                "Tue 2025-01-28 12:00",
+                "Tue 2025-02-11 12:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_monday_and_last_friday_of_the_month() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=1MO", "2025-01-01T08:00:00Z", 3),
+            vec![
+                "Mon 2025-01-06 08:00",
+                "Mon 2025-02-03 08:00",
+                "Mon 2025-03-03 08:00",
+            ]
+        );
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=-1FR", "2025-01-01T08:00:00Z", 2),
+            vec!["Fri 2025-01-31 08:00", "Fri 2025-02-28 08:00"]
+        );
+    }
+
+    #[test]
+    fn test_months_without_the_start_day_are_skipped() {
+        assert_eq!(
+            expand("FREQ=MONTHLY;COUNT=3", "2025-01-31T09:00:00Z", 10),
+            vec![
+                "Fri 2025-01-31 09:00",
+                "Mon 2025-03-31 09:00",
+                "Sat 2025-05-31 09:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_includes_excluded_dates() {
+        let rule = "DTSTART:20250106T120000Z\n\
+                    RRULE:FREQ=DAILY;COUNT=5\n\
+                    EXDATE:20250107T120000Z,20250109";
+        assert_eq!(
+            expand(rule, "2030-01-01T00:00:00Z", 10),
+            vec![
+                "Mon 2025-01-06 12:00",
+                "Wed 2025-01-08 12:00",
+                "Fri 2025-01-10 12:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_until_is_inclusive_and_date_only_until_covers_the_day() {
+        assert_eq!(
+            expand(
+                "FREQ=WEEKLY;UNTIL=20250120T120000Z",
+                "2025-01-06T12:00:00Z",
+                10
+            )
+            .len(),
+            3
+        );
+        assert_eq!(
+            expand("FREQ=DAILY;UNTIL=20250108", "2025-01-06T18:00:00Z", 10).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_rules_that_never_match_end() {
+        let set = RecurrenceSet::parse("FREQ=MONTHLY;BYDAY=5MO;UNTIL=20250228").unwrap();
+        assert_eq!(
+            set.occurrences(at("2025-01-01T00:00:00Z"), Tz::UTC).count(),
+            0
+        );
+        let set = RecurrenceSet::parse("FREQ=YEARLY").unwrap();
+        assert_eq!(
+            set.occurrences(at("2024-02-29T00:00:00Z"), Tz::UTC).nth(1),
+            Some(at("2028-02-29T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_anchor_adds_a_dtstart_only_when_missing() {
+        let start = at("2025-01-06T12:00:00Z");
+        let london = chrono_tz::Europe::London;
+        let (text, _) = RecurrenceSet::anchor("FREQ=DAILY", start, london).unwrap();
+        assert_eq!(
+            text,
+            "DTSTART;TZID=Europe/London:20250106T120000\nRRULE:FREQ=DAILY"
+        );
+
+        // Anchored rules keep counting from their own DTSTART
+        let later = at("2026-06-01T00:00:00Z");
+        let (anchored, set) = RecurrenceSet::anchor(&text, later, london).unwrap();
+        assert_eq!(anchored, text);
+        assert_eq!(set.occurrences(later, london).next(), Some(start));
+    }
+
+    #[test]
+    fn test_occurrences_keep_their_local_time_across_daylight_saving() {
+        let rule = "DTSTART;TZID=Europe/London:20250329T073000\nRRULE:FREQ=DAILY;COUNT=2";
+        let set = RecurrenceSet::parse(rule).unwrap();
+        let london = chrono_tz::Europe::London;
+        let times: Vec<_> = set
+            .occurrences(at("2030-01-01T00:00:00Z"), london)
+            .map(|time| time.to_rfc3339())
+            .collect();
+
+        assert_eq!(
+            times,
+            vec!["2025-03-29T07:30:00+00:00", "2025-03-30T06:30:00+00:00"]
+        );
+
+        // Floating times and dates are read in the timezone the rule is expanded in
+        let set = RecurrenceSet::parse(
+            "DTSTART:20250701T120000\nRRULE:FREQ=DAILY;UNTIL=20250702\nEXDATE:20250701T120000",
+        )
+        .unwrap();
+        let times: Vec<_> = set
+            .occurrences(at("2030-01-01T00:00:00Z"), london)
+            .collect();
+        assert_eq!(times, vec![at("2025-07-02T11:00:00Z")]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected_with_precise_messages() {
+        let error = |text: &str| RecurrenceSet::parse(text).unwrap_err().to_string();
+
+        assert_eq!(error("INTERVAL=2"), "RRULE is missing FREQ");
+        assert_eq!(
+            error("FREQ=HOURLY"),
+            "FREQ must be DAILY, WEEKLY, MONTHLY or YEARLY, not 'HOURLY'"
+        );
+        assert_eq!(
+            error("FREQ=DAILY;INTERVAL=0"),
+            "INTERVAL must be a whole number greater than zero, not '0'"
+        );
+        assert_eq!(
+            error("FREQ=DAILY;COUNT=3;UNTIL=20250101"),
+            "COUNT and UNTIL cannot both be set"
+        );
+        assert_eq!(
+            error("FREQ=WEEKLY;BYDAY=TU,XX"),
+            "'XX' is not a BYDAY value such as MO, TU or 1MO"
+        );
+        assert_eq!(
+            error("FREQ=WEEKLY;BYDAY=1MO"),
+            "BYDAY values with an ordinal such as '1MO' are only allowed with FREQ=MONTHLY"
+        );
+        assert_eq!(
+            error("FREQ=MONTHLY;BYDAY=6MO"),
+            "BYDAY ordinal in '6MO' must be between 1 and 5 or -5 and -1"
+        );
+        assert_eq!(
+            error("FREQ=DAILY;BYMONTH=1"),
+            "unsupported RRULE part 'BYMONTH', expected FREQ, INTERVAL, COUNT, UNTIL or BYDAY"
+        );
+        assert_eq!(
+            error("FREQ=DAILY;FREQ=WEEKLY"),
+            "RRULE part FREQ is given more than once"
+        );
+        assert_eq!(
+            error("FREQ=DAILY;UNTIL=2025-01-01"),
+            "UNTIL value '2025-01-01' is not a date (YYYYMMDD) or date-time (YYYYMMDDTHHMMSSZ)"
+        );
+        assert_eq!(error("DTSTART:20250101"), "no RRULE line found");
+        assert_eq!(
+            error("DTSTART;TZID=Mars/Olympus:20250101T120000\nRRULE:FREQ=DAILY"),
+            "unknown TZID 'Mars/Olympus', expected an IANA name such as Europe/London"
+        );
+        assert_eq!(
+            error("DTSTART;X-SOURCE=term:20250101T120000\nRRULE:FREQ=DAILY"),
+            "unsupported parameter 'X-SOURCE=term', only VALUE and TZID are allowed"
+        );
+        assert_eq!(
+            error("RDATE:20250101\nRRULE:FREQ=DAILY"),
+            "unknown property 'RDATE', expected DTSTART, RRULE or EXDATE"
+        );
+    }
+}
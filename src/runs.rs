@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::storage_v2::{MenuSchedule, Storage};
+
+/// How an attempt to run a schedule occurrence turned out
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The schedule's preset was applied
+    Applied,
+    /// The presets of overlapping schedules were applied together
+    Merged,
+    /// An overlapping schedule kept the menu, so nothing changed
+    Skipped,
+    /// The occurrence overlapped another while overlaps are not allowed
+    Conflicted,
+    /// The occurrence ended while the scheduler was not running, and the misfire
+    /// policy passed over it
+    Missed,
+    /// The run stopped with an error, possibly after changing some items
+    Failed,
+}
+
+/// An entry in the append-only log of schedule runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduleRun {
+    pub id: Uuid,
+    pub schedule_id: Uuid,
+    /// When the occurrence was due to start
+    pub occurrence_start: DateTime<Utc>,
+    /// When the scheduler actually ran it
+    pub started_at: DateTime<Utc>,
+    /// The presets the menu was set from; empty if nothing was applied
+    pub preset_ids: Vec<Uuid>,
+    /// The new availability of every menu item the run changed
+    pub items_changed: BTreeMap<Uuid, bool>,
+    pub outcome: RunOutcome,
+    pub error: Option<String>,
+}
+
+impl ScheduleRun {
+    /// A run of the schedule's current occurrence starting at `started_at`, to be
+    /// filled in as it goes
+    pub fn start(schedule: &MenuSchedule, started_at: DateTime<Utc>) -> Self {
+        ScheduleRun {
+            id: Uuid::new_v4(),
+            schedule_id: schedule.id,
+            occurrence_start: schedule.start_time,
+            started_at,
+            preset_ids: Vec::new(),
+            items_changed: BTreeMap::new(),
+            outcome: RunOutcome::Failed,
+            error: None,
+        }
+    }
+}
+
+/// Append a finished run to the log.
+///
+/// Failures are logged rather than returned, so a full disk cannot stop the
+/// scheduler from changing the menu.
+pub fn record(storage: &dyn Storage, run: ScheduleRun) {
+    let (schedule_id, outcome) = (run.schedule_id, run.outcome);
+    if let Err(e) = storage.append_schedule_run(run) {
+        error!(
+            "Failed to record {:?} run of schedule {}: {}",
+            outcome, schedule_id, e
+        );
+    }
+}
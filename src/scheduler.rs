@@ -1,11 +1,29 @@
 use actix_web::web::Data;
-use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use chrono::{DateTime, Datelike, Days, Duration, Months, Utc};
+use chrono_tz::Tz;
 use log::{error, info, warn};
+use serde::Serialize;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use tokio::time::sleep;
-
-use crate::storage::{JsonStorage, MenuSchedule, ScheduleRecurrence, ScheduleStatus};
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::config::{ConflictPolicy, MisfirePolicy};
+use crate::history::{self, Change, RevisionAction, SCHEDULER_USER};
+use crate::leader::LeaderLock;
+use crate::migrations::DataKind;
+use crate::recurrence::RecurrenceSet;
+use crate::runs::{self, RunOutcome, ScheduleRun};
+use crate::storage_v2::{
+    ConflictDecision, ConflictOutcome, MenuItem, MenuPreset, MenuSchedule, PresetScope,
+    ScheduleRecurrence, ScheduleStatus, Storage, StorageError,
+};
+use crate::timezone;
+
+/// The longest the scheduler waits before retrying a failed occurrence
+const MAX_RETRY_DELAY_SECONDS: u64 = 24 * 60 * 60;
 
 /// A wrapper for MenuSchedule that implements Ord for use in BinaryHeap
 #[derive(Debug, Clone)]
@@ -35,8 +53,8 @@ impl Ord for ScheduledEvent {
     }
 }
 
-/// Check if a schedule conflicts with any existing schedules
-/// A conflict occurs if the time ranges overlap
+/// Check if a schedule's current occurrence conflicts with that of any existing
+/// schedule. A conflict occurs if the time ranges overlap
 pub fn has_schedule_conflict(
     schedule: &MenuSchedule,
     existing_schedules: &[MenuSchedule],
@@ -48,72 +66,636 @@ pub fn has_schedule_conflict(
         }
 
         // Check for time overlap
-        if schedule.start_time <= existing.end_time && schedule.end_time >= existing.start_time {
+        if overlaps(schedule, existing) {
             return Some(existing.clone());
         }
     }
     None
 }
 
+/// Whether the current occurrences of two schedules overlap
+fn overlaps(a: &MenuSchedule, b: &MenuSchedule) -> bool {
+    windows_overlap((a.start_time, a.end_time), (b.start_time, b.end_time))
+}
+
+/// Whether two occurrence windows overlap; windows that touch count, as a menu
+/// change on the boundary would otherwise race
+fn windows_overlap(a: Occurrence, b: Occurrence) -> bool {
+    a.0 <= b.1 && a.1 >= b.0
+}
+
+/// Whether the presets of two schedules can set the same menu items, so their
+/// occurrences compete for the menu when they overlap. A schedule whose preset
+/// is missing is taken to cover the whole menu
+pub fn shares_menu(
+    a: &MenuSchedule,
+    b: &MenuSchedule,
+    presets: &[MenuPreset],
+    items: &[MenuItem],
+) -> bool {
+    preset_scope(presets, a.preset_id).overlaps(&preset_scope(presets, b.preset_id), items)
+}
+
+fn preset_scope(presets: &[MenuPreset], preset_id: Uuid) -> PresetScope {
+    presets
+        .iter()
+        .find(|preset| preset.id == preset_id)
+        .map(|preset| preset.scope.clone())
+        .unwrap_or_default()
+}
+
+/// The start and end of one occurrence
+pub type Occurrence = (DateTime<Utc>, DateTime<Utc>);
+
+/// An occurrence of a schedule being checked that overlaps an occurrence of an
+/// existing schedule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduleClash {
+    pub schedule_id: Uuid,
+    pub schedule_name: String,
+    /// The occurrence of the schedule being checked
+    pub occurrence_start: DateTime<Utc>,
+    pub occurrence_end: DateTime<Utc>,
+    /// The occurrence of the existing schedule it overlaps
+    pub conflicting_start: DateTime<Utc>,
+    pub conflicting_end: DateTime<Utc>,
+}
+
+/// The occurrences of a schedule from its current one on that start before
+/// `until`, stopping at its series end and leaving out its exception dates
+pub fn occurrences_until(schedule: &MenuSchedule, until: DateTime<Utc>, tz: Tz) -> Vec<Occurrence> {
+    let length = schedule.end_time - schedule.start_time;
+    let in_range = |start: &DateTime<Utc>| {
+        *start < until && schedule.series_end.is_none_or(|end| *start <= end)
+    };
+    let starts: Vec<DateTime<Utc>> = match (&schedule.recurrence, schedule.rrule.as_deref()) {
+        (ScheduleRecurrence::Custom, None) => vec![schedule.start_time],
+        (ScheduleRecurrence::Custom, Some(rule)) => match RecurrenceSet::parse(rule) {
+            Ok(set) => set
+                .occurrences(schedule.series_start, tz)
+                .skip_while(|start| *start < schedule.start_time)
+                .take_while(in_range)
+                .collect(),
+            Err(_) => vec![schedule.start_time],
+        },
+        (recurrence, _) => std::iter::successors(Some(schedule.start_time), |start| {
+            next_fixed_occurrence(recurrence, schedule.series_start, *start, tz)
+        })
+        .take_while(in_range)
+        .collect(),
+    };
+    starts
+        .into_iter()
+        .filter(|start| !is_exception(schedule, *start, tz))
+        .map(|start| (start, start + length))
+        .collect()
+}
+
+/// Whether an occurrence starting at `start` falls on one of the schedule's
+/// exception dates, in local time
+pub fn is_exception(schedule: &MenuSchedule, start: DateTime<Utc>, tz: Tz) -> bool {
+    !schedule.exception_dates.is_empty()
+        && schedule
+            .exception_dates
+            .contains(&timezone::local(tz, start).date())
+}
+
+/// Every pair of overlapping occurrences between `schedule` and the schedules in
+/// `existing` that start before `until`, in the order of `existing`.
+///
+/// Recurring schedules are expanded occurrence by occurrence, so a daily
+/// breakfast and a Friday brunch clash on Fridays even when their next
+/// occurrences do not overlap. Ended schedules have no occurrences left.
+pub fn find_conflicts(
+    schedule: &MenuSchedule,
+    existing: &[MenuSchedule],
+    until: DateTime<Utc>,
+    tz: Tz,
+) -> Vec<ScheduleClash> {
+    let ours = occurrences_until(schedule, until, tz);
+    let mut clashes = Vec::new();
+    for other in existing {
+        if other.id == schedule.id || matches!(other.status, ScheduleStatus::Ended) {
+            continue;
+        }
+        let theirs = occurrences_until(other, until, tz);
+        // Both lists are in order and an occurrence ends before the next begins,
+        // so each of ours only needs the occurrences from the first that is not
+        // over by the time it starts
+        let mut first = 0;
+        for &occurrence in &ours {
+            while first < theirs.len() && theirs[first].1 < occurrence.0 {
+                first += 1;
+            }
+            for &conflicting in theirs[first..]
+                .iter()
+                .take_while(|conflicting| conflicting.0 <= occurrence.1)
+            {
+                if windows_overlap(occurrence, conflicting) {
+                    clashes.push(ScheduleClash {
+                        schedule_id: other.id,
+                        schedule_name: other.name.clone(),
+                        occurrence_start: occurrence.0,
+                        occurrence_end: occurrence.1,
+                        conflicting_start: conflicting.0,
+                        conflicting_end: conflicting.1,
+                    });
+                }
+            }
+        }
+    }
+    clashes
+}
+
+/// The schedules in `existing` with an occurrence that overlaps the current
+/// occurrence of `schedule`, found the way [`find_conflicts`] finds them when
+/// schedules are saved
+fn current_occurrence_conflicts(
+    schedule: &MenuSchedule,
+    existing: &[MenuSchedule],
+    tz: Tz,
+) -> Vec<MenuSchedule> {
+    // Occurrences that start right as this one ends touch it, so look just past it
+    let until = schedule.end_time + Duration::seconds(1);
+    let clashes: Vec<ScheduleClash> = find_conflicts(schedule, existing, until, tz)
+        .into_iter()
+        .filter(|clash| clash.occurrence_start == schedule.start_time)
+        .collect();
+    existing
+        .iter()
+        .filter(|other| clashes.iter().any(|clash| clash.schedule_id == other.id))
+        .cloned()
+        .collect()
+}
+
+/// Settle an occurrence of `schedule` that overlaps the occurrences in `running`.
+///
+/// Under `HighestPriority` and `MostSpecific` the schedule only takes over the menu
+/// if it outranks every running schedule; on a tie the schedule that started
+/// first keeps it.
+pub fn resolve_conflict(
+    schedule: &MenuSchedule,
+    running: &[MenuSchedule],
+    policy: ConflictPolicy,
+    now: DateTime<Utc>,
+) -> ConflictDecision {
+    let names = running
+        .iter()
+        .map(|other| format!("'{}' ({})", other.name, other.id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (outcome, reason) = match policy {
+        ConflictPolicy::Reject => (
+            ConflictOutcome::Rejected,
+            format!("Conflicts with schedule {}", names),
+        ),
+        ConflictPolicy::MergePresets => (
+            ConflictOutcome::Merged,
+            format!("Presets merged with schedule {}", names),
+        ),
+        ConflictPolicy::HighestPriority | ConflictPolicy::MostSpecific => {
+            let owner = menu_owner(running.iter().chain([schedule]), policy)
+                .expect("the schedule itself is a candidate");
+            let ranking = if policy == ConflictPolicy::HighestPriority {
+                "a higher priority"
+            } else {
+                "a more specific schedule"
+            };
+            if owner.id == schedule.id {
+                (
+                    ConflictOutcome::Applied,
+                    format!("Has {} than schedule {}", ranking, names),
+                )
+            } else {
+                (
+                    ConflictOutcome::Skipped,
+                    format!(
+                        "Schedule '{}' ({}) keeps the menu: it is not outranked by {}",
+                        owner.name, owner.id, ranking
+                    ),
+                )
+            }
+        }
+    };
+    ConflictDecision {
+        policy,
+        outcome,
+        overlapping: running.iter().map(|other| other.id).collect(),
+        reason,
+        decided_at: now,
+    }
+}
+
+/// The schedule whose preset sets the menu among overlapping ones: the highest
+/// ranked under `policy`, or the one that started first among equals
+fn menu_owner<'a>(
+    schedules: impl Iterator<Item = &'a MenuSchedule>,
+    policy: ConflictPolicy,
+) -> Option<&'a MenuSchedule> {
+    schedules.max_by(|a, b| {
+        rank(a, policy)
+            .cmp(&rank(b, policy))
+            .then(b.start_time.cmp(&a.start_time))
+    })
+}
+
+/// How strongly a schedule claims the menu under `policy`; higher wins
+fn rank(schedule: &MenuSchedule, policy: ConflictPolicy) -> (i64, i64) {
+    match policy {
+        ConflictPolicy::HighestPriority => (schedule.priority.into(), 0),
+        ConflictPolicy::MostSpecific => {
+            // A one-off beats a rule, which beats the fixed recurrences from the
+            // least to the most frequent; then the shorter occurrence wins
+            let recurrence = match schedule.recurrence {
+                ScheduleRecurrence::Custom if schedule.rrule.is_none() => 4,
+                ScheduleRecurrence::Custom => 3,
+                ScheduleRecurrence::Monthly => 2,
+                ScheduleRecurrence::Weekly => 1,
+                ScheduleRecurrence::Daily => 0,
+            };
+            let length = (schedule.end_time - schedule.start_time).num_seconds();
+            (recurrence, -length)
+        }
+        ConflictPolicy::Reject | ConflictPolicy::MergePresets => (0, 0),
+    }
+}
+
+/// The availability overlapping schedules give the menu items in their presets'
+/// scopes, by item. Each item is set by the schedules covering it under `policy`:
+/// available if any of their presets lists it. Items no schedule covers are left out
+fn combined_menu(
+    presets: &[MenuPreset],
+    items: &[MenuItem],
+    schedules: &[&MenuSchedule],
+    policy: ConflictPolicy,
+) -> BTreeMap<Uuid, bool> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let setters = item_setters(presets, item, schedules, policy);
+            let listed = setters.iter().any(|setter| {
+                presets.iter().any(|preset| {
+                    preset.id == setter.preset_id && preset.menu_item_ids.contains(&item.id)
+                })
+            });
+            (!setters.is_empty()).then_some((item.id, listed))
+        })
+        .collect()
+}
+
+/// The overlapping schedules that set `item` under `policy`, from those whose
+/// presets' scopes cover it
+fn item_setters<'a>(
+    presets: &[MenuPreset],
+    item: &MenuItem,
+    schedules: &[&'a MenuSchedule],
+    policy: ConflictPolicy,
+) -> Vec<&'a MenuSchedule> {
+    let covering: Vec<&MenuSchedule> = schedules
+        .iter()
+        .copied()
+        .filter(|schedule| preset_scope(presets, schedule.preset_id).contains(item))
+        .collect();
+    menu_setters(&covering, policy)
+}
+
+/// The overlapping schedules whose presets make up the menu under `policy`
+fn menu_setters<'a>(
+    schedules: &[&'a MenuSchedule],
+    policy: ConflictPolicy,
+) -> Vec<&'a MenuSchedule> {
+    if policy == ConflictPolicy::MergePresets {
+        schedules.to_vec()
+    } else {
+        menu_owner(schedules.iter().copied(), policy)
+            .into_iter()
+            .collect()
+    }
+}
+
+/// A stretch of time over which the scheduled menu stays the same
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MenuPeriod {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Schedules with an occurrence running, in the order the occurrences started
+    pub schedule_ids: Vec<Uuid>,
+    /// The presets the menu is set from; empty while no schedule is running
+    pub preset_ids: Vec<Uuid>,
+    /// The available menu items, in the order they were given
+    pub available_item_ids: Vec<Uuid>,
+}
+
+/// Work out the menu from `from` until `until` if the scheduler runs `schedules`
+/// as they stand, without touching storage.
+///
+/// Items start out with their current availability, or with the availability a
+/// running occurrence will put back. Each occurrence sets the items in its preset's
+/// scope, overlaps are settled item by item under `policy` as the scheduler settles
+/// them, and once no occurrence covers an item it goes back, or to the fallback
+/// preset of the schedule that last covered it. Only pending and active schedules run.
+pub fn simulate_menu(
+    items: &[MenuItem],
+    presets: &[MenuPreset],
+    schedules: &[MenuSchedule],
+    policy: ConflictPolicy,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    tz: Tz,
+) -> Vec<MenuPeriod> {
+    let find_preset = |preset_id: Uuid| presets.iter().find(|preset| preset.id == preset_id);
+
+    // The menu to return to once nothing is running; where running occurrences
+    // remember the same item, the one that started first saw it earlier
+    let mut baseline: HashSet<Uuid> = items
+        .iter()
+        .filter(|item| item.is_available)
+        .map(|item| item.id)
+        .collect();
+    let mut active: Vec<&MenuSchedule> = schedules
+        .iter()
+        .filter(|schedule| matches!(schedule.status, ScheduleStatus::Active))
+        .collect();
+    active.sort_by_key(|schedule| std::cmp::Reverse(schedule.start_time));
+    for (item_id, was_available) in active
+        .iter()
+        .filter_map(|schedule| schedule.availability_snapshot.as_ref())
+        .flatten()
+    {
+        if *was_available {
+            baseline.insert(*item_id);
+        } else {
+            baseline.remove(item_id);
+        }
+    }
+
+    // Every occurrence in range, as a copy of its schedule moved to that occurrence
+    let mut occurrences: Vec<MenuSchedule> = schedules
+        .iter()
+        .filter(|schedule| {
+            matches!(
+                schedule.status,
+                ScheduleStatus::Pending | ScheduleStatus::Active
+            )
+        })
+        .flat_map(|schedule| {
+            occurrences_until(schedule, until, tz)
+                .into_iter()
+                .filter(|(_, end)| *end > from)
+                .map(|(start, end)| MenuSchedule {
+                    start_time: start,
+                    end_time: end,
+                    ..schedule.clone()
+                })
+        })
+        .collect();
+    occurrences.sort_by_key(|occurrence| occurrence.start_time);
+
+    let mut bounds: Vec<DateTime<Utc>> = occurrences
+        .iter()
+        .flat_map(|occurrence| [occurrence.start_time, occurrence.end_time])
+        .filter(|time| *time > from && *time < until)
+        .chain([from, until])
+        .collect();
+    bounds.sort();
+    bounds.dedup();
+
+    let mut periods: Vec<MenuPeriod> = Vec::new();
+    for pair in bounds.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let running: Vec<&MenuSchedule> = occurrences
+            .iter()
+            .filter(|occurrence| occurrence.start_time <= start && start < occurrence.end_time)
+            .collect();
+        let menu = combined_menu(presets, items, &running, policy);
+        let setters: HashSet<Uuid> = items
+            .iter()
+            .flat_map(|item| item_setters(presets, item, &running, policy))
+            .map(|setter| setter.id)
+            .collect();
+        let mut preset_ids: Vec<Uuid> = Vec::new();
+        for setter in running
+            .iter()
+            .filter(|occurrence| setters.contains(&occurrence.id))
+        {
+            if !preset_ids.contains(&setter.preset_id) {
+                preset_ids.push(setter.preset_id);
+            }
+        }
+        let period = MenuPeriod {
+            start,
+            end,
+            schedule_ids: running.iter().map(|occurrence| occurrence.id).collect(),
+            preset_ids,
+            available_item_ids: items
+                .iter()
+                .filter(|item| {
+                    menu.get(&item.id)
+                        .copied()
+                        .unwrap_or_else(|| baseline.contains(&item.id))
+                })
+                .map(|item| item.id)
+                .collect(),
+        };
+        match periods.last_mut() {
+            Some(last)
+                if last.schedule_ids == period.schedule_ids
+                    && last.preset_ids == period.preset_ids
+                    && last.available_item_ids == period.available_item_ids =>
+            {
+                last.end = end;
+            }
+            _ => periods.push(period),
+        }
+
+        // An occurrence that ends with none it competes with still running hands
+        // the items in its fallback preset's scope over to that preset
+        let mut ended: Vec<&MenuSchedule> = running
+            .iter()
+            .copied()
+            .filter(|occurrence| occurrence.end_time <= end)
+            .collect();
+        ended.sort_by_key(|occurrence| (occurrence.end_time, occurrence.start_time));
+        for occurrence in ended {
+            let handed_over = running.iter().any(|other| {
+                other.end_time > end && shares_menu(occurrence, other, presets, items)
+            });
+            if handed_over {
+                continue;
+            }
+            let Some(fallback) = occurrence.fallback_preset_id.and_then(find_preset) else {
+                continue;
+            };
+            for item in items.iter().filter(|item| fallback.scope.contains(item)) {
+                if fallback.menu_item_ids.contains(&item.id) {
+                    baseline.insert(item.id);
+                } else {
+                    baseline.remove(&item.id);
+                }
+            }
+        }
+    }
+    periods
+}
+
 /// Starts the scheduler service that runs in the background
-/// checking for due menu schedules and executing them
-pub async fn start_scheduler(storage: Data<JsonStorage>) {
-    info!("Starting scheduler service");
+/// checking for due menu schedules and executing them.
+///
+/// Every schedule write, whether through the API or an edit on disk, makes the
+/// scheduler rebuild its queue straight away.
+///
+/// Schedules only run while this process holds the lease in `lock`, which is
+/// renewed every heartbeat; otherwise it stands by, trying to take the lease over.
+/// Heartbeats sleep on `heartbeat`, which must keep the same time as `clock`.
+pub async fn start_scheduler(
+    storage: Data<dyn Storage>,
+    clock: Data<dyn Clock>,
+    heartbeat: Data<dyn Clock>,
+    lock: Data<LeaderLock>,
+) {
+    info!(
+        "Starting scheduler service as instance {}",
+        lock.instance_id()
+    );
 
     // Spawn the scheduler task as a background process
     tokio::spawn(async move {
-        run_scheduler(storage).await;
+        run_while_leader(storage, clock, heartbeat, lock).await;
     });
 }
 
-/// Main scheduler loop that efficiently waits for the next schedule to execute
-async fn run_scheduler(storage: Data<JsonStorage>) {
+/// Renew or take the lease every heartbeat, running the scheduler while it is held.
+///
+/// A scheduler that loses the lease is aborted. It only yields while waiting for
+/// its next event or a change, so an occurrence is never left half applied. A
+/// heartbeat that cannot renew the lease, because another instance is checking it
+/// or the lock file cannot be written, keeps the scheduler running until the
+/// lease it last wrote runs out.
+async fn run_while_leader(
+    storage: Data<dyn Storage>,
+    clock: Data<dyn Clock>,
+    heartbeat: Data<dyn Clock>,
+    lock: Data<LeaderLock>,
+) {
+    let mut scheduler: Option<JoinHandle<()>> = None;
+    loop {
+        let now = heartbeat.now();
+        let leading = lock.try_acquire(now).unwrap_or_else(|e| {
+            error!("Failed to update the scheduler lease: {}", e);
+            lock.is_leader()
+        });
+        match scheduler.take() {
+            None if leading => {
+                info!("Took the scheduler lease, running schedules");
+                scheduler = Some(tokio::spawn(run_scheduler(
+                    storage.clone(),
+                    clock.clone(),
+                    lock.clone(),
+                )));
+            }
+            Some(running) if !leading => {
+                warn!("Lost the scheduler lease, standing by");
+                running.abort();
+            }
+            running => scheduler = running,
+        }
+        heartbeat.sleep_until(now + lock.heartbeat_interval()).await;
+    }
+}
+
+/// Main scheduler loop that sleeps until the next schedule is due or schedules change.
+///
+/// Times come from `clock`, and each due event is handled as of the time it was
+/// picked up, so a test clock can step through days of schedules at once.
+///
+/// A due event is only handled while `lock` holds an unexpired lease, as its
+/// heartbeat may not have caught up yet with a lease lost to another instance.
+/// Handling an event does not yield, so that check covers every write it makes.
+async fn run_scheduler(storage: Data<dyn Storage>, clock: Data<dyn Clock>, lock: Data<LeaderLock>) {
+    let mut changes = storage.subscribe_schedule_changes();
+
     // Load and sort all pending and active schedules
-    let mut events = load_scheduled_events(&storage).await;
+    let mut events = load_scheduled_events(&storage, &mut changes).await;
 
     loop {
         // Get the next schedule to execute
         if let Some(event) = events.peek() {
-            let now = Utc::now();
-
-            if event.execution_time <= now {
+            let now = clock.now();
+
+            if event.execution_time <= now && !lock.holds_lease(now) {
+                // Leave the event to whoever holds the lease, until the heartbeat
+                // renews it or stops this scheduler
+                warn!("Scheduler lease ran out, holding back due schedules");
+                tokio::select! {
+                    _ = lock.wait_for_lease(now) => {}
+                    changed = changes.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        events = load_scheduled_events(&storage, &mut changes).await;
+                    }
+                }
+            } else if event.execution_time <= now {
                 // Event is due to execute now
                 let event = events.pop().unwrap();
 
                 if matches!(event.schedule.status, ScheduleStatus::Active) {
                     // Check if Active schedule has ended
-                    handle_ended_active_schedule(&storage, &event.schedule).await;
+                    handle_ended_active_schedule(&storage, &event.schedule, now).await;
+                } else if skip_exception_date(&storage, &event.schedule, now) {
+                    // Nothing runs on one of the schedule's exception dates
+                } else if event.schedule.end_time <= now {
+                    // The whole occurrence went by while the scheduler was not running
+                    let policy = storage
+                        .get_app_settings()
+                        .map(|settings| settings.menu.misfire_policy)
+                        .unwrap_or_default();
+                    if let Err(e) =
+                        catch_up_missed_occurrences(&storage, event.schedule, policy, now).await
+                    {
+                        error!("Failed to catch up on missed occurrences: {}", e);
+                    }
                 } else {
                     // Execute the pending schedule
-                    if let Err(e) = execute_schedule(&storage, event.schedule).await {
+                    if let Err(e) = execute_schedule(&storage, event.schedule, now).await {
                         error!("Failed to execute schedule: {}", e);
                     }
                 }
 
                 // Reload events to account for any recurring schedules that may have been updated
-                events = load_scheduled_events(&storage).await;
+                events = load_scheduled_events(&storage, &mut changes).await;
             } else {
-                // Calculate sleep duration to the next event with millisecond precision
-                let sleep_duration = (event.execution_time - now)
-                    .to_std()
-                    .unwrap_or_else(|_| std::time::Duration::from_secs(0));
-
-                // Sleep until the next event is due
-                sleep(sleep_duration).await;
+                // Sleep until the next event is due, or until schedules change
+                tokio::select! {
+                    _ = clock.sleep_until(event.execution_time) => {}
+                    changed = changes.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        events = load_scheduled_events(&storage, &mut changes).await;
+                    }
+                }
             }
         } else {
-            // No events scheduled, wait for a bit before checking again
-            // This could happen if all schedules are ended or deleted
-            sleep(std::time::Duration::from_secs(1)).await;
-
-            // Check again for new events
-            events = load_scheduled_events(&storage).await;
+            // No events scheduled, e.g. all schedules are ended or deleted,
+            // so wait until one is written
+            if changes.changed().await.is_err() {
+                break;
+            }
+            events = load_scheduled_events(&storage, &mut changes).await;
         }
     }
+    warn!("Storage closed its schedule change channel, stopping scheduler");
 }
 
 /// Load all pending and active schedules into a priority queue
-async fn load_scheduled_events(storage: &Data<JsonStorage>) -> BinaryHeap<ScheduledEvent> {
+async fn load_scheduled_events(
+    storage: &Data<dyn Storage>,
+    changes: &mut watch::Receiver<()>,
+) -> BinaryHeap<ScheduledEvent> {
+    // Writes made from here on, including the scheduler's own, trigger another reload
+    changes.borrow_and_update();
+
     let mut events = BinaryHeap::new();
     let schedules = match storage.get_menu_schedules() {
         Ok(schedules) => schedules,
@@ -123,31 +705,34 @@ async fn load_scheduled_events(storage: &Data<JsonStorage>) -> BinaryHeap<Schedu
         }
     };
 
-    let now = Utc::now();
-
     for schedule in schedules {
         match schedule.status {
             ScheduleStatus::Pending => {
-                if schedule.start_time >= now {
-                    // Schedule is pending and will run in the future
-                    events.push(ScheduledEvent {
-                        schedule: schedule.clone(),
-                        execution_time: schedule.start_time,
-                    });
-                } else {
-                    // Schedule is pending but already past due - execute immediately
-                    // We'll execute these when we return to the main loop
-                    events.push(ScheduledEvent {
-                        schedule: schedule.clone(),
-                        execution_time: now, // Mark as executable immediately
+                // Schedule runs at its start, or once its retry backoff is over.
+                // One already past due is queued by when it was due, so overdue
+                // occurrences of every schedule are caught up on in order as soon
+                // as we return to the main loop
+                let due = schedule
+                    .next_retry_at
+                    .map_or(schedule.start_time, |retry_at| {
+                        retry_at.max(schedule.start_time)
                     });
-                }
+                events.push(ScheduledEvent {
+                    schedule: schedule.clone(),
+                    execution_time: due,
+                });
             }
             ScheduleStatus::Active => {
-                // Active schedules need to be checked for when they end
+                // Active schedules need to be checked for when they end, or once
+                // the retry backoff of a failed handover is over
+                let due = schedule
+                    .next_retry_at
+                    .map_or(schedule.end_time, |retry_at| {
+                        retry_at.max(schedule.end_time)
+                    });
                 events.push(ScheduledEvent {
                     schedule: schedule.clone(),
-                    execution_time: schedule.end_time,
+                    execution_time: due,
                 });
             }
             _ => {
@@ -159,32 +744,206 @@ async fn load_scheduled_events(storage: &Data<JsonStorage>) -> BinaryHeap<Schedu
     events
 }
 
-/// Execute a pending schedule by updating menu items based on the associated preset
+/// Execute a pending schedule by updating menu items based on the associated preset.
+///
+/// Like API writes, every update expects the version that was read, so a schedule
+/// or item an admin changed in the meantime fails with a version conflict instead
+/// of being overwritten. That and other transient storage errors are retried with
+/// backoff; anything else marks the schedule `Failed` (see [`settle_failure`]).
+/// Every attempt is added to the run log, whether or not it succeeds.
 async fn execute_schedule(
-    storage: &Data<JsonStorage>,
+    storage: &Data<dyn Storage>,
+    schedule: MenuSchedule,
+    now: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let schedule_id = schedule.id;
+    let mut run = ScheduleRun::start(&schedule, now);
+    let result = run_occurrence(storage, schedule, &mut run, now);
+    if let Err(e) = &result {
+        run.outcome = RunOutcome::Failed;
+        run.error = Some(e.to_string());
+        let transient = e
+            .downcast_ref::<StorageError>()
+            .is_some_and(StorageError::is_transient);
+        if let Err(settle_err) = settle_failure(
+            storage,
+            schedule_id,
+            &e.to_string(),
+            transient,
+            ScheduleStatus::Pending,
+            now,
+        ) {
+            error!(
+                "Failed to record the failure of schedule {}: {}",
+                schedule_id, settle_err
+            );
+        }
+    }
+    runs::record(storage.get_ref(), run);
+    result
+}
+
+/// Move a schedule whose occurrence failed on through the state machine.
+///
+/// After a transient error it goes back to `retry_as` with `next_retry_at` set,
+/// waiting `schedule_retry_delay_seconds` and twice as long for each retry after,
+/// until `schedule_retry_limit` retries have failed. Then, or straight away for
+/// any other error, it is `Failed` with the reason in `error_message` until an
+/// admin retries or acknowledges it.
+fn settle_failure(
+    storage: &Data<dyn Storage>,
+    schedule_id: Uuid,
+    error: &str,
+    transient: bool,
+    retry_as: ScheduleStatus,
+    now: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    let settings = storage.get_app_settings()?.menu;
+    // The run may have got as far as making the schedule Active
+    let Some(schedule) = storage.get_menu_schedule_by_id(schedule_id)? else {
+        return Ok(());
+    };
+    let mut settled = schedule.clone();
+    settled.updated_at = now;
+    if transient && schedule.retry_count < settings.schedule_retry_limit {
+        settled.retry_count += 1;
+        let retry_at =
+            now + retry_delay(settings.schedule_retry_delay_seconds, settled.retry_count);
+        settled.status = retry_as;
+        settled.next_retry_at = Some(retry_at);
+        settled.error_message = Some(format!(
+            "Attempt {} failed, retrying at {}: {}",
+            settled.retry_count, retry_at, error
+        ));
+        warn!(
+            "Schedule {} ({}) failed, retry {} of {} at {}: {}",
+            schedule.name,
+            schedule.id,
+            settled.retry_count,
+            settings.schedule_retry_limit,
+            retry_at,
+            error
+        );
+    } else {
+        settled.status = ScheduleStatus::Failed;
+        settled.next_retry_at = None;
+        settled.error_message = Some(if transient {
+            format!("Gave up after {} retries: {}", schedule.retry_count, error)
+        } else {
+            error.to_string()
+        });
+        error!(
+            "Schedule {} ({}) failed: {}",
+            schedule.name, schedule.id, error
+        );
+    }
+    settled.version =
+        storage.update_menu_schedule(schedule.id, settled.clone(), schedule.version)?;
+    record_schedule_change(storage.get_ref(), &schedule, &settled, now)?;
+    Ok(())
+}
+
+/// How long to wait before retry number `attempt`, counting from 1, which is
+/// never more than a day
+fn retry_delay(base_seconds: u64, attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    let seconds = base_seconds
+        .saturating_mul(factor)
+        .min(MAX_RETRY_DELAY_SECONDS);
+    Duration::seconds(seconds as i64)
+}
+
+/// Put a `Failed` schedule back in the queue, to run its occurrence again at once
+/// with a fresh set of retries
+pub fn retry_schedule(schedule: &mut MenuSchedule, now: DateTime<Utc>) {
+    schedule.status = ScheduleStatus::Pending;
+    schedule.retry_count = 0;
+    schedule.next_retry_at = None;
+    schedule.error_message = None;
+    schedule.updated_at = now;
+}
+
+/// Give up on the occurrence of a `Failed` schedule: availability it had already
+/// changed is put back, and the schedule moves on to its next occurrence or ends
+pub fn acknowledge_failure(
+    storage: &Data<dyn Storage>,
+    schedule: &mut MenuSchedule,
+    tz: Tz,
+    now: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(snapshot) = &schedule.availability_snapshot {
+        set_availability(storage.get_ref(), storage.get_menu_items()?, now, |item| {
+            snapshot.get(&item.id).copied()
+        })?;
+    }
+    advance_to_next_occurrence(schedule, now, tz);
+    Ok(())
+}
+
+/// Apply a pending occurrence to the menu, noting what happened in `run`
+fn run_occurrence(
+    storage: &Data<dyn Storage>,
     mut schedule: MenuSchedule,
+    run: &mut ScheduleRun,
+    now: DateTime<Utc>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Get all schedules to check for conflicts
-    let all_schedules = storage.get_menu_schedules()?;
+    // Get the presets and menu items, and the live schedules whose presets could
+    // set the same items, to check for conflicts. Ended, failed and conflicted
+    // schedules keep their last window but no longer touch the menu, and paused
+    // ones have no effect at all until they are resumed
+    let presets = storage.get_menu_presets()?;
+    let preset_found = presets.iter().any(|p| p.id == schedule.preset_id);
+    let menu_items = storage.get_menu_items()?;
+    let competing: Vec<MenuSchedule> = storage
+        .get_menu_schedules()?
+        .into_iter()
+        .filter(|other| {
+            matches!(
+                other.status,
+                ScheduleStatus::Pending | ScheduleStatus::Active
+            ) && shares_menu(&schedule, other, &presets, &menu_items)
+        })
+        .collect();
+    let policy = storage
+        .get_validation_rules()?
+        .menu_schedules
+        .effective_conflict_policy();
 
     // Check for conflicts before executing
-    if let Some(conflicting_schedule) = has_schedule_conflict(&schedule, &all_schedules) {
+    let conflicting = if policy == ConflictPolicy::Reject {
+        let tz = storage.get_app_settings()?.localization.tz();
+        current_occurrence_conflicts(&schedule, &competing, tz)
+    } else {
+        Vec::new()
+    };
+    if let Some(conflicting_schedule) = conflicting.first() {
         warn!(
             "Schedule {} ({}) conflicts with {} ({}), skipping execution",
             schedule.name, schedule.id, conflicting_schedule.name, conflicting_schedule.id
         );
         // Update schedule status to Conflicted
+        let decision = resolve_conflict(&schedule, &conflicting, policy, now);
         let mut conflicted_schedule = schedule.clone();
         conflicted_schedule.status = ScheduleStatus::Conflicted;
-        conflicted_schedule.error_message = Some(format!(
-            "Conflicts with schedule '{}' ({})",
-            conflicting_schedule.name, conflicting_schedule.id
-        ));
-        if let Err(update_err) = storage.update_menu_schedule(schedule.id, conflicted_schedule) {
-            error!(
+        conflicted_schedule.error_message = Some(decision.reason.clone());
+        conflicted_schedule.conflict_decision = Some(decision);
+        run.outcome = RunOutcome::Conflicted;
+        run.error = conflicted_schedule.error_message.clone();
+        match storage.update_menu_schedule(
+            schedule.id,
+            conflicted_schedule.clone(),
+            schedule.version,
+        ) {
+            Ok(version) => {
+                conflicted_schedule.version = version;
+                // A failure is logged, with no request to report it to
+                let _ =
+                    record_schedule_change(storage.get_ref(), &schedule, &conflicted_schedule, now);
+            }
+            Err(update_err) => error!(
                 "Failed to update schedule status to Conflicted: {}",
                 update_err
-            );
+            ),
         }
         return Ok(());
     }
@@ -194,82 +953,97 @@ async fn execute_schedule(
         schedule.name, schedule.id
     );
 
-    // Set status to Active during execution
-    schedule.status = ScheduleStatus::Active;
-    schedule.updated_at = Utc::now();
-    storage.update_menu_schedule(schedule.id, schedule.clone())?;
-
-    // Get the associated preset
-    let presets = storage.get_menu_presets()?;
-    let preset = presets
+    // Settle overlaps with occurrences that are already running
+    let running: Vec<MenuSchedule> = competing
         .into_iter()
-        .find(|p| p.id == schedule.preset_id)
-        .ok_or_else(|| {
-            format!(
-                "Preset with id {} not found for schedule {}",
-                schedule.preset_id, schedule.id
-            )
-        })?;
-
-    // Get all menu items
-    let menu_items = storage.get_menu_items()?;
-
-    // Update menu items based on the preset
-    // Set is_available = true for items in the preset
-    // Set is_available = false for items not in the preset
-    for mut item in menu_items {
-        item.is_available = preset.menu_item_ids.contains(&item.id);
-        storage.update_menu_item(item.id, item)?;
+        .filter(|other| {
+            other.id != schedule.id
+                && matches!(other.status, ScheduleStatus::Active)
+                && overlaps(&schedule, other)
+        })
+        .collect();
+    let decision =
+        (!running.is_empty()).then(|| resolve_conflict(&schedule, &running, policy, now));
+    if let Some(decision) = &decision {
+        info!(
+            "Schedule {} ({}) overlaps running schedules: {:?}, {}",
+            schedule.name, schedule.id, decision.outcome, decision.reason
+        );
     }
 
-    // Update schedule status based on recurrence and end time
-    let now = Utc::now();
-
-    // Check if schedule has ended
-    if schedule.end_time <= now {
-        // Schedule has ended, mark as ended
-        schedule.status = ScheduleStatus::Ended;
-        schedule.updated_at = now;
-        schedule.error_message = None;
-    } else {
-        // Schedule is still active, update based on recurrence
-        match schedule.recurrence {
-            ScheduleRecurrence::Daily
-            | ScheduleRecurrence::Weekly
-            | ScheduleRecurrence::Monthly => {
-                // For recurring schedules, calculate next occurrence and set status to Pending
-                if let Some(next_start) = calculate_next_occurrence(&schedule, now) {
-                    // Check if next occurrence is before or at end time
-                    if next_start <= schedule.end_time {
-                        schedule.start_time = next_start;
-                        schedule.status = ScheduleStatus::Pending;
-                        schedule.updated_at = now;
-                        schedule.error_message = None; // Clear any previous error
-                    } else {
-                        // Next occurrence would be after end time, mark as ended
-                        schedule.status = ScheduleStatus::Ended;
-                        schedule.updated_at = now;
-                        schedule.error_message =
-                            Some("Next occurrence is after schedule end time".to_string());
-                    }
-                } else {
-                    // If we can't calculate next occurrence, mark as ended
-                    schedule.status = ScheduleStatus::Ended;
-                    schedule.updated_at = now;
-                    schedule.error_message = Some("Cannot calculate next occurrence".to_string());
-                }
-            }
-            ScheduleRecurrence::Custom => {
-                // For custom recurrence, mark as ended after execution
-                schedule.status = ScheduleStatus::Ended;
-                schedule.updated_at = now;
-                schedule.error_message = None;
-            }
+    // Work out the menu this occurrence calls for; a skipped one leaves it alone
+    let applying: Vec<&MenuSchedule> = match decision.as_ref().map(|decision| decision.outcome) {
+        _ if !preset_found => Vec::new(),
+        Some(ConflictOutcome::Skipped | ConflictOutcome::Rejected) => {
+            run.outcome = RunOutcome::Skipped;
+            Vec::new()
+        }
+        Some(ConflictOutcome::Merged) => {
+            run.outcome = RunOutcome::Merged;
+            running.iter().chain([&schedule]).collect()
         }
+        Some(ConflictOutcome::Applied) | None => {
+            run.outcome = RunOutcome::Applied;
+            vec![&schedule]
+        }
+    };
+    for applied in &applying {
+        if !run.preset_ids.contains(&applied.preset_id) {
+            run.preset_ids.push(applied.preset_id);
+        }
+    }
+    let menu =
+        (!applying.is_empty()).then(|| combined_menu(&presets, &menu_items, &applying, policy));
+
+    if !preset_found {
+        return Err(format!(
+            "Preset with id {} not found for schedule {}",
+            schedule.preset_id, schedule.id
+        )
+        .into());
     }
 
-    // Update the schedule in storage
-    storage.update_menu_schedule(schedule.id, schedule.clone())?;
+    // Set status to Active during execution, remembering the availability the
+    // preset is about to change so it can be put back when the occurrence ends.
+    // An earlier attempt that failed part way saw some items before it changed them
+    let pending_schedule = schedule.clone();
+    schedule.status = ScheduleStatus::Active;
+    schedule.conflict_decision = decision;
+    schedule.availability_snapshot = menu.as_ref().map(|menu| {
+        let mut snapshot: BTreeMap<Uuid, bool> = menu_items
+            .iter()
+            .filter(|item| {
+                menu.get(&item.id)
+                    .is_some_and(|&available| available != item.is_available)
+            })
+            .map(|item| (item.id, item.is_available))
+            .collect();
+        snapshot.extend(
+            pending_schedule
+                .availability_snapshot
+                .clone()
+                .unwrap_or_default(),
+        );
+        snapshot
+    });
+    schedule.retry_count = 0;
+    schedule.next_retry_at = None;
+    schedule.error_message = None;
+    schedule.updated_at = now;
+    schedule.version =
+        storage.update_menu_schedule(schedule.id, schedule.clone(), schedule.version)?;
+    record_schedule_change(storage.get_ref(), &pending_schedule, &schedule, now)?;
+
+    // Set is_available for the items in the presets' scopes, leaving the rest alone
+    if let Some(menu) = menu {
+        let changed = set_availability(storage.get_ref(), menu_items, now, |item| {
+            menu.get(&item.id).copied()
+        })?;
+        run.items_changed = changed
+            .into_iter()
+            .map(|(item_id, was_available)| (item_id, !was_available))
+            .collect();
+    }
 
     info!(
         "Successfully executed pending schedule: {} ({})",
@@ -278,119 +1052,618 @@ async fn execute_schedule(
     Ok(())
 }
 
-/// Update an active schedule to ended status
-async fn handle_ended_active_schedule(storage: &Data<JsonStorage>, schedule: &MenuSchedule) {
+/// Move a pending schedule whose occurrence falls on one of its exception dates on
+/// to its next occurrence, such as after an admin added the date. Returns whether
+/// it did
+fn skip_exception_date(
+    storage: &Data<dyn Storage>,
+    schedule: &MenuSchedule,
+    now: DateTime<Utc>,
+) -> bool {
+    if schedule.exception_dates.is_empty() {
+        return false;
+    }
+    let tz = match storage.get_app_settings() {
+        Ok(settings) => settings.localization.tz(),
+        Err(e) => {
+            error!(
+                "Failed to read settings for schedule {}: {}",
+                schedule.id, e
+            );
+            return false;
+        }
+    };
+    if !is_exception(schedule, schedule.start_time, tz) {
+        return false;
+    }
+
+    let mut next_schedule = schedule.clone();
+    skip_exception_dates(&mut next_schedule, tz, now);
     info!(
-        "Active schedule {} has ended, setting to Ended",
-        schedule.id
+        "Skipping occurrence of schedule {} on an exception date, setting to {:?}",
+        schedule.id, next_schedule.status
     );
-    let mut ended_schedule = schedule.clone();
-    ended_schedule.status = ScheduleStatus::Ended;
-    ended_schedule.updated_at = Utc::now();
-    ended_schedule.error_message = None;
-    if let Err(update_err) = storage.update_menu_schedule(schedule.id, ended_schedule) {
-        error!("Failed to update active schedule to Ended: {}", update_err);
+    match storage.update_menu_schedule(schedule.id, next_schedule.clone(), schedule.version) {
+        Ok(version) => {
+            next_schedule.version = version;
+            // A failure is logged, with no request to report it to
+            let _ = record_schedule_change(storage.get_ref(), schedule, &next_schedule, now);
+        }
+        Err(update_err) => error!(
+            "Failed to move schedule {} past its exception date: {}",
+            schedule.id, update_err
+        ),
     }
+    true
 }
 
-/// Calculate the next occurrence of a recurring schedule
-fn calculate_next_occurrence(
-    schedule: &MenuSchedule,
-    _now: chrono::DateTime<Utc>,
-) -> Option<chrono::DateTime<Utc>> {
-    match schedule.recurrence {
-        ScheduleRecurrence::Daily => {
-            // Add one day
-            Some(schedule.start_time + ChronoDuration::days(1))
-        }
-        ScheduleRecurrence::Weekly => {
-            // Add one week
-            Some(schedule.start_time + ChronoDuration::weeks(1))
-        }
-        ScheduleRecurrence::Monthly => {
-            // For monthly, we add one month
-            schedule
-                .start_time
-                .date_naive()
-                .checked_add_months(chrono::Months::new(1))
-                .map(|next_month| next_month.and_time(schedule.start_time.time()).and_utc())
-        }
-        ScheduleRecurrence::Custom => None, // Custom recurrence not implemented yet
+/// Move a pending schedule on from an occurrence on one of its exception dates
+/// to the next one that is not, or end it when the series is over
+pub fn skip_exception_dates(schedule: &mut MenuSchedule, tz: Tz, now: DateTime<Utc>) {
+    if schedule.status == ScheduleStatus::Pending && is_exception(schedule, schedule.start_time, tz)
+    {
+        advance_to_next_occurrence(schedule, now, tz);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Duration as ChronoDuration;
-    use uuid::Uuid;
+/// Stop a schedule from running until it is resumed. A running occurrence ends
+/// there and then, handing the menu over or back as it would at its end time
+pub fn pause_schedule(
+    storage: &Data<dyn Storage>,
+    schedule: &mut MenuSchedule,
+    now: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if schedule.status == ScheduleStatus::Active {
+        hand_over_menu(storage.get_ref(), schedule, now)?;
+        schedule.availability_snapshot = None;
+    }
+    schedule.status = ScheduleStatus::Paused;
+    schedule.retry_count = 0;
+    schedule.next_retry_at = None;
+    schedule.updated_at = now;
+    Ok(())
+}
 
-    #[test]
-    fn test_has_schedule_conflict_no_conflict() {
-        let now = Utc::now();
-        let schedule1 = MenuSchedule {
-            id: uuid::Uuid::new_v4(),
-            preset_id: uuid::Uuid::new_v4(),
-            name: "Schedule 1".to_string(),
-            description: "Test schedule 1".to_string(),
-            start_time: now,
-            end_time: now + ChronoDuration::hours(1),
-            recurrence: ScheduleRecurrence::Daily,
-            status: ScheduleStatus::Pending,
-            error_message: None,
-            created_at: now,
-            updated_at: now,
-        };
+/// Let a paused schedule run again from its next occurrence that has not ended.
+/// Occurrences that went by while it was paused are not caught up on
+pub fn resume_schedule(schedule: &mut MenuSchedule, tz: Tz, now: DateTime<Utc>) {
+    schedule.status = ScheduleStatus::Pending;
+    schedule.error_message = None;
+    schedule.updated_at = now;
+    while schedule.status == ScheduleStatus::Pending && schedule.end_time <= now {
+        advance_to_next_occurrence(schedule, now, tz);
+    }
+    skip_exception_dates(schedule, tz, now);
+}
 
-        let schedule2 = MenuSchedule {
-            id: uuid::Uuid::new_v4(),
-            preset_id: uuid::Uuid::new_v4(),
-            name: "Schedule 2".to_string(),
-            description: "Test schedule 2".to_string(),
-            start_time: now + ChronoDuration::hours(2),
-            end_time: now + ChronoDuration::hours(3),
-            recurrence: ScheduleRecurrence::Daily,
-            status: ScheduleStatus::Pending,
-            error_message: None,
-            created_at: now,
-            updated_at: now,
-        };
+/// Catch up on a pending schedule whose occurrence ended while the scheduler was
+/// not running, such as during an outage.
+///
+/// The missed occurrences the policy passes over are logged as `Missed` runs and
+/// the schedule jumps past them. Under `All` only the earliest runs here; the
+/// schedule then moves on to the next, which is queued by when it was due, so
+/// the missed occurrences of every schedule run in order.
+async fn catch_up_missed_occurrences(
+    storage: &Data<dyn Storage>,
+    schedule: MenuSchedule,
+    policy: MisfirePolicy,
+    now: DateTime<Utc>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tz = storage.get_app_settings()?.localization.tz();
+    let missed: Vec<Occurrence> = occurrences_until(&schedule, now, tz)
+        .into_iter()
+        .take_while(|(_, end)| *end <= now)
+        .collect();
+    let Some(&(latest_start, latest_end)) = missed.last() else {
+        return execute_schedule(storage, schedule, now).await;
+    };
+    let skipped = match policy {
+        MisfirePolicy::All => 0,
+        MisfirePolicy::Latest => missed.len() - 1,
+        MisfirePolicy::Skip => missed.len(),
+    };
 
-        let existing_schedules = vec![schedule2];
-        let result = has_schedule_conflict(&schedule1, &existing_schedules);
+    let mut next_schedule = schedule.clone();
+    if skipped > 0 {
+        warn!(
+            "Skipping {} missed occurrence(s) of schedule {} ({})",
+            skipped, schedule.name, schedule.id
+        );
+        for &(start, _) in &missed[..skipped] {
+            let mut run = ScheduleRun::start(&schedule, now);
+            run.occurrence_start = start;
+            run.outcome = RunOutcome::Missed;
+            runs::record(storage.get_ref(), run);
+        }
 
-        assert!(result.is_none());
+        // Jump to the latest missed occurrence, and past it when skipping that too
+        next_schedule.start_time = latest_start;
+        next_schedule.end_time = latest_end;
+        if policy == MisfirePolicy::Skip {
+            advance_to_next_occurrence(&mut next_schedule, now, tz);
+        }
+        next_schedule.updated_at = now;
+        next_schedule.version =
+            storage.update_menu_schedule(schedule.id, next_schedule.clone(), schedule.version)?;
+        record_schedule_change(storage.get_ref(), &schedule, &next_schedule, now)?;
+    }
+    if policy == MisfirePolicy::Skip {
+        return Ok(());
     }
 
-    #[test]
-    fn test_has_schedule_conflict_with_conflict() {
-        let now = Utc::now();
-        let schedule1 = MenuSchedule {
-            id: uuid::Uuid::new_v4(),
-            preset_id: uuid::Uuid::new_v4(),
-            name: "Schedule 1".to_string(),
-            description: "Test schedule 1".to_string(),
-            start_time: now,
-            end_time: now + ChronoDuration::hours(2),
-            recurrence: ScheduleRecurrence::Daily,
+    // Run the occurrence and close it straight away, as its window is over
+    info!(
+        "Running missed occurrence of schedule {} ({}) due at {}",
+        next_schedule.name, next_schedule.id, next_schedule.start_time
+    );
+    execute_schedule(storage, next_schedule, now).await?;
+    if let Some(active) = storage.get_menu_schedule_by_id(schedule.id)?
+        && matches!(active.status, ScheduleStatus::Active)
+    {
+        handle_ended_active_schedule(storage, &active, now).await;
+    }
+    Ok(())
+}
+
+/// Close an active occurrence at the end of its window, and queue the next one
+/// or end the series.
+///
+/// An occurrence whose menu cannot be handed over stays active, and the handover
+/// is retried or given up on like a failed run (see [`settle_failure`]).
+async fn handle_ended_active_schedule(
+    storage: &Data<dyn Storage>,
+    schedule: &MenuSchedule,
+    now: DateTime<Utc>,
+) {
+    let tz = match storage.get_app_settings() {
+        Ok(settings) => settings.localization.tz(),
+        Err(e) => {
+            error!(
+                "Failed to read settings for schedule {}: {}",
+                schedule.id, e
+            );
+            return;
+        }
+    };
+    if let Err(e) = hand_over_menu(storage.get_ref(), schedule, now) {
+        // Stay active with the snapshot, so the menu can still be put back when
+        // the handover is retried or the failure acknowledged
+        let error = format!("Failed to restore the menu: {}", e);
+        if let Err(settle_err) = settle_failure(
+            storage,
+            schedule.id,
+            &error,
+            e.is_transient(),
+            ScheduleStatus::Active,
+            now,
+        ) {
+            error!(
+                "Failed to record the failure of schedule {}: {}",
+                schedule.id, settle_err
+            );
+        }
+        return;
+    }
+
+    let mut next_schedule = schedule.clone();
+    advance_to_next_occurrence(&mut next_schedule, now, tz);
+    info!(
+        "Occurrence of schedule {} has ended, setting to {:?}",
+        schedule.id, next_schedule.status
+    );
+    match storage.update_menu_schedule(schedule.id, next_schedule.clone(), schedule.version) {
+        Ok(version) => {
+            next_schedule.version = version;
+            // A failure is logged, with no request to report it to
+            let _ = record_schedule_change(storage.get_ref(), schedule, &next_schedule, now);
+        }
+        Err(update_err) => error!(
+            "Failed to move schedule {} past its occurrence: {}",
+            schedule.id, update_err
+        ),
+    }
+}
+
+/// Pass the menu on from an occurrence that has ended.
+///
+/// While occurrences whose presets could set the same items are still running,
+/// the one that started first takes over the availability to put back when they
+/// are all over, and the items they cover are set as the remaining schedules call
+/// for under the conflict policy. Items none of them cover are restored, as is the
+/// whole menu when nothing is left running.
+pub fn hand_over_menu(
+    storage: &dyn Storage,
+    schedule: &MenuSchedule,
+    now: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    let presets = storage.get_menu_presets()?;
+    let menu_items = storage.get_menu_items()?;
+    let running: Vec<MenuSchedule> = storage
+        .get_menu_schedules()?
+        .into_iter()
+        .filter(|other| {
+            other.id != schedule.id
+                && matches!(other.status, ScheduleStatus::Active)
+                && other.end_time > now
+                && shares_menu(schedule, other, &presets, &menu_items)
+        })
+        .collect();
+    let Some(heir) = running.iter().min_by_key(|other| other.start_time) else {
+        return restore_menu(storage, schedule, now);
+    };
+    let policy = storage
+        .get_validation_rules()?
+        .menu_schedules
+        .effective_conflict_policy();
+
+    // Where both remember an item, the schedule that started first saw it earlier
+    let (earlier, later) = if heir.start_time <= schedule.start_time {
+        (&heir.availability_snapshot, &schedule.availability_snapshot)
+    } else {
+        (&schedule.availability_snapshot, &heir.availability_snapshot)
+    };
+    let mut snapshot: BTreeMap<Uuid, bool> = later.clone().unwrap_or_default();
+    snapshot.extend(earlier.clone().unwrap_or_default());
+
+    // The remaining schedules set what they cover; the rest goes back as it was
+    let remaining: Vec<&MenuSchedule> = running.iter().collect();
+    let menu = combined_menu(&presets, &menu_items, &remaining, policy);
+    let (handed_over, restored): (BTreeMap<Uuid, bool>, BTreeMap<Uuid, bool>) = snapshot
+        .into_iter()
+        .partition(|(item_id, _)| menu.contains_key(item_id));
+    snapshot = handed_over;
+    let changed = set_availability(storage, menu_items, now, |item| {
+        menu.get(&item.id)
+            .or_else(|| restored.get(&item.id))
+            .copied()
+    })?;
+    for (item_id, was_available) in changed {
+        if menu.contains_key(&item_id) {
+            snapshot.entry(item_id).or_insert(was_available);
+        }
+    }
+
+    let mut next_heir = heir.clone();
+    next_heir.availability_snapshot = Some(snapshot);
+    next_heir.updated_at = now;
+    next_heir.version = storage.update_menu_schedule(heir.id, next_heir.clone(), heir.version)?;
+    record_schedule_change(storage, heir, &next_heir, now)?;
+    info!(
+        "Schedule {} handed the menu over to running schedule {}",
+        schedule.id, heir.id
+    );
+    Ok(())
+}
+
+/// Undo an occurrence's changes to the menu by applying the schedule's fallback
+/// preset to the items in its scope, and by putting back the availability it
+/// changed everywhere else
+fn restore_menu(
+    storage: &dyn Storage,
+    schedule: &MenuSchedule,
+    now: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    let menu_items = storage.get_menu_items()?;
+    let snapshot = schedule.availability_snapshot.clone().unwrap_or_default();
+    if let Some(fallback_id) = schedule.fallback_preset_id {
+        match storage.get_menu_preset_by_id(fallback_id)? {
+            Some(fallback) => {
+                set_availability(storage, menu_items, now, |item| {
+                    if fallback.scope.contains(item) {
+                        Some(fallback.menu_item_ids.contains(&item.id))
+                    } else {
+                        snapshot.get(&item.id).copied()
+                    }
+                })?;
+                return Ok(());
+            }
+            None => warn!(
+                "Fallback preset {} of schedule {} not found, restoring the previous menu",
+                fallback_id, schedule.id
+            ),
+        }
+    }
+    set_availability(storage, menu_items, now, |item| {
+        snapshot.get(&item.id).copied()
+    })?;
+    Ok(())
+}
+
+/// Give each menu item the availability `available` returns for it, if any.
+///
+/// Items that already have the right availability are left alone, so their
+/// versions (and any ETags admins hold) stay valid. Returns the previous
+/// availability of the items that changed.
+fn set_availability(
+    storage: &dyn Storage,
+    menu_items: Vec<MenuItem>,
+    now: DateTime<Utc>,
+    available: impl Fn(&MenuItem) -> Option<bool>,
+) -> Result<BTreeMap<Uuid, bool>, StorageError> {
+    let mut changed = BTreeMap::new();
+    for mut item in menu_items {
+        let Some(is_available) = available(&item) else {
+            continue;
+        };
+        if item.is_available == is_available {
+            continue;
+        }
+        let previous_item = item.clone();
+        changed.insert(item.id, item.is_available);
+        item.is_available = is_available;
+        item.version = storage.update_menu_item(item.id, item.clone(), item.version)?;
+        history::record(
+            storage,
+            DataKind::MenuItems,
+            item.id,
+            RevisionAction::Updated,
+            Some(&previous_item),
+            Some(&item),
+            Change::new(SCHEDULER_USER, now),
+        )?;
+    }
+    Ok(changed)
+}
+
+/// Move a schedule whose occurrence has ended on to its next occurrence, keeping
+/// the occurrence's length, or end it when the series is over
+fn advance_to_next_occurrence(schedule: &mut MenuSchedule, now: DateTime<Utc>, tz: Tz) {
+    schedule.updated_at = now;
+    schedule.error_message = None;
+    schedule.availability_snapshot = None;
+    schedule.retry_count = 0;
+    schedule.next_retry_at = None;
+    if matches!(schedule.recurrence, ScheduleRecurrence::Custom) && schedule.rrule.is_none() {
+        // Without a rule, a custom schedule runs once and then ends
+        schedule.status = ScheduleStatus::Ended;
+        return;
+    }
+    match calculate_next_occurrence(schedule, now, tz) {
+        Some(next_start) if schedule.series_end.is_some_and(|end| next_start > end) => {
+            schedule.status = ScheduleStatus::Ended;
+            schedule.error_message = Some("Next occurrence is after the series end".to_string());
+        }
+        Some(next_start) => {
+            let length = schedule.end_time - schedule.start_time;
+            schedule.start_time = next_start;
+            schedule.end_time = next_start + length;
+            schedule.status = ScheduleStatus::Pending;
+        }
+        None => {
+            schedule.status = ScheduleStatus::Ended;
+            schedule.error_message = Some(if schedule.rrule.is_some() {
+                "Recurrence rule has no further occurrences".to_string()
+            } else {
+                "Cannot calculate next occurrence".to_string()
+            });
+        }
+    }
+}
+
+/// Add a status change the scheduler made to the schedule's history
+fn record_schedule_change(
+    storage: &dyn Storage,
+    before: &MenuSchedule,
+    after: &MenuSchedule,
+    now: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    history::record(
+        storage,
+        DataKind::MenuSchedules,
+        after.id,
+        RevisionAction::Updated,
+        Some(before),
+        Some(after),
+        Change::new(SCHEDULER_USER, now),
+    )
+}
+
+/// Calculate the next occurrence of a recurring schedule that is not on one of
+/// its exception dates, or `None` for a custom schedule without a rule or whose
+/// rule has run out.
+///
+/// Days, weeks and months are counted from the wall-clock series start in `tz`,
+/// so a schedule keeps its local time when the clocks change.
+fn calculate_next_occurrence(
+    schedule: &MenuSchedule,
+    _now: chrono::DateTime<Utc>,
+    tz: Tz,
+) -> Option<chrono::DateTime<Utc>> {
+    if matches!(schedule.recurrence, ScheduleRecurrence::Custom) {
+        return next_rule_occurrence(schedule, tz);
+    }
+    let next =
+        |start| next_fixed_occurrence(&schedule.recurrence, schedule.series_start, start, tz);
+    std::iter::successors(next(schedule.start_time), |start| next(*start))
+        .find(|start| !is_exception(schedule, *start, tz))
+}
+
+/// The first start after `start` of a daily, weekly or monthly series beginning
+/// at `series_start`.
+///
+/// Every start is counted in days or months from the series start rather than
+/// from the start before it, so a monthly series on the 31st runs on the last day
+/// of shorter months and is back on the 31st the month after.
+fn next_fixed_occurrence(
+    recurrence: &ScheduleRecurrence,
+    series_start: DateTime<Utc>,
+    start: DateTime<Utc>,
+    tz: Tz,
+) -> Option<DateTime<Utc>> {
+    let first = timezone::local(tz, series_start);
+    let local_start = timezone::local(tz, start);
+    let days = (local_start.date() - first.date()).num_days();
+    // How many occurrences in `start` is, give or take one
+    let elapsed = match recurrence {
+        ScheduleRecurrence::Daily => days,
+        ScheduleRecurrence::Weekly => days / 7,
+        ScheduleRecurrence::Monthly => {
+            let months = |time: chrono::NaiveDateTime| {
+                i64::from(time.year()) * 12 + i64::from(time.month0())
+            };
+            months(local_start) - months(first)
+        }
+        ScheduleRecurrence::Custom => return None,
+    };
+    let nth = |n: u32| match recurrence {
+        ScheduleRecurrence::Daily => first.checked_add_days(Days::new(n.into())),
+        ScheduleRecurrence::Weekly => first.checked_add_days(Days::new(u64::from(n) * 7)),
+        ScheduleRecurrence::Monthly => first.checked_add_months(Months::new(n)),
+        ScheduleRecurrence::Custom => None,
+    };
+    let from = u32::try_from(elapsed.saturating_sub(1).max(0)).ok()?;
+    (from..)
+        .map_while(nth)
+        .map(|local| timezone::resolve(tz, local))
+        .find(|next| *next > start)
+}
+
+/// Expand a custom schedule's rule from its DTSTART, which create and update pin to
+/// the first start, so COUNT and INTERVAL carry on where the last run left off
+fn next_rule_occurrence(schedule: &MenuSchedule, tz: Tz) -> Option<DateTime<Utc>> {
+    let rule = schedule.rrule.as_deref()?;
+    match RecurrenceSet::parse(rule) {
+        Ok(set) => set
+            .occurrences(schedule.series_start, tz)
+            .find(|start| *start > schedule.start_time && !is_exception(schedule, *start, tz)),
+        Err(e) => {
+            error!(
+                "Schedule {} has an invalid recurrence rule: {}",
+                schedule.id, e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ManualClock, SystemClock};
+    use crate::leader::LOCK_FILE;
+    use chrono::Duration as ChronoDuration;
+    use std::sync::Arc;
+    use tokio::time::sleep;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_has_schedule_conflict_no_conflict() {
+        let now = Utc::now();
+        let schedule1 = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Schedule 1".to_string(),
+            description: "Test schedule 1".to_string(),
+            start_time: now,
+            end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let schedule2 = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Schedule 2".to_string(),
+            description: "Test schedule 2".to_string(),
+            start_time: now + ChronoDuration::hours(2),
+            end_time: now + ChronoDuration::hours(3),
+            series_start: now + ChronoDuration::hours(2),
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let existing_schedules = vec![schedule2];
+        let result = has_schedule_conflict(&schedule1, &existing_schedules);
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_has_schedule_conflict_with_conflict() {
+        let now = Utc::now();
+        let schedule1 = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Schedule 1".to_string(),
+            description: "Test schedule 1".to_string(),
+            start_time: now,
+            end_time: now + ChronoDuration::hours(2),
+            series_start: now,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         let schedule2 = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Schedule 2".to_string(),
             description: "Test schedule 2".to_string(),
             start_time: now + ChronoDuration::hours(1),
             end_time: now + ChronoDuration::hours(3),
+            series_start: now + ChronoDuration::hours(1),
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         let existing_schedules = vec![schedule2.clone()];
@@ -406,15 +1679,28 @@ mod tests {
         let schedule = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Schedule".to_string(),
             description: "Test schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         let existing_schedules = vec![schedule.clone()];
@@ -429,18 +1715,31 @@ mod tests {
         let schedule = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Daily Schedule".to_string(),
             description: "Test daily schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_occurrence = calculate_next_occurrence(&schedule, now).unwrap();
+        let next_occurrence = calculate_next_occurrence(&schedule, now, Tz::UTC).unwrap();
         assert_eq!(next_occurrence, now + ChronoDuration::days(1));
     }
 
@@ -450,18 +1749,31 @@ mod tests {
         let schedule = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Weekly Schedule".to_string(),
             description: "Test weekly schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Weekly,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_occurrence = calculate_next_occurrence(&schedule, now).unwrap();
+        let next_occurrence = calculate_next_occurrence(&schedule, now, Tz::UTC).unwrap();
         assert_eq!(next_occurrence, now + ChronoDuration::weeks(1));
     }
 
@@ -473,45 +1785,272 @@ mod tests {
         let schedule = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Monthly Schedule".to_string(),
             description: "Test monthly schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Monthly,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_occurrence = calculate_next_occurrence(&schedule, now).unwrap();
+        let next_occurrence = calculate_next_occurrence(&schedule, now, Tz::UTC).unwrap();
         let expected = chrono::DateTime::parse_from_rfc3339("2023-02-15T10:00:00Z")
             .unwrap()
             .with_timezone(&Utc);
         assert_eq!(next_occurrence, expected);
     }
 
+    #[test]
+    fn test_calculate_next_occurrence_monthly_returns_to_the_end_of_the_month() {
+        let at = |time: &str| {
+            chrono::DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let start = at("2024-01-31T12:00:00Z");
+        let mut schedule = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Month End Lunch".to_string(),
+            description: "Lunch on the last day of the month".to_string(),
+            start_time: start,
+            end_time: start + ChronoDuration::hours(2),
+            series_start: start,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Monthly,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: start,
+            updated_at: start,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        // February is cut short, but March is back on the 31st
+        let mut starts = vec![schedule.start_time];
+        for _ in 0..3 {
+            let ended = schedule.end_time;
+            advance_to_next_occurrence(&mut schedule, ended, Tz::UTC);
+            starts.push(schedule.start_time);
+        }
+        let expected = [
+            "2024-01-31T12:00:00Z",
+            "2024-02-29T12:00:00Z",
+            "2024-03-31T12:00:00Z",
+            "2024-04-30T12:00:00Z",
+        ];
+        assert_eq!(starts, expected.map(at));
+
+        // Conflict checks expand the series the same way
+        let occurrences: Vec<DateTime<Utc>> =
+            occurrences_until(&schedule, at("2024-08-01T00:00:00Z"), Tz::UTC)
+                .into_iter()
+                .map(|(start, _)| start)
+                .collect();
+        let expected = [
+            "2024-04-30T12:00:00Z",
+            "2024-05-31T12:00:00Z",
+            "2024-06-30T12:00:00Z",
+            "2024-07-31T12:00:00Z",
+        ];
+        assert_eq!(occurrences, expected.map(at));
+    }
+
     #[test]
     fn test_calculate_next_occurrence_custom_returns_none() {
         let now = Utc::now();
         let schedule = MenuSchedule {
             id: uuid::Uuid::new_v4(),
             preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Custom Schedule".to_string(),
             description: "Test custom schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Custom,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_occurrence = calculate_next_occurrence(&schedule, now);
+        let next_occurrence = calculate_next_occurrence(&schedule, now, Tz::UTC);
         assert!(next_occurrence.is_none());
     }
 
+    #[test]
+    fn test_calculate_next_occurrence_keeps_local_time_across_daylight_saving() {
+        // 07:30 GMT breakfast the day before the clocks go forward
+        let start = chrono::DateTime::parse_from_rfc3339("2025-03-29T07:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let schedule = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Breakfast".to_string(),
+            description: "Daily breakfast".to_string(),
+            start_time: start,
+            end_time: start + ChronoDuration::weeks(1),
+            series_start: start,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: start,
+            updated_at: start,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let next_occurrence =
+            calculate_next_occurrence(&schedule, start, chrono_tz::Europe::London).unwrap();
+
+        // 07:30 BST is 06:30 UTC, 23 hours later
+        assert_eq!(next_occurrence, start + ChronoDuration::hours(23));
+    }
+
+    #[test]
+    fn test_calculate_next_occurrence_custom_follows_rrule() {
+        // Friday lunch on a weekdays-only rule is followed by Monday's
+        let friday = chrono::DateTime::parse_from_rfc3339("2025-01-03T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut schedule = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Term Lunch".to_string(),
+            description: "Weekday lunch".to_string(),
+            start_time: friday,
+            end_time: friday + ChronoDuration::hours(2),
+            series_start: friday,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Custom,
+            rrule: Some(
+                "DTSTART:20250101T120000Z\nRRULE:FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;COUNT=4"
+                    .to_string(),
+            ),
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: friday,
+            updated_at: friday,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let next_occurrence = calculate_next_occurrence(&schedule, friday, Tz::UTC).unwrap();
+        assert_eq!(next_occurrence, friday + ChronoDuration::days(3));
+
+        // COUNT=4 is used up by Wednesday to Monday
+        schedule.start_time = next_occurrence;
+        assert!(calculate_next_occurrence(&schedule, next_occurrence, Tz::UTC).is_none());
+    }
+
+    #[test]
+    fn test_advance_to_next_occurrence_keeps_the_window_until_series_end() {
+        let start = chrono::DateTime::parse_from_rfc3339("2025-06-02T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut schedule = MenuSchedule {
+            id: uuid::Uuid::new_v4(),
+            preset_id: uuid::Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Lunch".to_string(),
+            description: "Lunch from noon to two".to_string(),
+            start_time: start,
+            end_time: start + ChronoDuration::hours(2),
+            series_start: start,
+            series_end: Some(start + ChronoDuration::days(1) + ChronoDuration::hours(1)),
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Active,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: start,
+            updated_at: start,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let first_end = schedule.end_time;
+        advance_to_next_occurrence(&mut schedule, first_end, Tz::UTC);
+        assert_eq!(schedule.status, ScheduleStatus::Pending);
+        assert_eq!(schedule.start_time, start + ChronoDuration::days(1));
+        assert_eq!(
+            schedule.end_time,
+            start + ChronoDuration::days(1) + ChronoDuration::hours(2)
+        );
+        assert_eq!(schedule.series_start, start);
+
+        // The third day starts after the series end
+        let second_end = schedule.end_time;
+        advance_to_next_occurrence(&mut schedule, second_end, Tz::UTC);
+        assert_eq!(schedule.status, ScheduleStatus::Ended);
+        assert_eq!(
+            schedule.error_message.as_deref(),
+            Some("Next occurrence is after the series end")
+        );
+    }
+
     #[test]
     fn test_scheduled_event_ordering() {
         let now = Utc::now();
@@ -519,15 +2058,28 @@ mod tests {
             schedule: MenuSchedule {
                 id: uuid::Uuid::new_v4(),
                 preset_id: uuid::Uuid::new_v4(),
+                fallback_preset_id: None,
                 name: "Schedule 1".to_string(),
                 description: "Test schedule 1".to_string(),
                 start_time: now + ChronoDuration::hours(2),
                 end_time: now + ChronoDuration::hours(3),
+                series_start: now + ChronoDuration::hours(2),
+                series_end: None,
                 recurrence: ScheduleRecurrence::Daily,
+                rrule: None,
+                exception_dates: Vec::new(),
+                priority: 0,
                 status: ScheduleStatus::Pending,
                 error_message: None,
+                conflict_decision: None,
+                retry_count: 0,
+                next_retry_at: None,
+                availability_snapshot: None,
                 created_at: now,
                 updated_at: now,
+                version: 1,
+                deleted_at: None,
+                deleted_by: None,
             },
             execution_time: now + ChronoDuration::hours(2),
         };
@@ -536,15 +2088,28 @@ mod tests {
             schedule: MenuSchedule {
                 id: uuid::Uuid::new_v4(),
                 preset_id: uuid::Uuid::new_v4(),
+                fallback_preset_id: None,
                 name: "Schedule 2".to_string(),
                 description: "Test schedule 2".to_string(),
                 start_time: now + ChronoDuration::hours(1),
                 end_time: now + ChronoDuration::hours(2),
+                series_start: now + ChronoDuration::hours(1),
+                series_end: None,
                 recurrence: ScheduleRecurrence::Daily,
+                rrule: None,
+                exception_dates: Vec::new(),
+                priority: 0,
                 status: ScheduleStatus::Pending,
                 error_message: None,
+                conflict_decision: None,
+                retry_count: 0,
+                next_retry_at: None,
+                availability_snapshot: None,
                 created_at: now,
                 updated_at: now,
+                version: 1,
+                deleted_at: None,
+                deleted_by: None,
             },
             execution_time: now + ChronoDuration::hours(1),
         };
@@ -559,6 +2124,249 @@ mod tests {
         assert_eq!(first_popped.execution_time, now + ChronoDuration::hours(1));
     }
 
+    #[test]
+    fn test_find_conflicts_expands_recurring_occurrences() {
+        let monday = chrono::DateTime::parse_from_rfc3339("2025-09-08T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let breakfast = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Breakfast".to_string(),
+            description: "Every morning from eight to nine".to_string(),
+            start_time: monday,
+            end_time: monday + ChronoDuration::hours(1),
+            series_start: monday,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: monday,
+            updated_at: monday,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let friday = monday + ChronoDuration::days(4) + ChronoDuration::minutes(30);
+        let brunch = MenuSchedule {
+            id: Uuid::new_v4(),
+            name: "Brunch".to_string(),
+            start_time: friday,
+            end_time: friday + ChronoDuration::hours(2),
+            series_start: friday,
+            recurrence: ScheduleRecurrence::Weekly,
+            ..breakfast.clone()
+        };
+        let until = monday + ChronoDuration::weeks(3);
+
+        // The next occurrences are days apart, but every Friday clashes
+        assert!(has_schedule_conflict(&brunch, std::slice::from_ref(&breakfast)).is_none());
+        let clashes = find_conflicts(&brunch, std::slice::from_ref(&breakfast), until, Tz::UTC);
+        assert_eq!(clashes.len(), 3);
+        assert!(
+            clashes
+                .iter()
+                .all(|clash| clash.schedule_id == breakfast.id)
+        );
+        assert_eq!(
+            clashes[1].occurrence_start,
+            friday + ChronoDuration::weeks(1)
+        );
+        assert_eq!(
+            clashes[1].conflicting_start,
+            monday + ChronoDuration::days(11)
+        );
+
+        // Nothing clashes after the series end, or with a schedule that has ended
+        let short_brunch = MenuSchedule {
+            series_end: Some(friday + ChronoDuration::days(1)),
+            ..brunch.clone()
+        };
+        assert_eq!(
+            find_conflicts(
+                &short_brunch,
+                std::slice::from_ref(&breakfast),
+                until,
+                Tz::UTC
+            )
+            .len(),
+            1
+        );
+        let ended = MenuSchedule {
+            status: ScheduleStatus::Ended,
+            ..breakfast.clone()
+        };
+        assert!(find_conflicts(&brunch, &[ended], until, Tz::UTC).is_empty());
+    }
+
+    #[test]
+    fn test_occurrences_until_follows_the_rule() {
+        let start = chrono::DateTime::parse_from_rfc3339("2025-09-09T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let schedule = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Fortnightly Curry".to_string(),
+            description: "Every other Tuesday".to_string(),
+            start_time: start,
+            end_time: start + ChronoDuration::hours(2),
+            series_start: start,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Custom,
+            rrule: Some("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU".to_string()),
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: start,
+            updated_at: start,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+
+        let occurrences = occurrences_until(&schedule, start + ChronoDuration::weeks(6), Tz::UTC);
+        assert_eq!(
+            occurrences,
+            [0, 2, 4].map(|weeks| {
+                let start = start + ChronoDuration::weeks(weeks);
+                (start, start + ChronoDuration::hours(2))
+            })
+        );
+    }
+
+    #[test]
+    fn test_simulate_menu_follows_occurrences_overlaps_and_fallbacks() {
+        use crate::storage::MenuCategory;
+
+        let noon = chrono::DateTime::parse_from_rfc3339("2025-09-09T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let items: Vec<MenuItem> = [("Porridge", true), ("Fish Pie", false)]
+            .into_iter()
+            .map(|(name, is_available)| MenuItem {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                category: MenuCategory::Mains,
+                description: "A test dish".to_string(),
+                allergens: Vec::new(),
+                tags: Vec::new(),
+                is_available,
+                version: 1,
+                deleted_at: None,
+                deleted_by: None,
+            })
+            .collect();
+        let preset = |name: &str, menu_item_ids: Vec<Uuid>| MenuPreset {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: "Test preset".to_string(),
+            menu_item_ids,
+            scope: PresetScope::default(),
+            created_at: noon,
+            updated_at: noon,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let (lunch_menu, closed) = (preset("Lunch", vec![items[1].id]), preset("Closed", vec![]));
+        let presets = vec![lunch_menu.clone(), closed.clone()];
+        let lunch = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: lunch_menu.id,
+            fallback_preset_id: None,
+            name: "Lunch".to_string(),
+            description: "Every day from twelve to two".to_string(),
+            start_time: noon,
+            end_time: noon + ChronoDuration::hours(2),
+            series_start: noon,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: noon,
+            updated_at: noon,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let (from, until) = (
+            noon - ChronoDuration::hours(2),
+            noon + ChronoDuration::hours(22),
+        );
+        let hours = |period: &MenuPeriod| {
+            (
+                (period.start - noon).num_hours(),
+                (period.end - noon).num_hours(),
+            )
+        };
+        let simulate = |schedules: &[MenuSchedule], policy| {
+            simulate_menu(&items, &presets, schedules, policy, from, until, Tz::UTC)
+        };
+
+        // The menu changes for the occurrence and goes back afterwards
+        let periods = simulate(std::slice::from_ref(&lunch), ConflictPolicy::Reject);
+        assert_eq!(
+            periods.iter().map(hours).collect::<Vec<_>>(),
+            [(-2, 0), (0, 2), (2, 22)]
+        );
+        assert_eq!(periods[0].available_item_ids, [items[0].id]);
+        assert_eq!(periods[1].available_item_ids, [items[1].id]);
+        assert_eq!(periods[1].preset_ids, [lunch_menu.id]);
+        assert_eq!(periods[2].available_item_ids, [items[0].id]);
+
+        // A fallback preset stays in place until the next occurrence
+        let closing = MenuSchedule {
+            fallback_preset_id: Some(closed.id),
+            ..lunch.clone()
+        };
+        let periods = simulate(&[closing], ConflictPolicy::Reject);
+        assert!(periods[2].available_item_ids.is_empty());
+
+        // A higher priority occurrence takes over while it runs
+        let tasting = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: closed.id,
+            start_time: noon + ChronoDuration::hours(1),
+            end_time: noon + ChronoDuration::hours(3),
+            recurrence: ScheduleRecurrence::Custom,
+            priority: 5,
+            ..lunch.clone()
+        };
+        let periods = simulate(
+            &[lunch.clone(), tasting.clone()],
+            ConflictPolicy::HighestPriority,
+        );
+        assert_eq!(
+            periods.iter().map(hours).collect::<Vec<_>>(),
+            [(-2, 0), (0, 1), (1, 2), (2, 3), (3, 22)]
+        );
+        assert_eq!(periods[2].schedule_ids, [lunch.id, tasting.id]);
+        assert_eq!(periods[2].preset_ids, [closed.id]);
+        assert!(periods[2].available_item_ids.is_empty());
+    }
+
     #[tokio::test]
     async fn test_scheduler_conflict_detection() {
         use crate::scheduler::has_schedule_conflict;
@@ -569,45 +2377,84 @@ mod tests {
         let schedule1 = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Schedule 1".to_string(),
             description: "First schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(2),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         // Create overlapping schedule
         let schedule2 = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Schedule 2".to_string(),
             description: "Second schedule".to_string(),
             start_time: now + ChronoDuration::hours(1), // Overlaps with schedule1
             end_time: now + ChronoDuration::hours(3),
+            series_start: now + ChronoDuration::hours(1),
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         // Create non-overlapping schedule
         let schedule3 = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Schedule 3".to_string(),
             description: "Third schedule".to_string(),
             start_time: now + ChronoDuration::hours(4),
             end_time: now + ChronoDuration::hours(5),
+            series_start: now + ChronoDuration::hours(4),
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
         // Test conflict detection
@@ -627,6 +2474,808 @@ mod tests {
         assert!(same_id_result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_new_schedule_wakes_a_sleeping_scheduler() {
+        use crate::config::{AppSettings, ValidationRules};
+        use crate::memory_storage::MemoryStorage;
+        use crate::storage::{MenuCategory, MenuItem, MenuPreset};
+        use std::sync::Arc;
+
+        let storage: Data<dyn Storage> = Data::from(Arc::new(MemoryStorage::new(
+            AppSettings::load("config/settings.toml").unwrap(),
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )) as Arc<dyn Storage>);
+        let now = Utc::now();
+        let item = MenuItem {
+            id: Uuid::new_v4(),
+            name: "Soup of the Day".to_string(),
+            category: MenuCategory::Mains,
+            description: "A test dish".to_string(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available: false,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Soup Only".to_string(),
+            description: "Test preset".to_string(),
+            menu_item_ids: vec![item.id],
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let schedule = |name: &str, start_time| MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: preset.id,
+            fallback_preset_id: None,
+            name: name.to_string(),
+            description: "Test schedule".to_string(),
+            start_time,
+            end_time: start_time + ChronoDuration::hours(1),
+            series_start: start_time,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Custom,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_item(item.clone()).unwrap();
+        storage.add_menu_preset(preset.clone()).unwrap();
+        storage
+            .add_menu_schedule(schedule("Next Week", now + ChronoDuration::weeks(1)))
+            .unwrap();
+
+        // The scheduler goes to sleep until next week's schedule...
+        let data_dir = std::env::temp_dir().join(format!("platter-scheduler-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let clock = Data::from(Arc::new(SystemClock) as Arc<dyn Clock>);
+        start_scheduler(
+            storage.clone(),
+            clock.clone(),
+            clock,
+            Data::new(LeaderLock::new(&data_dir, 30)),
+        )
+        .await;
+        sleep(std::time::Duration::from_millis(50)).await;
+
+        // ...and a schedule that is due sooner must still run on time
+        storage
+            .add_menu_schedule(schedule("Now", Utc::now()))
+            .unwrap();
+        let mut available = false;
+        for _ in 0..100 {
+            sleep(std::time::Duration::from_millis(20)).await;
+            available = storage
+                .get_menu_item_by_id(item.id)
+                .unwrap()
+                .unwrap()
+                .is_available;
+            if available {
+                break;
+            }
+        }
+        assert!(available);
+    }
+
+    /// Storage with a breakfast item on the menu, a lunch item off it, and a Daily
+    /// lunch schedule that is due now
+    fn lunch_service(policy: ConflictPolicy) -> (Data<dyn Storage>, Vec<MenuItem>, MenuSchedule) {
+        use crate::config::{AppSettings, ValidationRules};
+        use crate::memory_storage::MemoryStorage;
+        use std::sync::Arc;
+
+        let mut rules = ValidationRules::load("config/validation.toml").unwrap();
+        rules.menu_schedules.allow_overlapping_schedules = policy != ConflictPolicy::Reject;
+        rules.menu_schedules.conflict_policy = policy;
+        let storage: Data<dyn Storage> = Data::from(Arc::new(MemoryStorage::new(
+            AppSettings::load("config/settings.toml").unwrap(),
+            rules,
+        )) as Arc<dyn Storage>);
+        let (items, schedule) = add_lunch_service(&storage);
+        (storage, items, schedule)
+    }
+
+    /// Porridge on the menu and a lunch schedule due now that swaps it for fish pie
+    fn add_lunch_service(storage: &Data<dyn Storage>) -> (Vec<MenuItem>, MenuSchedule) {
+        use crate::storage::{MenuCategory, MenuPreset};
+
+        let now = Utc::now();
+        let items: Vec<MenuItem> = [("Porridge", true), ("Fish Pie", false)]
+            .into_iter()
+            .map(|(name, is_available)| MenuItem {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                category: MenuCategory::Mains,
+                description: "A test dish".to_string(),
+                allergens: Vec::new(),
+                tags: Vec::new(),
+                is_available,
+                version: 1,
+                deleted_at: None,
+                deleted_by: None,
+            })
+            .collect();
+        let preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Lunch".to_string(),
+            description: "Test preset".to_string(),
+            menu_item_ids: vec![items[1].id],
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let schedule = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: preset.id,
+            fallback_preset_id: None,
+            name: "Lunch Service".to_string(),
+            description: "Test schedule".to_string(),
+            start_time: now,
+            end_time: now + ChronoDuration::hours(2),
+            series_start: now,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
+            status: ScheduleStatus::Pending,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        for item in &items {
+            storage.add_menu_item(item.clone()).unwrap();
+        }
+        storage.add_menu_preset(preset).unwrap();
+        storage.add_menu_schedule(schedule.clone()).unwrap();
+        (items, schedule)
+    }
+
+    fn availability(storage: &Data<dyn Storage>, items: &[MenuItem]) -> Vec<bool> {
+        items
+            .iter()
+            .map(|item| {
+                storage
+                    .get_menu_item_by_id(item.id)
+                    .unwrap()
+                    .unwrap()
+                    .is_available
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_occurrence_end_restores_the_previous_menu() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(availability(&storage, &items), [false, true]);
+        let active = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(active.availability_snapshot.as_ref().unwrap().len(), 2);
+
+        handle_ended_active_schedule(&storage, &active, Utc::now()).await;
+        assert_eq!(availability(&storage, &items), [true, false]);
+        let next = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.status, ScheduleStatus::Pending);
+        assert!(next.availability_snapshot.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_settled_schedules_do_not_block_an_occurrence() {
+        let (storage, items, lunch) = lunch_service(ConflictPolicy::Reject);
+        // Each keeps the window of the lunch it last ran or gave up on
+        for status in [
+            ScheduleStatus::Ended,
+            ScheduleStatus::Failed,
+            ScheduleStatus::Conflicted,
+        ] {
+            storage
+                .add_menu_schedule(MenuSchedule {
+                    id: Uuid::new_v4(),
+                    name: format!("{:?} lunch", status),
+                    status,
+                    ..lunch.clone()
+                })
+                .unwrap();
+        }
+
+        execute_schedule(&storage, lunch.clone(), Utc::now())
+            .await
+            .unwrap();
+
+        let lunch_now = storage.get_menu_schedule_by_id(lunch.id).unwrap().unwrap();
+        assert_eq!(lunch_now.status, ScheduleStatus::Active);
+        assert_eq!(availability(&storage, &items), [false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_occurrences_are_found_like_saved_schedules() {
+        let (storage, _items, lunch) = lunch_service(ConflictPolicy::Reject);
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+        // Its current window is today's, but today is one of its exception dates
+        let mut skipped = MenuSchedule {
+            id: Uuid::new_v4(),
+            name: "Skipped lunch".to_string(),
+            exception_dates: vec![timezone::local(tz, lunch.start_time).date()],
+            ..lunch.clone()
+        };
+        storage.add_menu_schedule(skipped.clone()).unwrap();
+
+        execute_schedule(&storage, lunch.clone(), Utc::now())
+            .await
+            .unwrap();
+        let lunch_now = storage.get_menu_schedule_by_id(lunch.id).unwrap().unwrap();
+        assert_eq!(lunch_now.status, ScheduleStatus::Active);
+
+        // Still waiting on yesterday's window, but today's overlaps the next lunch
+        let (storage, _items, lunch) = lunch_service(ConflictPolicy::Reject);
+        skipped.id = Uuid::new_v4();
+        skipped.preset_id = lunch.preset_id;
+        skipped.exception_dates.clear();
+        skipped.start_time -= ChronoDuration::days(1);
+        skipped.end_time -= ChronoDuration::days(1);
+        storage.add_menu_schedule(skipped.clone()).unwrap();
+
+        execute_schedule(&storage, lunch.clone(), Utc::now())
+            .await
+            .unwrap();
+        let lunch_now = storage.get_menu_schedule_by_id(lunch.id).unwrap().unwrap();
+        assert_eq!(lunch_now.status, ScheduleStatus::Conflicted);
+        assert_eq!(
+            lunch_now.conflict_decision.unwrap().outcome,
+            ConflictOutcome::Rejected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_every_run_is_logged() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+
+        let runs = storage.get_schedule_runs(schedule.id).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].outcome, RunOutcome::Applied);
+        assert_eq!(runs[0].occurrence_start, schedule.start_time);
+        assert_eq!(runs[0].preset_ids, [schedule.preset_id]);
+        assert_eq!(
+            runs[0].items_changed,
+            BTreeMap::from([(items[0].id, false), (items[1].id, true)])
+        );
+
+        // A run that fails is logged with its error
+        let mut orphan = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        orphan.preset_id = Uuid::new_v4();
+        assert!(
+            execute_schedule(&storage, orphan, Utc::now())
+                .await
+                .is_err()
+        );
+        let runs = storage.get_schedule_runs(schedule.id).unwrap();
+        assert_eq!(runs[1].outcome, RunOutcome::Failed);
+        assert!(runs[1].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_missed_occurrences_follow_the_misfire_policy() {
+        // Three lunches went by while the server was down; the fourth is under way
+        let cases = [
+            (MisfirePolicy::Skip, 3, None, 3),
+            (MisfirePolicy::Latest, 2, Some(1), 3),
+            (MisfirePolicy::All, 0, Some(3), 1),
+        ];
+        for (policy, missed, ran_days_ago, moved_days) in cases {
+            let (storage, items, mut schedule) = lunch_service(ConflictPolicy::Reject);
+            let first_start = Utc::now() - ChronoDuration::days(3) - ChronoDuration::minutes(30);
+            schedule.start_time = first_start;
+            schedule.end_time = first_start + ChronoDuration::hours(2);
+            schedule.series_start = first_start;
+            schedule.version = storage
+                .update_menu_schedule(schedule.id, schedule.clone(), 1)
+                .unwrap();
+            let tz = storage.get_app_settings().unwrap().localization.tz();
+            let starts: Vec<_> =
+                occurrences_until(&schedule, first_start + ChronoDuration::days(4), tz)
+                    .into_iter()
+                    .map(|(start, _)| start)
+                    .collect();
+
+            catch_up_missed_occurrences(&storage, schedule.clone(), policy, Utc::now())
+                .await
+                .unwrap();
+
+            let runs = storage.get_schedule_runs(schedule.id).unwrap();
+            let missed_starts: Vec<_> = runs
+                .iter()
+                .filter(|run| run.outcome == RunOutcome::Missed)
+                .map(|run| run.occurrence_start)
+                .collect();
+            assert_eq!(missed_starts, starts[..missed], "{:?}", policy);
+            let ran: Vec<_> = runs
+                .iter()
+                .filter(|run| run.outcome == RunOutcome::Applied)
+                .map(|run| run.occurrence_start)
+                .collect();
+            let expected_ran: Vec<_> = ran_days_ago
+                .map(|days| starts[3 - days])
+                .into_iter()
+                .collect();
+            assert_eq!(ran, expected_ran, "{:?}", policy);
+
+            // The menu is back as it was, and the schedule waits for its next lunch
+            assert_eq!(availability(&storage, &items), [true, false]);
+            let next = storage
+                .get_menu_schedule_by_id(schedule.id)
+                .unwrap()
+                .unwrap();
+            assert_eq!(next.status, ScheduleStatus::Pending);
+            assert_eq!(next.start_time, starts[moved_days], "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_with_each_attempt() {
+        let delays: Vec<i64> = (1..=4)
+            .map(|attempt| retry_delay(30, attempt).num_seconds())
+            .collect();
+        assert_eq!(delays, [30, 60, 120, 240]);
+        assert_eq!(retry_delay(u64::MAX, 40), ChronoDuration::days(1));
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_until_the_limit() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        let limit = storage
+            .get_app_settings()
+            .unwrap()
+            .menu
+            .schedule_retry_limit;
+        // An admin renames the schedule while the scheduler holds the old version
+        let mut renamed = schedule.clone();
+        renamed.name = "Lunch".to_string();
+        storage
+            .update_menu_schedule(schedule.id, renamed, schedule.version)
+            .unwrap();
+
+        for attempt in 1..=limit {
+            let before = Utc::now();
+            assert!(
+                execute_schedule(&storage, schedule.clone(), Utc::now())
+                    .await
+                    .is_err()
+            );
+            let retrying = storage
+                .get_menu_schedule_by_id(schedule.id)
+                .unwrap()
+                .unwrap();
+            assert_eq!(retrying.status, ScheduleStatus::Pending);
+            assert_eq!(retrying.retry_count, attempt);
+            let wait = retrying.next_retry_at.unwrap() - before;
+            assert!(wait >= retry_delay(30, attempt), "attempt {}", attempt);
+        }
+        assert!(
+            execute_schedule(&storage, schedule.clone(), Utc::now())
+                .await
+                .is_err()
+        );
+        let failed = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(failed.status, ScheduleStatus::Failed);
+        assert_eq!(failed.next_retry_at, None);
+        assert!(failed.error_message.unwrap().starts_with("Gave up after"));
+
+        // An admin's retry starts afresh with the current version and succeeds
+        let mut retried = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        retry_schedule(&mut retried, Utc::now());
+        retried.version = storage
+            .update_menu_schedule(schedule.id, retried.clone(), retried.version)
+            .unwrap();
+        execute_schedule(&storage, retried, Utc::now())
+            .await
+            .unwrap();
+        let active = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(active.status, ScheduleStatus::Active);
+        assert_eq!(active.retry_count, 0);
+        assert_eq!(availability(&storage, &items), [false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_handover_keeps_the_occurrence_until_it_is_retried() {
+        use crate::storage_v2::HybridStorage;
+        use std::sync::Arc;
+
+        let root = std::env::temp_dir().join(format!("platter-scheduler-{}", Uuid::new_v4()));
+        let (data_dir, config_dir) = (root.join("data"), root.join("config"));
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::copy("config/settings.toml", config_dir.join("settings.toml")).unwrap();
+        std::fs::copy("config/validation.toml", config_dir.join("validation.toml")).unwrap();
+        let storage: Data<dyn Storage> = Data::from(Arc::new(
+            HybridStorage::new(
+                data_dir.to_str().unwrap(),
+                config_dir.to_str().unwrap(),
+                Arc::new(SystemClock),
+            )
+            .unwrap(),
+        ) as Arc<dyn Storage>);
+        let (items, schedule) = add_lunch_service(&storage);
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+        let active = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+
+        // The menu items file cannot be written when the occurrence ends
+        let items_path = data_dir.join("menu_items.json");
+        std::fs::remove_file(&items_path).unwrap();
+        std::fs::create_dir(&items_path).unwrap();
+        handle_ended_active_schedule(&storage, &active, active.end_time).await;
+        let waiting = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(waiting.status, ScheduleStatus::Active);
+        assert_eq!(waiting.availability_snapshot, active.availability_snapshot);
+        assert_eq!(waiting.retry_count, 1);
+        let retry_at = waiting.next_retry_at.unwrap();
+        assert!(retry_at > active.end_time);
+        let events =
+            load_scheduled_events(&storage, &mut storage.subscribe_schedule_changes()).await;
+        assert_eq!(events.peek().unwrap().execution_time, retry_at);
+
+        // The retry puts the menu back and moves on to the next occurrence
+        std::fs::remove_dir(&items_path).unwrap();
+        handle_ended_active_schedule(&storage, &waiting, retry_at).await;
+        assert_eq!(availability(&storage, &items), [true, false]);
+        let next = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.status, ScheduleStatus::Pending);
+        assert_eq!(next.start_time, active.start_time + ChronoDuration::days(1));
+        assert_eq!((next.retry_count, next.next_retry_at), (0, None));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failure_waits_for_acknowledgement() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        let mut orphan = schedule.clone();
+        orphan.preset_id = Uuid::new_v4();
+        assert!(
+            execute_schedule(&storage, orphan, Utc::now())
+                .await
+                .is_err()
+        );
+
+        // Nothing changed and the schedule is not left Active
+        let mut failed = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(failed.status, ScheduleStatus::Failed);
+        assert_eq!(failed.retry_count, 0);
+        assert!(failed.error_message.clone().unwrap().contains("not found"));
+        assert_eq!(availability(&storage, &items), [true, false]);
+
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+        acknowledge_failure(&storage, &mut failed, tz, Utc::now()).unwrap();
+        assert_eq!(failed.status, ScheduleStatus::Pending);
+        assert_eq!(failed.error_message, None);
+        assert_eq!(
+            failed.start_time,
+            next_fixed_occurrence(
+                &ScheduleRecurrence::Daily,
+                schedule.series_start,
+                schedule.start_time,
+                tz
+            )
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exception_dates_are_skipped() {
+        let (storage, _items, mut schedule) = lunch_service(ConflictPolicy::Reject);
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+        let day = |start: DateTime<Utc>| timezone::local(tz, start).date();
+        let starts: Vec<_> =
+            occurrences_until(&schedule, schedule.start_time + ChronoDuration::days(4), tz)
+                .into_iter()
+                .map(|(start, _)| start)
+                .collect();
+
+        // No lunch today or the day after tomorrow
+        schedule.exception_dates = vec![day(starts[0]), day(starts[2])];
+        schedule.version = storage
+            .update_menu_schedule(schedule.id, schedule.clone(), schedule.version)
+            .unwrap();
+        let remaining: Vec<_> =
+            occurrences_until(&schedule, starts[3] + ChronoDuration::hours(1), tz)
+                .into_iter()
+                .map(|(start, _)| start)
+                .collect();
+        assert_eq!(remaining, [starts[1], starts[3]]);
+
+        // The scheduler moves the schedule past today instead of running it
+        assert!(skip_exception_date(&storage, &schedule, Utc::now()));
+        let moved = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved.status, ScheduleStatus::Pending);
+        assert_eq!(moved.start_time, starts[1]);
+        assert_eq!(
+            calculate_next_occurrence(&moved, Utc::now(), tz),
+            Some(starts[3])
+        );
+        assert!(!skip_exception_date(&storage, &moved, Utc::now()));
+    }
+
+    #[tokio::test]
+    async fn test_paused_schedule_hands_the_menu_back_and_resumes_later() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(availability(&storage, &items), [false, true]);
+
+        let mut paused = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        pause_schedule(&storage, &mut paused, Utc::now()).unwrap();
+        assert_eq!(paused.status, ScheduleStatus::Paused);
+        assert_eq!(paused.availability_snapshot, None);
+        assert_eq!(availability(&storage, &items), [true, false]);
+
+        // Resumed three days later, it waits for lunch instead of catching up
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+        let first_start = Utc::now() - ChronoDuration::days(3) + ChronoDuration::hours(1);
+        paused.start_time = first_start;
+        paused.end_time = first_start + ChronoDuration::hours(2);
+        let next = occurrences_until(&paused, first_start + ChronoDuration::days(4), tz)
+            .into_iter()
+            .find(|(_, end)| *end > Utc::now())
+            .unwrap();
+        resume_schedule(&mut paused, tz, Utc::now());
+        assert_eq!(paused.status, ScheduleStatus::Pending);
+        assert_eq!((paused.start_time, paused.end_time), next);
+    }
+
+    #[tokio::test]
+    async fn test_paused_schedule_does_not_block_an_overlapping_one() {
+        let (storage, items, lunch) = lunch_service(ConflictPolicy::Reject);
+        let mut paused = MenuSchedule {
+            id: Uuid::new_v4(),
+            name: "Early lunch".to_string(),
+            start_time: lunch.start_time - ChronoDuration::minutes(30),
+            end_time: lunch.end_time - ChronoDuration::minutes(30),
+            ..lunch.clone()
+        };
+        storage.add_menu_schedule(paused.clone()).unwrap();
+        pause_schedule(&storage, &mut paused, Utc::now()).unwrap();
+        storage
+            .update_menu_schedule(paused.id, paused.clone(), paused.version)
+            .unwrap();
+
+        // Neither in the queue nor a competitor for the lunch it overlaps
+        let events =
+            load_scheduled_events(&storage, &mut storage.subscribe_schedule_changes()).await;
+        assert!(events.iter().all(|event| event.schedule.id != paused.id));
+        execute_schedule(&storage, lunch.clone(), Utc::now())
+            .await
+            .unwrap();
+
+        let lunch_now = storage.get_menu_schedule_by_id(lunch.id).unwrap().unwrap();
+        assert_eq!(lunch_now.status, ScheduleStatus::Active);
+        assert_eq!(lunch_now.conflict_decision, None);
+        assert_eq!(availability(&storage, &items), [false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_occurrence_end_applies_the_fallback_preset() {
+        use crate::storage::MenuPreset;
+
+        let (storage, items, mut schedule) = lunch_service(ConflictPolicy::Reject);
+        let now = Utc::now();
+        let closed = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Closed".to_string(),
+            description: "Nothing on the menu".to_string(),
+            menu_item_ids: Vec::new(),
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_preset(closed.clone()).unwrap();
+        schedule.fallback_preset_id = Some(closed.id);
+        schedule.version = storage
+            .update_menu_schedule(schedule.id, schedule.clone(), 1)
+            .unwrap();
+
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+        let active = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        handle_ended_active_schedule(&storage, &active, Utc::now()).await;
+
+        assert_eq!(availability(&storage, &items), [false, false]);
+    }
+
+    #[test]
+    fn test_resolve_conflict_by_priority_and_specificity() {
+        let now = Utc::now();
+        let daily = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
+            name: "Daily Lunch".to_string(),
+            description: "Every day".to_string(),
+            start_time: now,
+            end_time: now + ChronoDuration::hours(3),
+            series_start: now,
+            series_end: None,
+            recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 5,
+            status: ScheduleStatus::Active,
+            error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let special = MenuSchedule {
+            id: Uuid::new_v4(),
+            name: "Chef's Special".to_string(),
+            recurrence: ScheduleRecurrence::Custom,
+            start_time: now + ChronoDuration::hours(1),
+            end_time: now + ChronoDuration::hours(2),
+            priority: 1,
+            status: ScheduleStatus::Pending,
+            ..daily.clone()
+        };
+        let running = [daily.clone()];
+
+        let decision = resolve_conflict(&special, &running, ConflictPolicy::HighestPriority, now);
+        assert_eq!(decision.outcome, ConflictOutcome::Skipped);
+        assert_eq!(decision.overlapping, [daily.id]);
+        assert!(decision.reason.contains("Daily Lunch"));
+
+        // A tie leaves the menu with the schedule that is already running
+        let level = MenuSchedule {
+            priority: 5,
+            ..special.clone()
+        };
+        let decision = resolve_conflict(&level, &running, ConflictPolicy::HighestPriority, now);
+        assert_eq!(decision.outcome, ConflictOutcome::Skipped);
+
+        let decision = resolve_conflict(&special, &running, ConflictPolicy::MostSpecific, now);
+        assert_eq!(decision.outcome, ConflictOutcome::Applied);
+
+        let decision = resolve_conflict(&special, &running, ConflictPolicy::MergePresets, now);
+        assert_eq!(decision.outcome, ConflictOutcome::Merged);
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_occurrence_hands_the_menu_back() {
+        use crate::storage::MenuPreset;
+
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::HighestPriority);
+        execute_schedule(&storage, schedule.clone(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(availability(&storage, &items), [false, true]);
+
+        let now = Utc::now();
+        let breakfast = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Breakfast".to_string(),
+            description: "Porridge only".to_string(),
+            menu_item_ids: vec![items[0].id],
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_preset(breakfast.clone()).unwrap();
+        let late_breakfast = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: breakfast.id,
+            name: "Late Breakfast".to_string(),
+            end_time: schedule.start_time + ChronoDuration::hours(1),
+            priority: 10,
+            version: 1,
+            ..schedule.clone()
+        };
+        storage.add_menu_schedule(late_breakfast.clone()).unwrap();
+
+        execute_schedule(&storage, late_breakfast.clone(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(availability(&storage, &items), [true, false]);
+        let active = storage
+            .get_menu_schedule_by_id(late_breakfast.id)
+            .unwrap()
+            .unwrap();
+        let decision = active.conflict_decision.clone().unwrap();
+        assert_eq!(decision.outcome, ConflictOutcome::Applied);
+        assert_eq!(decision.overlapping, [schedule.id]);
+
+        // Lunch takes the menu back, and still restores the original one later
+        handle_ended_active_schedule(&storage, &active, Utc::now()).await;
+        assert_eq!(availability(&storage, &items), [false, true]);
+        let lunch = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        handle_ended_active_schedule(&storage, &lunch, Utc::now()).await;
+        assert_eq!(availability(&storage, &items), [true, false]);
+    }
+
     #[tokio::test]
     async fn test_recurring_schedule_calculation() {
         use crate::scheduler::calculate_next_occurrence;
@@ -637,36 +3286,62 @@ mod tests {
         let daily_schedule = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Daily Schedule".to_string(),
             description: "Daily test schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Daily,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_daily = calculate_next_occurrence(&daily_schedule, now).unwrap();
+        let next_daily = calculate_next_occurrence(&daily_schedule, now, Tz::UTC).unwrap();
         assert_eq!(next_daily, now + ChronoDuration::days(1));
 
         // Test weekly recurrence
         let weekly_schedule = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Weekly Schedule".to_string(),
             description: "Weekly test schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Weekly,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_weekly = calculate_next_occurrence(&weekly_schedule, now).unwrap();
+        let next_weekly = calculate_next_occurrence(&weekly_schedule, now, Tz::UTC).unwrap();
         assert_eq!(next_weekly, now + ChronoDuration::weeks(1));
 
         // Test monthly recurrence (using a date that allows for safe addition)
@@ -677,18 +3352,31 @@ mod tests {
         let monthly_schedule = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Monthly Schedule".to_string(),
             description: "Monthly test schedule".to_string(),
             start_time: month_start,
             end_time: month_start + ChronoDuration::hours(1),
+            series_start: month_start,
+            series_end: None,
             recurrence: ScheduleRecurrence::Monthly,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_monthly = calculate_next_occurrence(&monthly_schedule, now).unwrap();
+        let next_monthly = calculate_next_occurrence(&monthly_schedule, now, Tz::UTC).unwrap();
         let expected_monthly = chrono::DateTime::parse_from_rfc3339("2023-02-15T10:00:00Z")
             .unwrap()
             .with_timezone(&Utc);
@@ -698,18 +3386,298 @@ mod tests {
         let custom_schedule = MenuSchedule {
             id: Uuid::new_v4(),
             preset_id: Uuid::new_v4(),
+            fallback_preset_id: None,
             name: "Custom Schedule".to_string(),
             description: "Custom test schedule".to_string(),
             start_time: now,
             end_time: now + ChronoDuration::hours(1),
+            series_start: now,
+            series_end: None,
             recurrence: ScheduleRecurrence::Custom,
+            rrule: None,
+            exception_dates: Vec::new(),
+            priority: 0,
             status: ScheduleStatus::Pending,
             error_message: None,
+            conflict_decision: None,
+            retry_count: 0,
+            next_retry_at: None,
+            availability_snapshot: None,
             created_at: now,
             updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
         };
 
-        let next_custom = calculate_next_occurrence(&custom_schedule, now);
+        let next_custom = calculate_next_occurrence(&custom_schedule, now, Tz::UTC);
         assert!(next_custom.is_none());
     }
+
+    #[tokio::test]
+    async fn test_scoped_presets_run_side_by_side() {
+        use crate::storage::MenuCategory;
+
+        let (storage, mut items, lunch) = lunch_service(ConflictPolicy::Reject);
+
+        // Lunch only sets the mains, and drinks are on all day
+        let mut lunch_preset = storage
+            .get_menu_preset_by_id(lunch.preset_id)
+            .unwrap()
+            .unwrap();
+        lunch_preset.scope.categories = vec![MenuCategory::Mains];
+        storage
+            .update_menu_preset(lunch_preset.id, lunch_preset.clone(), lunch_preset.version)
+            .unwrap();
+        let tea = MenuItem {
+            id: Uuid::new_v4(),
+            name: "Tea".to_string(),
+            category: MenuCategory::Beverages,
+            tags: vec!["drinks".to_string()],
+            is_available: false,
+            ..items[0].clone()
+        };
+        storage.add_menu_item(tea.clone()).unwrap();
+        items.push(tea.clone());
+        let drinks_preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Drinks".to_string(),
+            menu_item_ids: vec![tea.id],
+            scope: PresetScope {
+                categories: Vec::new(),
+                tags: vec!["drinks".to_string()],
+            },
+            ..lunch_preset
+        };
+        storage.add_menu_preset(drinks_preset.clone()).unwrap();
+        let drinks = MenuSchedule {
+            id: Uuid::new_v4(),
+            preset_id: drinks_preset.id,
+            name: "Drinks".to_string(),
+            end_time: lunch.start_time + ChronoDuration::hours(8),
+            ..lunch.clone()
+        };
+        storage.add_menu_schedule(drinks.clone()).unwrap();
+
+        let presets = storage.get_menu_presets().unwrap();
+        assert!(!shares_menu(&lunch, &drinks, &presets, &items));
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+        let predicted = simulate_menu(
+            &items,
+            &presets,
+            &storage.get_menu_schedules().unwrap(),
+            ConflictPolicy::Reject,
+            lunch.start_time,
+            drinks.end_time + ChronoDuration::hours(1),
+            tz,
+        );
+        let menu_at = |time: DateTime<Utc>| -> Vec<bool> {
+            let period = predicted
+                .iter()
+                .find(|period| period.start <= time && time < period.end)
+                .unwrap();
+            items
+                .iter()
+                .map(|item| period.available_item_ids.contains(&item.id))
+                .collect()
+        };
+
+        // Both run at once, each setting only its own items
+        execute_schedule(&storage, drinks.clone(), Utc::now())
+            .await
+            .unwrap();
+        execute_schedule(&storage, lunch.clone(), Utc::now())
+            .await
+            .unwrap();
+        let lunch_now = storage.get_menu_schedule_by_id(lunch.id).unwrap().unwrap();
+        assert_eq!(lunch_now.status, ScheduleStatus::Active);
+        assert_eq!(lunch_now.conflict_decision, None);
+        assert_eq!(availability(&storage, &items), [false, true, true]);
+        assert_eq!(
+            menu_at(lunch.start_time + ChronoDuration::hours(1)),
+            [false, true, true]
+        );
+
+        // The end of lunch puts the mains back and leaves the drinks on
+        handle_ended_active_schedule(&storage, &lunch_now, Utc::now()).await;
+        assert_eq!(availability(&storage, &items), [true, false, true]);
+        assert_eq!(
+            menu_at(lunch.end_time + ChronoDuration::hours(1)),
+            [true, false, true]
+        );
+        let drinks_now = storage.get_menu_schedule_by_id(drinks.id).unwrap().unwrap();
+        assert_eq!(drinks_now.status, ScheduleStatus::Active);
+        assert_eq!(
+            drinks_now.availability_snapshot,
+            Some(BTreeMap::from([(tea.id, false)]))
+        );
+        assert_eq!(
+            menu_at(drinks.end_time + ChronoDuration::minutes(30)),
+            [true, false, false]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_standby_runs_schedules_once_the_leader_lease_expires() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        let data_dir = std::env::temp_dir().join(format!("platter-scheduler-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        // Another process holds the lease and then stops renewing it
+        let start = schedule.start_time;
+        assert!(LeaderLock::new(&data_dir, 30).try_acquire(start).unwrap());
+        let clock = Arc::new(ManualClock::new(start));
+        let heartbeat = Arc::new(clock.handle());
+        let standby = Data::new(LeaderLock::new(&data_dir, 30));
+        tokio::spawn(run_while_leader(
+            storage.clone(),
+            Data::from(clock.clone() as Arc<dyn Clock>),
+            Data::from(heartbeat.clone() as Arc<dyn Clock>),
+            standby.clone(),
+        ));
+
+        // The lunch that is due stays with the leader
+        assert_eq!(
+            heartbeat.next_deadline().await,
+            start + ChronoDuration::seconds(10)
+        );
+        assert!(!standby.is_leader());
+        assert_eq!(availability(&storage, &items), [true, false]);
+
+        // Once the lease runs out the standby takes over and runs it
+        clock.set(start + ChronoDuration::seconds(31));
+        let mut available = Vec::new();
+        for _ in 0..100 {
+            sleep(std::time::Duration::from_millis(20)).await;
+            available = availability(&storage, &items);
+            if available == [false, true] {
+                break;
+            }
+        }
+        assert_eq!(available, [false, true]);
+        assert!(standby.is_leader());
+        let lease = standby.current_lease().unwrap().unwrap();
+        assert_eq!(lease.holder, standby.instance_id());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_holds_back_writes_once_its_lease_runs_out() {
+        let (storage, items, schedule) = lunch_service(ConflictPolicy::Reject);
+        let data_dir = std::env::temp_dir().join(format!("platter-scheduler-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        // The leader waits for lunch...
+        let start = schedule.start_time;
+        let clock = Arc::new(ManualClock::new(start - ChronoDuration::hours(1)));
+        let heartbeat = Arc::new(clock.handle());
+        let lock = Data::new(LeaderLock::new(&data_dir, 30));
+        tokio::spawn(run_while_leader(
+            storage.clone(),
+            Data::from(clock.clone() as Arc<dyn Clock>),
+            Data::from(heartbeat.clone() as Arc<dyn Clock>),
+            lock.clone(),
+        ));
+        assert_eq!(clock.next_deadline().await, start);
+        assert!(lock.is_leader());
+
+        // ...but by the time it wakes its lease has run out, and another instance
+        // is checking the lock file, so it cannot be renewed
+        let guard =
+            std::fs::File::create(data_dir.join(LOCK_FILE).with_extension("lock.update")).unwrap();
+        guard.lock().unwrap();
+        clock.set(start + ChronoDuration::minutes(1));
+        let renewal = heartbeat.next_deadline().await;
+        sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!lock.is_leader());
+        assert_eq!(availability(&storage, &items), [true, false]);
+        assert!(storage.get_schedule_runs(schedule.id).unwrap().is_empty());
+
+        // Once the lease is free again the next heartbeat takes it and lunch runs
+        drop(guard);
+        clock.set(renewal);
+        let mut available = Vec::new();
+        for _ in 0..100 {
+            sleep(std::time::Duration::from_millis(20)).await;
+            available = availability(&storage, &items);
+            if available == [false, true] {
+                break;
+            }
+        }
+        assert_eq!(available, [false, true]);
+        assert!(lock.is_leader());
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_runs_a_week_of_lunches_on_a_manual_clock() {
+        let (storage, items, mut schedule) = lunch_service(ConflictPolicy::Reject);
+        let tz = storage.get_app_settings().unwrap().localization.tz();
+
+        // Daily lunch from a fixed Monday, except on the Wednesday
+        let monday = DateTime::parse_from_rfc3339("2025-11-03T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        schedule.start_time = monday;
+        schedule.end_time = monday + ChronoDuration::hours(2);
+        schedule.series_start = monday;
+        schedule.exception_dates = vec![timezone::local(tz, monday).date() + Days::new(2)];
+        schedule.version = storage
+            .update_menu_schedule(schedule.id, schedule.clone(), schedule.version)
+            .unwrap();
+        let week_end = monday + ChronoDuration::weeks(1);
+        let predicted = simulate_menu(
+            &items,
+            &storage.get_menu_presets().unwrap(),
+            &storage.get_menu_schedules().unwrap(),
+            ConflictPolicy::Reject,
+            monday,
+            week_end,
+            tz,
+        );
+
+        let clock = Arc::new(ManualClock::new(monday));
+        let data_dir = std::env::temp_dir().join(format!("platter-scheduler-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        tokio::spawn(run_while_leader(
+            storage.clone(),
+            Data::from(clock.clone() as Arc<dyn Clock>),
+            Data::from(Arc::new(clock.handle()) as Arc<dyn Clock>),
+            Data::new(LeaderLock::new(&data_dir, 30)),
+        ));
+
+        // Move the clock straight to each time the scheduler waits for, and check
+        // the menu against the simulation once it has caught up. Each step outruns
+        // the lease, so the scheduler also waits for the heartbeat to renew it
+        loop {
+            let deadline =
+                tokio::time::timeout(std::time::Duration::from_secs(5), clock.next_deadline())
+                    .await
+                    .expect("the scheduler should wait for its next event");
+            let now = clock.now();
+            let period = predicted
+                .iter()
+                .find(|period| period.start <= now && now < period.end)
+                .unwrap();
+            let expected: Vec<bool> = items
+                .iter()
+                .map(|item| period.available_item_ids.contains(&item.id))
+                .collect();
+            assert_eq!(availability(&storage, &items), expected, "at {now}");
+            if deadline >= week_end {
+                break;
+            }
+            clock.set(deadline);
+        }
+
+        let runs = storage.get_schedule_runs(schedule.id).unwrap();
+        assert_eq!(runs.len(), 6);
+        assert!(runs.iter().all(|run| run.outcome == RunOutcome::Applied));
+        assert!(
+            runs.iter()
+                .all(|run| run.started_at == run.occurrence_start)
+        );
+        assert_eq!(availability(&storage, &items), [true, false]);
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
 }
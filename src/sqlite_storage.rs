@@ -0,0 +1,803 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, ErrorCode, OptionalExtension, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::config::{AdminUser, AppSettings, ValidationRules};
+use crate::history::{Change, Revision};
+use crate::integrity;
+use crate::migrations::DataKind;
+use crate::runs::ScheduleRun;
+use crate::storage_v2::{
+    HasId, MenuItem, MenuPreset, MenuSchedule, Notice, ScheduleChanges, Storage, StorageError,
+    StorageSnapshot, Trash, Trashable, Versioned, next_version, not_found, supersede_versions,
+};
+
+// Every record is kept as a JSON document keyed by its id; rowid preserves insertion order
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS menu_items (id TEXT PRIMARY KEY, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS notices (id TEXT PRIMARY KEY, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS menu_presets (id TEXT PRIMARY KEY, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS menu_schedules (id TEXT PRIMARY KEY, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS admin_users (username TEXT PRIMARY KEY, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS revisions (
+        id TEXT PRIMARY KEY,
+        record_id TEXT NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS revisions_by_record ON revisions (record_id);
+    CREATE TABLE IF NOT EXISTS schedule_runs (
+        id TEXT PRIMARY KEY,
+        schedule_id TEXT NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS schedule_runs_by_schedule ON schedule_runs (schedule_id);
+";
+
+/// A table holding one record type
+#[derive(Clone, Copy)]
+struct Table {
+    name: &'static str,
+    label: &'static str,
+}
+
+const MENU_ITEMS: Table = Table {
+    name: "menu_items",
+    label: "Menu item",
+};
+const NOTICES: Table = Table {
+    name: "notices",
+    label: "Notice",
+};
+const MENU_PRESETS: Table = Table {
+    name: "menu_presets",
+    label: "Menu preset",
+};
+const MENU_SCHEDULES: Table = Table {
+    name: "menu_schedules",
+    label: "Menu schedule",
+};
+
+/// Storage backed by a single SQLite database file
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    schedule_changes: ScheduleChanges,
+    references: integrity::ReferenceLock,
+    app_settings: AppSettings,
+    validation_rules: ValidationRules,
+}
+
+impl SqliteStorage {
+    pub fn open(
+        path: &str,
+        app_settings: AppSettings,
+        validation_rules: ValidationRules,
+    ) -> Result<Self, StorageError> {
+        log::info!("Opening SQLite database {}", path);
+        Self::with_connection(Connection::open(path)?, app_settings, validation_rules)
+    }
+
+    fn with_connection(
+        connection: Connection,
+        app_settings: AppSettings,
+        validation_rules: ValidationRules,
+    ) -> Result<Self, StorageError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            schedule_changes: ScheduleChanges::default(),
+            references: integrity::ReferenceLock::default(),
+            app_settings,
+            validation_rules,
+        })
+    }
+
+    /// Records of a table that are in the trash, or that are not
+    fn all<T: DeserializeOwned + Trashable>(
+        &self,
+        table: Table,
+        in_trash: bool,
+    ) -> Result<Vec<T>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let records: Vec<T> = Self::read_table(&connection, table)?;
+        Ok(records
+            .into_iter()
+            .filter(|r| r.is_trashed() == in_trash)
+            .collect())
+    }
+
+    fn read_table<T: DeserializeOwned>(
+        connection: &Connection,
+        table: Table,
+    ) -> Result<Vec<T>, StorageError> {
+        let mut statement =
+            connection.prepare(&format!("SELECT body FROM {} ORDER BY rowid", table.name))?;
+        let bodies = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        bodies
+            .iter()
+            .map(|body| Ok(serde_json::from_str(body)?))
+            .collect()
+    }
+
+    fn replace_table<T: HasId + Serialize>(
+        connection: &Connection,
+        table: Table,
+        records: &[T],
+    ) -> Result<(), StorageError> {
+        connection.execute(&format!("DELETE FROM {}", table.name), [])?;
+        let mut statement = connection.prepare(&format!(
+            "INSERT INTO {} (id, body) VALUES (?1, ?2)",
+            table.name
+        ))?;
+        for record in records {
+            statement.execute(params![
+                record.get_id().to_string(),
+                serde_json::to_string(record)?
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn get<T: DeserializeOwned + Trashable>(
+        &self,
+        table: Table,
+        id: Uuid,
+    ) -> Result<Option<T>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(Self::read_record::<T>(&connection, table, id)?.filter(|r| !r.is_trashed()))
+    }
+
+    fn read_record<T: DeserializeOwned>(
+        connection: &Connection,
+        table: Table,
+        id: Uuid,
+    ) -> Result<Option<T>, StorageError> {
+        let body: Option<String> = connection
+            .query_row(
+                &format!("SELECT body FROM {} WHERE id = ?1", table.name),
+                params![id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(body.map(|b| serde_json::from_str(&b)).transpose()?)
+    }
+
+    /// A live record, or a trashed one when `in_trash` is set
+    fn read_stored<T: DeserializeOwned + Trashable>(
+        connection: &Connection,
+        table: Table,
+        id: Uuid,
+        in_trash: bool,
+    ) -> Result<T, StorageError> {
+        Self::read_record::<T>(connection, table, id)?
+            .filter(|r| r.is_trashed() == in_trash)
+            .ok_or_else(|| not_found(table.label, id, in_trash))
+    }
+
+    fn write_record<T: Serialize>(
+        connection: &Connection,
+        table: Table,
+        id: Uuid,
+        record: &T,
+    ) -> Result<(), StorageError> {
+        connection.execute(
+            &format!("UPDATE {} SET body = ?2 WHERE id = ?1", table.name),
+            params![id.to_string(), serde_json::to_string(record)?],
+        )?;
+        Ok(())
+    }
+
+    fn insert<T: HasId + Versioned + Serialize>(
+        &self,
+        table: Table,
+        mut record: T,
+    ) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let id = record.get_id();
+        record.set_version(1);
+        let result = connection.execute(
+            &format!("INSERT INTO {} (id, body) VALUES (?1, ?2)", table.name),
+            params![id.to_string(), serde_json::to_string(&record)?],
+        );
+        match result {
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == ErrorCode::ConstraintViolation =>
+            {
+                Err(StorageError::Validation(format!(
+                    "{} with id {} already exists",
+                    table.label, id
+                )))
+            }
+            other => other.map(|_| ()).map_err(StorageError::from),
+        }
+    }
+
+    // The connection lock is held from the version check to the write, so no other
+    // writer can slip in between
+    fn update<T: HasId + Versioned + Trashable + Serialize + DeserializeOwned>(
+        &self,
+        table: Table,
+        id: Uuid,
+        mut record: T,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let stored: T = Self::read_stored(&connection, table, id, false)?;
+        let version = next_version(table.label, &stored, expected_version)?;
+        record.set_version(version);
+        Self::write_record(&connection, table, id, &record)?;
+        Ok(version)
+    }
+
+    /// Move a record to the trash, or back out of it when `deleted` is `None`
+    fn set_trashed<T: HasId + Versioned + Trashable + Serialize + DeserializeOwned>(
+        &self,
+        table: Table,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Option<Change<'_>>,
+    ) -> Result<u64, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut record: T = Self::read_stored(&connection, table, id, deleted.is_none())?;
+        let version = next_version(table.label, &record, expected_version)?;
+        record.set_version(version);
+        record.set_deleted(deleted.map(|change| (change.at, change.by.to_string())));
+        Self::write_record(&connection, table, id, &record)?;
+        Ok(version)
+    }
+
+    fn purge<T: HasId + Versioned + Trashable + DeserializeOwned>(
+        &self,
+        table: Table,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let stored: T = Self::read_stored(&connection, table, id, true)?;
+        next_version(table.label, &stored, expected_version)?;
+        connection.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", table.name),
+            params![id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn purge_before<T: HasId + Trashable + DeserializeOwned>(
+        &self,
+        table: Table,
+        cutoff: DateTime<Utc>,
+    ) -> Result<usize, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let records: Vec<T> = Self::read_table(&connection, table)?;
+        let mut purged = 0;
+        for record in records
+            .iter()
+            .filter(|r| r.deleted_at().is_some_and(|deleted_at| deleted_at < cutoff))
+        {
+            purged += connection.execute(
+                &format!("DELETE FROM {} WHERE id = ?1", table.name),
+                params![record.get_id().to_string()],
+            )?;
+        }
+        Ok(purged)
+    }
+}
+
+impl Storage for SqliteStorage {
+    // Public getters
+    fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError> {
+        self.all(MENU_ITEMS, false)
+    }
+
+    fn get_menu_item_by_id(&self, id: Uuid) -> Result<Option<MenuItem>, StorageError> {
+        self.get(MENU_ITEMS, id)
+    }
+
+    fn get_notices(&self) -> Result<Vec<Notice>, StorageError> {
+        self.all(NOTICES, false)
+    }
+
+    fn get_notice_by_id(&self, id: Uuid) -> Result<Option<Notice>, StorageError> {
+        self.get(NOTICES, id)
+    }
+
+    fn get_menu_presets(&self) -> Result<Vec<MenuPreset>, StorageError> {
+        self.all(MENU_PRESETS, false)
+    }
+
+    fn get_menu_preset_by_id(&self, id: Uuid) -> Result<Option<MenuPreset>, StorageError> {
+        self.get(MENU_PRESETS, id)
+    }
+
+    fn get_menu_schedules(&self) -> Result<Vec<MenuSchedule>, StorageError> {
+        self.all(MENU_SCHEDULES, false)
+    }
+
+    fn get_menu_schedule_by_id(&self, id: Uuid) -> Result<Option<MenuSchedule>, StorageError> {
+        self.get(MENU_SCHEDULES, id)
+    }
+
+    fn get_admin_users(&self) -> Result<Vec<AdminUser>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut statement = connection.prepare("SELECT body FROM admin_users ORDER BY rowid")?;
+        let bodies = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        bodies
+            .iter()
+            .map(|body| Ok(serde_json::from_str(body)?))
+            .collect()
+    }
+
+    fn get_admin_user_by_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<AdminUser>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let body: Option<String> = connection
+            .query_row(
+                "SELECT body FROM admin_users WHERE username = ?1",
+                params![username],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(body.map(|b| serde_json::from_str(&b)).transpose()?)
+    }
+
+    fn get_app_settings(&self) -> Result<AppSettings, StorageError> {
+        Ok(self.app_settings.clone())
+    }
+
+    fn get_validation_rules(&self) -> Result<ValidationRules, StorageError> {
+        Ok(self.validation_rules.clone())
+    }
+
+    // Menu items
+    fn add_menu_item(&self, item: MenuItem) -> Result<(), StorageError> {
+        self.insert(MENU_ITEMS, item)
+    }
+
+    fn update_menu_item(
+        &self,
+        id: Uuid,
+        updated_item: MenuItem,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.update(MENU_ITEMS, id, updated_item, expected_version)
+    }
+
+    fn delete_menu_item(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_item(self, id, expected_version, deleted, || {
+            self.set_trashed::<MenuItem>(MENU_ITEMS, id, expected_version, Some(deleted))
+                .map(|_| ())
+        })
+    }
+
+    // Notices
+    fn add_notice(&self, notice: Notice) -> Result<(), StorageError> {
+        self.insert(NOTICES, notice)
+    }
+
+    fn update_notice(
+        &self,
+        id: Uuid,
+        updated_notice: Notice,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.update(NOTICES, id, updated_notice, expected_version)
+    }
+
+    fn delete_notice(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.set_trashed::<Notice>(NOTICES, id, expected_version, Some(deleted))
+            .map(|_| ())
+    }
+
+    // Menu presets
+    fn add_menu_preset(&self, preset: MenuPreset) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_preset(self, &preset, None)?;
+        self.insert(MENU_PRESETS, preset)
+    }
+
+    fn update_menu_preset(
+        &self,
+        id: Uuid,
+        updated_preset: MenuPreset,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.get(MENU_PRESETS, id)?;
+        integrity::check_preset(self, &updated_preset, previous.as_ref())?;
+        self.update(MENU_PRESETS, id, updated_preset, expected_version)
+    }
+
+    fn delete_menu_preset(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_preset(self, id, expected_version, deleted, || {
+            self.set_trashed::<MenuPreset>(MENU_PRESETS, id, expected_version, Some(deleted))
+                .map(|_| ())
+        })
+    }
+
+    // Menu schedules
+    fn add_menu_schedule(&self, schedule: MenuSchedule) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_schedule(self, &schedule, None)?;
+        self.insert(MENU_SCHEDULES, schedule)?;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    fn update_menu_schedule(
+        &self,
+        id: Uuid,
+        updated_schedule: MenuSchedule,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.get(MENU_SCHEDULES, id)?;
+        integrity::check_schedule(self, &updated_schedule, previous.as_ref())?;
+        let version = self.update(MENU_SCHEDULES, id, updated_schedule, expected_version)?;
+        self.schedule_changes.notify();
+        Ok(version)
+    }
+
+    fn delete_menu_schedule(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.set_trashed::<MenuSchedule>(MENU_SCHEDULES, id, expected_version, Some(deleted))?;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    // Trash
+    fn get_trash(&self) -> Result<Trash, StorageError> {
+        Ok(Trash {
+            menu_items: self.all(MENU_ITEMS, true)?,
+            notices: self.all(NOTICES, true)?,
+            menu_presets: self.all(MENU_PRESETS, true)?,
+            menu_schedules: self.all(MENU_SCHEDULES, true)?,
+        })
+    }
+
+    fn restore_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_restore(self, kind, id)?;
+        let version = match kind {
+            DataKind::MenuItems => {
+                self.set_trashed::<MenuItem>(MENU_ITEMS, id, expected_version, None)
+            }
+            DataKind::Notices => self.set_trashed::<Notice>(NOTICES, id, expected_version, None),
+            DataKind::MenuPresets => {
+                self.set_trashed::<MenuPreset>(MENU_PRESETS, id, expected_version, None)
+            }
+            DataKind::MenuSchedules => {
+                self.set_trashed::<MenuSchedule>(MENU_SCHEDULES, id, expected_version, None)
+            }
+        }?;
+        if kind == DataKind::MenuSchedules {
+            self.schedule_changes.notify();
+        }
+        Ok(version)
+    }
+
+    fn purge_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<(), StorageError> {
+        match kind {
+            DataKind::MenuItems => self.purge::<MenuItem>(MENU_ITEMS, id, expected_version),
+            DataKind::Notices => self.purge::<Notice>(NOTICES, id, expected_version),
+            DataKind::MenuPresets => self.purge::<MenuPreset>(MENU_PRESETS, id, expected_version),
+            DataKind::MenuSchedules => {
+                self.purge::<MenuSchedule>(MENU_SCHEDULES, id, expected_version)
+            }
+        }
+    }
+
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        Ok(self.purge_before::<MenuItem>(MENU_ITEMS, cutoff)?
+            + self.purge_before::<Notice>(NOTICES, cutoff)?
+            + self.purge_before::<MenuPreset>(MENU_PRESETS, cutoff)?
+            + self.purge_before::<MenuSchedule>(MENU_SCHEDULES, cutoff)?)
+    }
+
+    // Schedule changes
+    fn subscribe_schedule_changes(&self) -> watch::Receiver<()> {
+        self.schedule_changes.subscribe()
+    }
+
+    // Change history
+    fn append_revision(&self, revision: Revision) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        connection.execute(
+            "INSERT INTO revisions (id, record_id, body) VALUES (?1, ?2, ?3)",
+            params![
+                revision.id.to_string(),
+                revision.record_id.to_string(),
+                serde_json::to_string(&revision)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_revisions(
+        &self,
+        kind: DataKind,
+        record_id: Uuid,
+    ) -> Result<Vec<Revision>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut statement =
+            connection.prepare("SELECT body FROM revisions WHERE record_id = ?1 ORDER BY rowid")?;
+        let bodies = statement
+            .query_map(params![record_id.to_string()], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut revisions = Vec::new();
+        for body in bodies {
+            let revision: Revision = serde_json::from_str(&body)?;
+            if revision.kind == kind {
+                revisions.push(revision);
+            }
+        }
+        Ok(revisions)
+    }
+
+    // Schedule runs
+    fn append_schedule_run(&self, run: ScheduleRun) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        connection.execute(
+            "INSERT INTO schedule_runs (id, schedule_id, body) VALUES (?1, ?2, ?3)",
+            params![
+                run.id.to_string(),
+                run.schedule_id.to_string(),
+                serde_json::to_string(&run)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_schedule_runs(&self, schedule_id: Uuid) -> Result<Vec<ScheduleRun>, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut statement = connection
+            .prepare("SELECT body FROM schedule_runs WHERE schedule_id = ?1 ORDER BY rowid")?;
+        let bodies = statement
+            .query_map(params![schedule_id.to_string()], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut runs = Vec::new();
+        for body in bodies {
+            runs.push(serde_json::from_str(&body)?);
+        }
+        Ok(runs)
+    }
+
+    // Admin users
+    fn add_admin_user(&self, user: AdminUser) -> Result<(), StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let result = connection.execute(
+            "INSERT INTO admin_users (username, body) VALUES (?1, ?2)",
+            params![user.username, serde_json::to_string(&user)?],
+        );
+        match result {
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == ErrorCode::ConstraintViolation =>
+            {
+                Err(StorageError::Validation(format!(
+                    "Duplicate username found: {}",
+                    user.username
+                )))
+            }
+            other => other.map(|_| ()).map_err(StorageError::from),
+        }
+    }
+
+    // Backups
+    fn snapshot(&self, now: DateTime<Utc>) -> Result<StorageSnapshot, StorageError> {
+        // Every query goes through the connection mutex, so holding it keeps the copy consistent
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(StorageSnapshot::new(
+            now,
+            Self::read_table(&connection, MENU_ITEMS)?,
+            Self::read_table(&connection, NOTICES)?,
+            Self::read_table(&connection, MENU_PRESETS)?,
+            Self::read_table(&connection, MENU_SCHEDULES)?,
+        ))
+    }
+
+    fn restore_snapshot(&self, mut snapshot: StorageSnapshot) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        let transaction = connection.transaction()?;
+        supersede_versions(
+            &mut snapshot.menu_items,
+            &Self::read_table(&transaction, MENU_ITEMS)?,
+        );
+        supersede_versions(
+            &mut snapshot.notices,
+            &Self::read_table(&transaction, NOTICES)?,
+        );
+        supersede_versions(
+            &mut snapshot.menu_presets,
+            &Self::read_table(&transaction, MENU_PRESETS)?,
+        );
+        supersede_versions(
+            &mut snapshot.menu_schedules,
+            &Self::read_table(&transaction, MENU_SCHEDULES)?,
+        );
+        Self::replace_table(&transaction, MENU_ITEMS, &snapshot.menu_items)?;
+        Self::replace_table(&transaction, NOTICES, &snapshot.notices)?;
+        Self::replace_table(&transaction, MENU_PRESETS, &snapshot.menu_presets)?;
+        Self::replace_table(&transaction, MENU_SCHEDULES, &snapshot.menu_schedules)?;
+        transaction.commit()?;
+        log::info!("Restored snapshot taken at {}", snapshot.created_at);
+        self.schedule_changes.notify();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MenuCategory;
+
+    fn open() -> SqliteStorage {
+        SqliteStorage::with_connection(
+            Connection::open_in_memory().unwrap(),
+            AppSettings::load("config/settings.toml").unwrap(),
+            ValidationRules::load("config/validation.toml").unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn sample_item(name: &str) -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: MenuCategory::Desserts,
+            description: "A test dish".to_string(),
+            allergens: vec!["Milk".to_string()],
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_crud_round_trip_keeps_insertion_order() {
+        let storage = open();
+        let first = sample_item("Sticky Toffee Pudding");
+        let second = sample_item("Custard");
+        storage.add_menu_item(first.clone()).unwrap();
+        storage.add_menu_item(second.clone()).unwrap();
+
+        let mut updated = first.clone();
+        updated.is_available = false;
+        storage.update_menu_item(first.id, updated, 1).unwrap();
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items[0].id, first.id);
+        assert!(!items[0].is_available);
+        assert_eq!(items[1].id, second.id);
+
+        storage
+            .delete_menu_item(second.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+        assert!(storage.get_menu_item_by_id(second.id).unwrap().is_none());
+        assert!(matches!(
+            storage.delete_menu_item(second.id, 2, Change::new("admin", Utc::now())),
+            Err(StorageError::NotFound(_))
+        ));
+        assert_eq!(storage.get_trash().unwrap().menu_items[0].id, second.id);
+    }
+
+    #[test]
+    fn test_duplicate_admin_usernames_are_rejected() {
+        let storage = open();
+        let user = AdminUser {
+            id: Uuid::new_v4(),
+            username: "admin".to_string(),
+            password_hash: "hash".to_string(),
+            created_at: Some(Utc::now()),
+            last_login: None,
+            roles: vec!["super_admin".to_string()],
+            is_active: true,
+        };
+        storage.add_admin_user(user.clone()).unwrap();
+
+        assert!(matches!(
+            storage.add_admin_user(user),
+            Err(StorageError::Validation(_))
+        ));
+        assert!(
+            storage
+                .get_admin_user_by_username("admin")
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -1,14 +1,11 @@
-use std::fs;
-use std::io;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
 
-use crate::error_handler::AppError;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
 use uuid::Uuid;
 
+use crate::config::ConflictPolicy;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MenuItem {
     pub id: Uuid,
@@ -16,10 +13,15 @@ pub struct MenuItem {
     pub category: MenuCategory,
     pub description: String,
     pub allergens: Vec<String>,
+    /// Lower-case labels presets can be scoped to, e.g. `drinks` or `vegan`
+    pub tags: Vec<String>,
     pub is_available: bool,
+    pub version: u64,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum MenuCategory {
     Mains,
     Sides,
@@ -35,13 +37,9 @@ pub struct Notice {
     pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AdminUser {
-    pub id: Uuid,
-    pub username: String,
-    pub password_hash: String,
+    pub version: u64,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -59,6 +57,31 @@ pub enum ScheduleStatus {
     Pending,
     Failed,
     Conflicted,
+    /// Suspended by an admin until resumed
+    Paused,
+}
+
+/// How the scheduler settled an occurrence that overlapped other schedules'
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConflictDecision {
+    pub policy: ConflictPolicy,
+    pub outcome: ConflictOutcome,
+    /// The schedules whose occurrences it overlapped
+    pub overlapping: Vec<Uuid>,
+    pub reason: String,
+    pub decided_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ConflictOutcome {
+    /// The schedule's preset set the menu
+    Applied,
+    /// The menu offered the schedule's preset together with the others'
+    Merged,
+    /// Another schedule kept the menu, so this occurrence left it alone
+    Skipped,
+    /// The schedule was marked `Conflicted`
+    Rejected,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -67,728 +90,86 @@ pub struct MenuPreset {
     pub name: String,
     pub description: String,
     pub menu_item_ids: Vec<Uuid>,
+    /// The items applying the preset sets; the rest of the menu is left alone
+    pub scope: PresetScope,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: u64,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
+}
+
+/// The menu items a preset applies to: those in any of `categories` or with any
+/// of `tags`. An empty scope covers the whole menu
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PresetScope {
+    pub categories: Vec<MenuCategory>,
+    pub tags: Vec<String>,
+}
+
+impl PresetScope {
+    pub fn is_whole_menu(&self) -> bool {
+        self.categories.is_empty() && self.tags.is_empty()
+    }
+
+    pub fn contains(&self, item: &MenuItem) -> bool {
+        self.is_whole_menu()
+            || self.categories.contains(&item.category)
+            || item.tags.iter().any(|tag| self.tags.contains(tag))
+    }
+
+    /// Whether the two scopes can cover the same item: they share a category or
+    /// a tag, or one of `items` falls in both
+    pub fn overlaps(&self, other: &PresetScope, items: &[MenuItem]) -> bool {
+        self.is_whole_menu()
+            || other.is_whole_menu()
+            || self
+                .categories
+                .iter()
+                .any(|category| other.categories.contains(category))
+            || self.tags.iter().any(|tag| other.tags.contains(tag))
+            || items
+                .iter()
+                .any(|item| self.contains(item) && other.contains(item))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MenuSchedule {
     pub id: Uuid,
     pub preset_id: Uuid,
+    /// Preset applied when an occurrence ends; `None` puts the menu back as it was
+    pub fallback_preset_id: Option<Uuid>,
     pub name: String,
     pub description: String,
+    /// Start of the current or next occurrence
     pub start_time: DateTime<Utc>,
+    /// End of the current or next occurrence
     pub end_time: DateTime<Utc>,
+    /// Start of the first occurrence
+    pub series_start: DateTime<Utc>,
+    /// No occurrence starts after this; `None` repeats until the recurrence runs out
+    pub series_end: Option<DateTime<Utc>>,
     pub recurrence: ScheduleRecurrence,
+    /// RFC 5545 rule for `Custom` schedules, e.g. `FREQ=WEEKLY;BYDAY=TU`
+    pub rrule: Option<String>,
+    /// Local dates on which no occurrence runs, e.g. exam days
+    pub exception_dates: Vec<NaiveDate>,
+    /// Higher priorities win overlaps under the `highest_priority` conflict policy
+    pub priority: i32,
     pub status: ScheduleStatus,
     pub error_message: Option<String>,
+    /// How the last occurrence that overlapped other schedules was settled
+    pub conflict_decision: Option<ConflictDecision>,
+    /// Attempts at the current occurrence that failed with a transient error
+    pub retry_count: u32,
+    /// When the scheduler tries the occurrence again after a transient error
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Availability the running occurrence changed, by item, from before it started
+    pub availability_snapshot: Option<BTreeMap<Uuid, bool>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-}
-
-#[derive(Error, Debug)]
-pub enum StorageError {
-    #[error("IO error: {0}")]
-    Io(io::Error),
-    #[error("JSON serialization error: {0}")]
-    Json(#[from] serde_json::Error),
-    #[error("Mutex poison error")]
-    PoisonError,
-    #[error(
-        "Permission denied: {0}. Please ensure the application has write access to the data directory."
-    )]
-    PermissionDenied(String),
-}
-
-impl From<io::Error> for StorageError {
-    fn from(error: io::Error) -> Self {
-        if error.kind() == io::ErrorKind::PermissionDenied {
-            StorageError::PermissionDenied(error.to_string())
-        } else {
-            StorageError::Io(error)
-        }
-    }
-}
-
-impl From<StorageError> for AppError {
-    fn from(storage_error: StorageError) -> Self {
-        match storage_error {
-            StorageError::Io(io_error) => AppError::Storage(io_error.to_string()),
-            StorageError::Json(json_error) => AppError::Storage(json_error.to_string()),
-            StorageError::PoisonError => AppError::Storage("Mutex poison error".to_string()),
-            StorageError::PermissionDenied(msg) => AppError::Storage(msg),
-        }
-    }
-}
-
-pub struct JsonStorage {
-    menu_items: Arc<Mutex<Vec<MenuItem>>>,
-    notices: Arc<Mutex<Vec<Notice>>>,
-    admin_users: Arc<Mutex<Vec<AdminUser>>>,
-    menu_presets: Arc<Mutex<Vec<MenuPreset>>>,
-    menu_schedules: Arc<Mutex<Vec<MenuSchedule>>>,
-    menu_items_path: String,
-    notices_path: String,
-    admin_users_path: String,
-    menu_presets_path: String,
-    menu_schedules_path: String,
-}
-
-impl JsonStorage {
-    pub fn new(
-        menu_items_path: &str,
-        notices_path: &str,
-        admin_users_path: &str,
-        menu_presets_path: &str,
-        menu_schedules_path: &str,
-    ) -> Result<Self, StorageError> {
-        Self::new_with_paths(
-            menu_items_path,
-            notices_path,
-            admin_users_path,
-            menu_presets_path,
-            menu_schedules_path,
-        )
-    }
-
-    // Public constructor to allow creation for testing
-    pub fn new_with_paths(
-        menu_items_path: &str,
-        notices_path: &str,
-        admin_users_path: &str,
-        menu_presets_path: &str,
-        menu_schedules_path: &str,
-    ) -> Result<Self, StorageError> {
-        log::debug!("JsonStorage::new() started");
-
-        // Ensure data directory exists
-        let data_dir = Path::new(menu_items_path)
-            .parent()
-            .unwrap_or(Path::new("."));
-        if !data_dir.exists() {
-            log::debug!("Creating data directory: {:?}", data_dir);
-            fs::create_dir_all(data_dir)?;
-        }
-
-        // Initialize with empty vectors
-        let menu_items = Arc::new(Mutex::new(Vec::new()));
-        let notices = Arc::new(Mutex::new(Vec::new()));
-        let admin_users = Arc::new(Mutex::new(Vec::new()));
-        let menu_presets = Arc::new(Mutex::new(Vec::new()));
-        let menu_schedules = Arc::new(Mutex::new(Vec::new()));
-
-        let storage = Self {
-            menu_items,
-            notices,
-            admin_users,
-            menu_presets,
-            menu_schedules,
-            menu_items_path: menu_items_path.to_string(),
-            notices_path: notices_path.to_string(),
-            admin_users_path: admin_users_path.to_string(),
-            menu_presets_path: menu_presets_path.to_string(),
-            menu_schedules_path: menu_schedules_path.to_string(),
-        };
-
-        // Load existing data or create empty files
-        log::debug!("Loading menu items...");
-        storage.load_menu_items()?;
-        log::debug!("Menu items loaded successfully");
-
-        log::debug!("Loading notices...");
-        storage.load_notices()?;
-        log::debug!("Notices loaded successfully");
-
-        log::debug!("Loading admin users...");
-        storage.load_admin_users()?;
-        log::debug!("Admin users loaded successfully");
-
-        log::debug!("Loading menu presets...");
-        storage.load_menu_presets()?;
-        log::debug!("Menu presets loaded successfully");
-
-        log::debug!("Loading menu schedules...");
-        storage.load_menu_schedules()?;
-        log::debug!("Menu schedules loaded successfully");
-
-        log::debug!("JsonStorage::new() completed");
-        Ok(storage)
-    }
-
-    pub fn load_menu_items(&self) -> Result<(), StorageError> {
-        log::debug!(
-            "load_menu_items() started for path: {}",
-            self.menu_items_path
-        );
-        let path = Path::new(&self.menu_items_path);
-        if !path.exists() {
-            log::debug!("Creating empty menu items file");
-            // Create empty file with empty array
-            let empty_vec: Vec<MenuItem> = Vec::new();
-            let json_data = serde_json::to_string_pretty(&empty_vec)?;
-            fs::write(path, json_data)?;
-        }
-
-        log::debug!("Reading menu items file");
-        let file_content = fs::read_to_string(path)?;
-        let items: Vec<MenuItem> = serde_json::from_str(&file_content)?;
-
-        log::debug!("Acquiring menu items mutex");
-        let mut menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        *menu_items = items;
-        log::debug!("Menu items loaded: {} items", menu_items.len());
-
-        Ok(())
-    }
-
-    pub fn save_menu_items(&self) -> Result<(), StorageError> {
-        let menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        let json_data = serde_json::to_string_pretty(&*menu_items)?;
-        match fs::write(&self.menu_items_path, json_data) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                log::error!("Failed to write to {}: {}", &self.menu_items_path, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    pub fn load_notices(&self) -> Result<(), StorageError> {
-        log::debug!("load_notices() started for path: {}", self.notices_path);
-        let path = Path::new(&self.notices_path);
-        if !path.exists() {
-            log::debug!("Creating empty notices file");
-            // Create empty file with empty array
-            let empty_vec: Vec<Notice> = Vec::new();
-            let json_data = serde_json::to_string_pretty(&empty_vec)?;
-            fs::write(path, json_data)?;
-        }
-
-        log::debug!("Reading notices file");
-        let file_content = fs::read_to_string(path)?;
-        let notices: Vec<Notice> = serde_json::from_str(&file_content)?;
-
-        log::debug!("Acquiring notices mutex");
-        let mut notices_lock = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        *notices_lock = notices;
-        log::debug!("Notices loaded: {} items", notices_lock.len());
-
-        Ok(())
-    }
-
-    pub fn load_admin_users(&self) -> Result<(), StorageError> {
-        log::debug!(
-            "load_admin_users() started for path: {}",
-            self.admin_users_path
-        );
-        let path = Path::new(&self.admin_users_path);
-        if !path.exists() {
-            log::debug!("Creating empty admin users file");
-            // Create empty file with empty array
-            let empty_vec: Vec<AdminUser> = Vec::new();
-            let json_data = serde_json::to_string_pretty(&empty_vec)?;
-            fs::write(path, json_data)?;
-        }
-
-        log::debug!("Reading admin users file");
-        let file_content = fs::read_to_string(path)?;
-        let users: Vec<AdminUser> = serde_json::from_str(&file_content)?;
-
-        log::debug!("Acquiring admin users mutex");
-        let mut admin_users_lock = self
-            .admin_users
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        *admin_users_lock = users;
-        log::debug!("Admin users loaded: {} users", admin_users_lock.len());
-
-        Ok(())
-    }
-
-    pub fn save_notices(&self) -> Result<(), StorageError> {
-        let notices = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        let json_data = serde_json::to_string_pretty(&*notices)?;
-        match fs::write(&self.notices_path, json_data) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                log::error!("Failed to write to {}: {}", &self.notices_path, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    pub fn save_admin_users(&self) -> Result<(), StorageError> {
-        log::debug!("save_admin_users() started");
-        let admin_users = self
-            .admin_users
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Admin users mutex acquired for saving");
-        let json_data = serde_json::to_string_pretty(&*admin_users)?;
-        log::debug!("JSON serialization completed");
-        match fs::write(&self.admin_users_path, json_data) {
-            Ok(_) => {
-                log::debug!("File write completed");
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Failed to write to {}: {}", &self.admin_users_path, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    pub fn load_menu_presets(&self) -> Result<(), StorageError> {
-        log::debug!(
-            "load_menu_presets() started for path: {}",
-            self.menu_presets_path
-        );
-        let path = Path::new(&self.menu_presets_path);
-        if !path.exists() {
-            log::debug!("Creating empty menu presets file");
-            // Create empty file with empty array
-            let empty_vec: Vec<MenuPreset> = Vec::new();
-            let json_data = serde_json::to_string_pretty(&empty_vec)?;
-            fs::write(path, json_data)?;
-        }
-
-        log::debug!("Reading menu presets file");
-        let file_content = fs::read_to_string(path)?;
-        let presets: Vec<MenuPreset> = serde_json::from_str(&file_content)?;
-
-        log::debug!("Acquiring menu presets mutex");
-        let mut menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        *menu_presets = presets;
-        log::debug!("Menu presets loaded: {} items", menu_presets.len());
-
-        Ok(())
-    }
-
-    pub fn load_menu_schedules(&self) -> Result<(), StorageError> {
-        log::debug!(
-            "load_menu_schedules() started for path: {}",
-            self.menu_schedules_path
-        );
-        let path = Path::new(&self.menu_schedules_path);
-        if !path.exists() {
-            log::debug!("Creating empty menu schedules file");
-            // Create empty file with empty array
-            let empty_vec: Vec<MenuSchedule> = Vec::new();
-            let json_data = serde_json::to_string_pretty(&empty_vec)?;
-            fs::write(path, json_data)?;
-        }
-
-        log::debug!("Reading menu schedules file");
-        let file_content = fs::read_to_string(path)?;
-        let schedules: Vec<MenuSchedule> = serde_json::from_str(&file_content)?;
-
-        log::debug!("Acquiring menu schedules mutex");
-        let mut menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        *menu_schedules = schedules;
-        log::debug!("Menu schedules loaded: {} items", menu_schedules.len());
-
-        Ok(())
-    }
-
-    pub fn save_menu_presets(&self) -> Result<(), StorageError> {
-        let menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        let json_data = serde_json::to_string_pretty(&*menu_presets)?;
-        match fs::write(&self.menu_presets_path, json_data) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                log::error!("Failed to write to {}: {}", &self.menu_presets_path, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    pub fn save_menu_schedules(&self) -> Result<(), StorageError> {
-        let menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        let json_data = serde_json::to_string_pretty(&*menu_schedules)?;
-        match fs::write(&self.menu_schedules_path, json_data) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                log::error!("Failed to write to {}: {}", &self.menu_schedules_path, e);
-                Err(e.into())
-            }
-        }
-    }
-
-    pub fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError> {
-        let menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(menu_items.clone())
-    }
-
-    pub fn get_notices(&self) -> Result<Vec<Notice>, StorageError> {
-        let notices = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        Ok(notices.clone())
-    }
-
-    pub fn get_admin_users(&self) -> Result<Vec<AdminUser>, StorageError> {
-        let admin_users = self
-            .admin_users
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(admin_users.clone())
-    }
-
-    pub fn add_menu_item(&self, item: MenuItem) -> Result<(), AppError> {
-        let mut menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        menu_items.push(item);
-        // Explicitly drop the lock before calling save_menu_items
-        drop(menu_items);
-        self.save_menu_items().map_err(AppError::from)
-    }
-
-    pub fn add_notice(&self, notice: Notice) -> Result<(), StorageError> {
-        let mut notices = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        notices.push(notice);
-        // Explicitly drop the lock before calling save_notices
-        drop(notices);
-        self.save_notices()
-    }
-
-    pub fn update_menu_item(&self, id: Uuid, updated_item: MenuItem) -> Result<(), AppError> {
-        log::debug!(
-            "update_menu_item() called with id: {}, item: {:?}",
-            id,
-            updated_item
-        );
-        log::debug!("About to acquire menu_items lock in update_menu_item");
-        let mut menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_items lock in update_menu_item");
-        if let Some(index) = menu_items.iter().position(|item| item.id == id) {
-            menu_items[index] = updated_item;
-            log::debug!("Item updated in memory");
-            // Explicitly drop the lock before calling save_menu_items
-            drop(menu_items);
-            log::debug!("Released menu_items lock in update_menu_item");
-            log::debug!("About to call save_menu_items()");
-            self.save_menu_items().map_err(AppError::from)?;
-            log::debug!("save_menu_items() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_items);
-            log::debug!("Released menu_items lock in update_menu_item (not found)");
-            Err(AppError::Storage(format!(
-                "Menu item with id {} not found",
-                id
-            )))
-        }
-    }
-
-    pub fn delete_menu_item(&self, id: Uuid) -> Result<(), AppError> {
-        log::debug!("delete_menu_item() called with id: {}", id);
-        log::debug!("About to acquire menu_items lock in delete_menu_item");
-        let mut menu_items = self
-            .menu_items
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_items lock in delete_menu_item");
-        if let Some(index) = menu_items.iter().position(|item| item.id == id) {
-            menu_items.remove(index);
-            log::debug!("Item removed from memory");
-            // Explicitly drop the lock before calling save_menu_items
-            drop(menu_items);
-            log::debug!("Released menu_items lock in delete_menu_item");
-            log::debug!("About to call save_menu_items()");
-            self.save_menu_items().map_err(AppError::from)?;
-            log::debug!("save_menu_items() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_items);
-            log::debug!("Released menu_items lock in delete_menu_item (not found)");
-            Err(AppError::Storage(format!(
-                "Menu item with id {} not found",
-                id
-            )))
-        }
-    }
-
-    pub fn update_notice(&self, id: Uuid, updated_notice: Notice) -> Result<(), StorageError> {
-        log::debug!(
-            "update_notice() called with id: {}, notice: {:?}",
-            id,
-            updated_notice
-        );
-        log::debug!("About to acquire notices lock in update_notice");
-        let mut notices = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired notices lock in update_notice");
-        if let Some(index) = notices.iter().position(|notice| notice.id == id) {
-            notices[index] = updated_notice;
-            log::debug!("Notice updated in memory");
-            // Explicitly drop the lock before calling save_notices
-            drop(notices);
-            log::debug!("Released notices lock in update_notice");
-            log::debug!("About to call save_notices()");
-            self.save_notices()?;
-            log::debug!("save_notices() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(notices);
-            log::debug!("Released notices lock in update_notice (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Notice with id {} not found", id),
-            )))
-        }
-    }
-
-    pub fn delete_notice(&self, id: Uuid) -> Result<(), StorageError> {
-        log::debug!("delete_notice() called with id: {}", id);
-        log::debug!("About to acquire notices lock in delete_notice");
-        let mut notices = self.notices.lock().map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired notices lock in delete_notice");
-        if let Some(index) = notices.iter().position(|notice| notice.id == id) {
-            notices.remove(index);
-            log::debug!("Notice removed from memory");
-            // Explicitly drop the lock before calling save_notices
-            drop(notices);
-            log::debug!("Released notices lock in delete_notice");
-            log::debug!("About to call save_notices()");
-            self.save_notices()?;
-            log::debug!("save_notices() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(notices);
-            log::debug!("Released notices lock in delete_notice (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Notice with id {} not found", id),
-            )))
-        }
-    }
-
-    pub fn get_admin_user_by_username(
-        &self,
-        username: &str,
-    ) -> Result<Option<AdminUser>, StorageError> {
-        let admin_users = self
-            .admin_users
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        let user = admin_users
-            .iter()
-            .find(|user| user.username == username)
-            .cloned();
-        Ok(user)
-    }
-
-    pub fn add_admin_user(&self, user: AdminUser) -> Result<(), StorageError> {
-        log::debug!("add_admin_user() started");
-        {
-            let mut admin_users = self
-                .admin_users
-                .lock()
-                .map_err(|_| StorageError::PoisonError)?;
-            log::debug!("Admin users mutex acquired");
-            admin_users.push(user);
-            log::debug!("User added to vector");
-        } // Lock is released here
-        log::debug!("Saving admin users...");
-        self.save_admin_users()?;
-        log::debug!("add_admin_user() completed successfully");
-        Ok(())
-    }
-
-    pub fn get_menu_presets(&self) -> Result<Vec<MenuPreset>, StorageError> {
-        let menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(menu_presets.clone())
-    }
-
-    pub fn get_menu_schedules(&self) -> Result<Vec<MenuSchedule>, StorageError> {
-        let menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(menu_schedules.clone())
-    }
-
-    pub fn add_menu_preset(&self, preset: MenuPreset) -> Result<(), StorageError> {
-        let mut menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        menu_presets.push(preset);
-        // Explicitly drop the lock before calling save_menu_presets
-        drop(menu_presets);
-        self.save_menu_presets()
-    }
-
-    pub fn add_menu_schedule(&self, schedule: MenuSchedule) -> Result<(), StorageError> {
-        let mut menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        menu_schedules.push(schedule);
-        // Explicitly drop the lock before calling save_menu_schedules
-        drop(menu_schedules);
-        self.save_menu_schedules()
-    }
-
-    pub fn update_menu_preset(
-        &self,
-        id: Uuid,
-        updated_preset: MenuPreset,
-    ) -> Result<(), StorageError> {
-        log::debug!(
-            "update_menu_preset() called with id: {}, preset: {:?}",
-            id,
-            updated_preset
-        );
-        log::debug!("About to acquire menu_presets lock in update_menu_preset");
-        let mut menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_presets lock in update_menu_preset");
-        if let Some(index) = menu_presets.iter().position(|preset| preset.id == id) {
-            menu_presets[index] = updated_preset;
-            log::debug!("Preset updated in memory");
-            // Explicitly drop the lock before calling save_menu_presets
-            drop(menu_presets);
-            log::debug!("Released menu_presets lock in update_menu_preset");
-            log::debug!("About to call save_menu_presets()");
-            self.save_menu_presets()?;
-            log::debug!("save_menu_presets() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_presets);
-            log::debug!("Released menu_presets lock in update_menu_preset (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Menu preset with id {} not found", id),
-            )))
-        }
-    }
-
-    pub fn update_menu_schedule(
-        &self,
-        id: Uuid,
-        updated_schedule: MenuSchedule,
-    ) -> Result<(), StorageError> {
-        log::debug!(
-            "update_menu_schedule() called with id: {}, schedule: {:?}",
-            id,
-            updated_schedule
-        );
-        log::debug!("About to acquire menu_schedules lock in update_menu_schedule");
-        let mut menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_schedules lock in update_menu_schedule");
-        if let Some(index) = menu_schedules.iter().position(|schedule| schedule.id == id) {
-            menu_schedules[index] = updated_schedule;
-            log::debug!("Schedule updated in memory");
-            // Explicitly drop the lock before calling save_menu_schedules
-            drop(menu_schedules);
-            log::debug!("Released menu_schedules lock in update_menu_schedule");
-            log::debug!("About to call save_menu_schedules()");
-            self.save_menu_schedules()?;
-            log::debug!("save_menu_schedules() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_schedules);
-            log::debug!("Released menu_schedules lock in update_menu_schedule (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Menu schedule with id {} not found", id),
-            )))
-        }
-    }
-
-    pub fn delete_menu_preset(&self, id: Uuid) -> Result<(), StorageError> {
-        log::debug!("delete_menu_preset() called with id: {}", id);
-        log::debug!("About to acquire menu_presets lock in delete_menu_preset");
-        let mut menu_presets = self
-            .menu_presets
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_presets lock in delete_menu_preset");
-        if let Some(index) = menu_presets.iter().position(|preset| preset.id == id) {
-            menu_presets.remove(index);
-            log::debug!("Preset removed from memory");
-            // Explicitly drop the lock before calling save_menu_presets
-            drop(menu_presets);
-            log::debug!("Released menu_presets lock in delete_menu_preset");
-            log::debug!("About to call save_menu_presets()");
-            self.save_menu_presets()?;
-            log::debug!("save_menu_presets() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_presets);
-            log::debug!("Released menu_presets lock in delete_menu_preset (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Menu preset with id {} not found", id),
-            )))
-        }
-    }
-
-    pub fn delete_menu_schedule(&self, id: Uuid) -> Result<(), StorageError> {
-        log::debug!("delete_menu_schedule() called with id: {}", id);
-        log::debug!("About to acquire menu_schedules lock in delete_menu_schedule");
-        let mut menu_schedules = self
-            .menu_schedules
-            .lock()
-            .map_err(|_| StorageError::PoisonError)?;
-        log::debug!("Acquired menu_schedules lock in delete_menu_schedule");
-        if let Some(index) = menu_schedules.iter().position(|schedule| schedule.id == id) {
-            menu_schedules.remove(index);
-            log::debug!("Schedule removed from memory");
-            // Explicitly drop the lock before calling save_menu_schedules
-            drop(menu_schedules);
-            log::debug!("Released menu_schedules lock in delete_menu_schedule");
-            log::debug!("About to call save_menu_schedules()");
-            self.save_menu_schedules()?;
-            log::debug!("save_menu_schedules() completed successfully");
-            Ok(())
-        } else {
-            // Explicitly drop the lock before returning error
-            drop(menu_schedules);
-            log::debug!("Released menu_schedules lock in delete_menu_schedule (not found)");
-            Err(StorageError::Io(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("Menu schedule with id {} not found", id),
-            )))
-        }
-    }
+    pub version: u64,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
 }
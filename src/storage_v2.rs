@@ -1,22 +1,41 @@
-use std::fs;
-use std::io;
-use std::path::Path;
-use std::sync::{Arc, RwLock};
 use std::collections::HashMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::config::{AdminConfig, AppSettings, ValidationRules, ConfigError};
+use crate::clock::Clock;
+use crate::config::{
+    AdminConfig, AdminUser, AppSettings, ConfigError, StorageBackend, ValidationRules,
+};
 use crate::error_handler::AppError;
+use crate::history::{Change, Revision};
+use crate::integrity;
+use crate::memory_storage::MemoryStorage;
+use crate::migrations::{self, DataKind};
+use crate::runs::ScheduleRun;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_storage::SqliteStorage;
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::watch;
 use uuid::Uuid;
 
 // Re-export types from original storage for compatibility
 pub use crate::storage::{
-    MenuItem, MenuCategory, Notice, MenuPreset, MenuSchedule,
-    ScheduleRecurrence, ScheduleStatus
+    ConflictDecision, ConflictOutcome, MenuItem, MenuPreset, MenuSchedule, Notice, PresetScope,
+    ScheduleRecurrence, ScheduleStatus,
 };
 
+pub const SCHEMA_VERSION: &str = "1.9.0";
+const GENERATED_BY: &str = "platter-admin-ui";
+const RESTORE_JOURNAL_FILE: &str = "restore.journal";
+const HISTORY_FILE: &str = "history.jsonl";
+const SCHEDULE_RUNS_FILE: &str = "schedule_runs.jsonl";
+
 #[derive(Error, Debug)]
 pub enum StorageError {
     #[error("IO error: {0}")]
@@ -27,10 +46,60 @@ pub enum StorageError {
     Config(#[from] ConfigError),
     #[error("RwLock poison error")]
     PoisonError,
-    #[error("Permission denied: {0}. Please ensure the application has write access to the data directory.")]
+    #[error(
+        "Permission denied: {0}. Please ensure the application has write access to the data directory."
+    )]
     PermissionDenied(String),
     #[error("Validation error: {0}")]
     Validation(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Recovered {path} from {source_path}: {reason}")]
+    Recovered {
+        path: String,
+        source_path: String,
+        reason: String,
+    },
+    #[error("Data file {path} is unreadable and could not be recovered: {reason}")]
+    Unrecoverable { path: String, reason: String },
+    #[error(
+        "Data file {path} uses schema version {found}, but this build only supports up to {supported}. Upgrade Platter before using this data."
+    )]
+    UnsupportedSchemaVersion {
+        path: String,
+        found: String,
+        supported: String,
+    },
+    #[error("{label} with id {id} has been modified (version {current}, expected {expected})")]
+    VersionConflict {
+        label: &'static str,
+        id: Uuid,
+        expected: u64,
+        current: u64,
+    },
+    #[error("{0}")]
+    Referenced(String),
+}
+
+impl StorageError {
+    /// Whether the same operation may well succeed if it is tried again later,
+    /// e.g. after a full disk is cleared or a record edited meanwhile is re-read
+    pub fn is_transient(&self) -> bool {
+        match self {
+            StorageError::Io(_)
+            | StorageError::PoisonError
+            | StorageError::VersionConflict { .. } => true,
+            #[cfg(feature = "sqlite")]
+            StorageError::Sqlite(rusqlite::Error::SqliteFailure(error, _)) => matches!(
+                error.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ),
+            _ => false,
+        }
+    }
 }
 
 impl From<io::Error> for StorageError {
@@ -52,6 +121,18 @@ impl From<StorageError> for AppError {
             StorageError::PoisonError => AppError::Storage("RwLock poison error".to_string()),
             StorageError::PermissionDenied(msg) => AppError::Storage(msg),
             StorageError::Validation(msg) => AppError::Storage(msg),
+            StorageError::NotFound(msg) => AppError::NotFound(msg),
+            conflict @ StorageError::VersionConflict { .. } => {
+                AppError::PreconditionFailed(conflict.to_string())
+            }
+            StorageError::Referenced(msg) => AppError::Conflict(msg),
+            #[cfg(feature = "sqlite")]
+            StorageError::Sqlite(sqlite_error) => AppError::Storage(sqlite_error.to_string()),
+            file_error @ (StorageError::Recovered { .. }
+            | StorageError::Unrecoverable { .. }
+            | StorageError::UnsupportedSchemaVersion { .. }) => {
+                AppError::Storage(file_error.to_string())
+            }
         }
     }
 }
@@ -71,6 +152,8 @@ pub struct JsonMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_items: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_notices: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub active_notices: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub active_schedules: Option<usize>,
@@ -83,188 +166,1626 @@ pub struct JsonMetadata {
     pub data_integrity_check: String,
 }
 
-impl<T> Default for JsonDataFile<T> {
+impl Default for JsonMetadata {
     fn default() -> Self {
         Self {
-            schema_version: "1.0.0".to_string(),
-            last_updated: Utc::now().to_rfc3339(),
-            generated_by: "platter-admin-ui".to_string(),
-            metadata: JsonMetadata {
-                total_items: None,
-                active_notices: None,
-                active_schedules: None,
-                total_presets: None,
-                total_schedules: None,
-                categories: None,
-                data_integrity_check: "passed".to_string(),
-            },
-            items: Vec::new(),
+            total_items: None,
+            total_notices: None,
+            active_notices: None,
+            active_schedules: None,
+            total_presets: None,
+            total_schedules: None,
+            categories: None,
+            data_integrity_check: "passed".to_string(),
+        }
+    }
+}
+
+impl<T> JsonDataFile<T> {
+    fn new(items: Vec<T>, metadata: JsonMetadata, last_updated: DateTime<Utc>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION.to_string(),
+            last_updated: last_updated.to_rfc3339(),
+            generated_by: GENERATED_BY.to_string(),
+            metadata,
+            items,
+        }
+    }
+}
+
+/// Records of one JSON data file together with their UUID index
+struct IndexedRecords<T> {
+    records: Vec<T>,
+    index: HashMap<Uuid, usize>,
+}
+
+impl<T: HasId> IndexedRecords<T> {
+    fn new(records: Vec<T>) -> Self {
+        let index = HybridStorage::build_index(&records);
+        Self { records, index }
+    }
+}
+
+/// Hashes of what a storage last wrote to each of its files, so the file watcher
+/// can tell the storage's own writes from edits made outside it
+#[derive(Default)]
+struct OwnWrites {
+    hashes: Mutex<HashMap<PathBuf, u64>>,
+}
+
+impl OwnWrites {
+    /// Note that `contents` are about to be written to `path`
+    fn record(&self, path: &str, contents: &[u8]) {
+        let Some(key) = Self::key(Path::new(path)) else {
+            return;
+        };
+        if let Ok(mut hashes) = self.hashes.lock() {
+            hashes.insert(key, Self::hash(contents));
+        }
+    }
+
+    /// Whether `path` holds exactly what was last recorded for it
+    fn matches(&self, path: &Path) -> bool {
+        let (Some(key), Ok(contents)) = (Self::key(path), fs::read(path)) else {
+            return false;
+        };
+        self.hashes
+            .lock()
+            .is_ok_and(|hashes| hashes.get(&key) == Some(&Self::hash(&contents)))
+    }
+
+    /// The same key for any spelling of a path, whether or not the file exists yet
+    fn key(path: &Path) -> Option<PathBuf> {
+        let parent = path.parent()?;
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        Some(fs::canonicalize(dir).ok()?.join(path.file_name()?))
+    }
+
+    fn hash(contents: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// One data file as a restore will leave it
+#[derive(Serialize, Deserialize)]
+struct JournaledFile {
+    path: String,
+    contents: String,
+}
+
+/// A restore that has not yet reached every data file.
+///
+/// The journal holds the full contents of every file the restore writes, so
+/// rolling it forward always replays the whole snapshot. Until it has been
+/// replayed, every data file write replays it first, and fails if it cannot.
+struct RestoreJournal {
+    path: String,
+    pending: Mutex<bool>,
+    own_writes: Arc<OwnWrites>,
+}
+
+impl RestoreJournal {
+    fn open(path: String, own_writes: Arc<OwnWrites>) -> Self {
+        let pending = Path::new(&path).exists();
+        Self {
+            path,
+            pending: Mutex::new(pending),
+            own_writes,
+        }
+    }
+
+    /// Record `files` as the outcome of a restore; from here on the restore is
+    /// rolled forward rather than discarded
+    fn begin(&self, files: &[JournaledFile]) -> Result<(), StorageError> {
+        let mut pending = self.pending.lock().map_err(|_| StorageError::PoisonError)?;
+        write_atomic(&self.path, &serde_json::to_vec(files)?)?;
+        *pending = true;
+        Ok(())
+    }
+
+    /// Write every file of a pending restore, then remove the journal
+    fn finish(&self) -> Result<(), StorageError> {
+        let mut pending = self.pending.lock().map_err(|_| StorageError::PoisonError)?;
+        if !*pending {
+            return Ok(());
+        }
+        let files: Vec<JournaledFile> = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        for file in files {
+            log::info!("Writing restored {}", file.path);
+            self.own_writes.record(&file.path, file.contents.as_bytes());
+            write_atomic(&file.path, file.contents.as_bytes())?;
+        }
+        fs::remove_file(&self.path)?;
+        *pending = false;
+        Ok(())
+    }
+}
+
+/// A cached, indexed collection backed by a single enveloped JSON data file
+struct JsonCollection<T> {
+    kind: DataKind,
+    path: String,
+    data: RwLock<IndexedRecords<T>>,
+    metadata_fn: fn(&[T]) -> JsonMetadata,
+    /// Outcome of the last reference scan, written as `data_integrity_check`
+    integrity_check: RwLock<String>,
+    own_writes: Arc<OwnWrites>,
+    restore_journal: Arc<RestoreJournal>,
+    /// Stamps `last_updated` on every write
+    clock: Arc<dyn Clock>,
+}
+
+impl<T> JsonCollection<T>
+where
+    T: HasId + Versioned + Trashable + Serialize + DeserializeOwned + Clone,
+{
+    fn load(
+        kind: DataKind,
+        path: String,
+        metadata_fn: fn(&[T]) -> JsonMetadata,
+        own_writes: Arc<OwnWrites>,
+        restore_journal: Arc<RestoreJournal>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, StorageError> {
+        let data_file = HybridStorage::load_json_file(&path, kind, metadata_fn, clock.now())?;
+        log::debug!("{} records loaded: {}", kind.label(), data_file.items.len());
+        Ok(Self {
+            kind,
+            data: RwLock::new(IndexedRecords::new(data_file.items)),
+            path,
+            metadata_fn,
+            integrity_check: RwLock::new(data_file.metadata.data_integrity_check),
+            own_writes,
+            restore_journal,
+            clock,
+        })
+    }
+
+    /// Record a new integrity scan outcome, rewriting the file if it changed
+    fn set_integrity_check(&self, check: String) -> Result<(), StorageError> {
+        {
+            let mut current = self
+                .integrity_check
+                .write()
+                .map_err(|_| StorageError::PoisonError)?;
+            if *current == check {
+                return Ok(());
+            }
+            if check == "passed" {
+                log::info!("Integrity check of {} passed", self.path);
+            } else {
+                log::warn!("Integrity check of {} {}", self.path, check);
+            }
+            *current = check;
+        }
+        let data = self.data.read().map_err(|_| StorageError::PoisonError)?;
+        self.persist(&data.records)
+    }
+
+    /// The file's metadata for `records`, including the last integrity scan
+    fn metadata(&self, records: &[T]) -> Result<JsonMetadata, StorageError> {
+        let check = self
+            .integrity_check
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(JsonMetadata {
+            data_integrity_check: check.clone(),
+            ..(self.metadata_fn)(records)
+        })
+    }
+
+    /// Re-read the data file after an external edit. Unlike startup, a file that
+    /// fails to parse is left untouched and the current records stay in place.
+    fn reload(&self) -> Result<(), StorageError> {
+        // Hold the lock while reading so a concurrent write cannot be overwritten
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let (data_file, _) = HybridStorage::read_data_file::<T>(&self.path, self.kind)?;
+        let records = IndexedRecords::new(data_file.items);
+        if records.index.len() != records.records.len() {
+            return Err(StorageError::Validation(format!(
+                "{} contains duplicate ids",
+                self.path
+            )));
+        }
+        *data = records;
+        log::debug!(
+            "{} records reloaded: {}",
+            self.kind.label(),
+            data.records.len()
+        );
+        Ok(())
+    }
+
+    /// Every record that is not in the trash
+    fn all(&self) -> Result<Vec<T>, StorageError> {
+        let data = self.data.read().map_err(|_| StorageError::PoisonError)?;
+        Ok(data
+            .records
+            .iter()
+            .filter(|r| !r.is_trashed())
+            .cloned()
+            .collect())
+    }
+
+    fn get(&self, id: Uuid) -> Result<Option<T>, StorageError> {
+        let data = self.data.read().map_err(|_| StorageError::PoisonError)?;
+        Ok(data
+            .index
+            .get(&id)
+            .map(|&idx| &data.records[idx])
+            .filter(|r| !r.is_trashed())
+            .cloned())
+    }
+
+    fn trashed(&self) -> Result<Vec<T>, StorageError> {
+        let data = self.data.read().map_err(|_| StorageError::PoisonError)?;
+        Ok(data
+            .records
+            .iter()
+            .filter(|r| r.is_trashed())
+            .cloned()
+            .collect())
+    }
+
+    fn insert(&self, mut record: T) -> Result<(), StorageError> {
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let id = record.get_id();
+        if data.index.contains_key(&id) {
+            return Err(StorageError::Validation(format!(
+                "{} with id {} already exists",
+                self.kind.label(),
+                id
+            )));
+        }
+        record.set_version(1);
+        let mut records = data.records.clone();
+        records.push(record);
+        self.commit(&mut data, records)
+    }
+
+    fn update(&self, id: Uuid, mut record: T, expected_version: u64) -> Result<u64, StorageError> {
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&data, id, false)?;
+        let version = next_version(self.kind.label(), &data.records[idx], expected_version)?;
+        record.set_version(version);
+        let mut records = data.records.clone();
+        records[idx] = record;
+        self.commit(&mut data, records)?;
+        Ok(version)
+    }
+
+    /// Move a record to the trash, or back out of it when `deleted` is `None`
+    fn set_trashed(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Option<Change<'_>>,
+    ) -> Result<u64, StorageError> {
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&data, id, deleted.is_none())?;
+        let version = next_version(self.kind.label(), &data.records[idx], expected_version)?;
+        let mut records = data.records.clone();
+        let record = &mut records[idx];
+        record.set_version(version);
+        record.set_deleted(deleted.map(|change| (change.at, change.by.to_string())));
+        self.commit(&mut data, records)?;
+        Ok(version)
+    }
+
+    /// Permanently remove a record from the trash
+    fn purge(&self, id: Uuid, expected_version: u64) -> Result<(), StorageError> {
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let idx = self.position(&data, id, true)?;
+        next_version(self.kind.label(), &data.records[idx], expected_version)?;
+        let mut records = data.records.clone();
+        records.remove(idx);
+        self.commit(&mut data, records)
+    }
+
+    /// Permanently remove everything trashed before `cutoff`
+    fn purge_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut data = self.data.write().map_err(|_| StorageError::PoisonError)?;
+        let records: Vec<T> = data
+            .records
+            .iter()
+            .filter(|r| r.deleted_at().is_none_or(|deleted_at| deleted_at >= cutoff))
+            .cloned()
+            .collect();
+        let purged = data.records.len() - records.len();
+        if purged > 0 {
+            self.commit(&mut data, records)?;
+        }
+        Ok(purged)
+    }
+
+    /// Write `records` to disk and, once that succeeds, make them the collection's
+    /// records in memory
+    fn commit(&self, data: &mut IndexedRecords<T>, records: Vec<T>) -> Result<(), StorageError> {
+        self.persist(&records)?;
+        *data = IndexedRecords::new(records);
+        Ok(())
+    }
+
+    /// Index of a live record, or of a trashed one when `in_trash` is set
+    fn position(
+        &self,
+        data: &IndexedRecords<T>,
+        id: Uuid,
+        in_trash: bool,
+    ) -> Result<usize, StorageError> {
+        data.index
+            .get(&id)
+            .copied()
+            .filter(|&idx| data.records[idx].is_trashed() == in_trash)
+            .ok_or_else(|| not_found(self.kind.label(), id, in_trash))
+    }
+
+    /// The collection's data file as it would be written for `records`
+    fn render(&self, records: &[T]) -> Result<JournaledFile, StorageError> {
+        let metadata = self.metadata(records)?;
+        Ok(JournaledFile {
+            path: self.path.clone(),
+            contents: HybridStorage::render_json_file(records, |_| metadata, self.clock.now())?,
+        })
+    }
+
+    fn persist(&self, records: &[T]) -> Result<(), StorageError> {
+        // Records in memory already match a pending restore, so it must reach disk first
+        self.restore_journal.finish()?;
+        let metadata = self.metadata(records)?;
+        let json_data = HybridStorage::render_json_file(records, |_| metadata, self.clock.now())?;
+        self.own_writes.record(&self.path, json_data.as_bytes());
+        write_atomic(&self.path, json_data.as_bytes()).inspect_err(|e| {
+            log::error!("Failed to write to {}: {}", self.path, e);
+        })
+    }
+}
+
+/// A consistent copy of every data collection, used for backups and restores
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageSnapshot {
+    pub schema_version: String,
+    pub created_at: DateTime<Utc>,
+    pub generated_by: String,
+    pub menu_items: Vec<MenuItem>,
+    pub notices: Vec<Notice>,
+    pub menu_presets: Vec<MenuPreset>,
+    pub menu_schedules: Vec<MenuSchedule>,
+}
+
+impl StorageSnapshot {
+    pub fn new(
+        created_at: DateTime<Utc>,
+        menu_items: Vec<MenuItem>,
+        notices: Vec<Notice>,
+        menu_presets: Vec<MenuPreset>,
+        menu_schedules: Vec<MenuSchedule>,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION.to_string(),
+            created_at,
+            generated_by: GENERATED_BY.to_string(),
+            menu_items,
+            notices,
+            menu_presets,
+            menu_schedules,
+        }
+    }
+}
+
+/// Records that have been deleted but not yet purged, as listed by the admin API
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Trash {
+    pub menu_items: Vec<MenuItem>,
+    pub notices: Vec<Notice>,
+    pub menu_presets: Vec<MenuPreset>,
+    pub menu_schedules: Vec<MenuSchedule>,
+}
+
+/// Tells subscribers such as the scheduler that schedules were written, so they
+/// can act on the change straight away instead of polling
+pub struct ScheduleChanges {
+    sender: watch::Sender<()>,
+}
+
+impl Default for ScheduleChanges {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(());
+        Self { sender }
+    }
+}
+
+impl ScheduleChanges {
+    /// Mark schedules as changed for every subscriber
+    pub fn notify(&self) {
+        self.sender.send_replace(());
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.sender.subscribe()
+    }
+}
+
+/// Persistence operations shared by every storage backend.
+///
+/// Handlers, authentication and the scheduler only ever see a
+/// `web::Data<dyn Storage>`, so the backend can be swapped in `settings.toml`.
+pub trait Storage: Send + Sync {
+    // Reload from the backing store. Backends that are their own source of
+    // truth have nothing to reload.
+    fn load_menu_items(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_notices(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_menu_presets(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_menu_schedules(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_admin_users(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_app_settings(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn load_validation_rules(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Whether the file at `path` still holds what this storage last wrote there.
+    /// The file watcher skips such changes, since the storage already has them.
+    fn wrote_file(&self, _path: &Path) -> bool {
+        false
+    }
+
+    // Getters
+    fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError>;
+    fn get_menu_item_by_id(&self, id: Uuid) -> Result<Option<MenuItem>, StorageError>;
+    fn get_notices(&self) -> Result<Vec<Notice>, StorageError>;
+    fn get_notice_by_id(&self, id: Uuid) -> Result<Option<Notice>, StorageError>;
+    fn get_menu_presets(&self) -> Result<Vec<MenuPreset>, StorageError>;
+    fn get_menu_preset_by_id(&self, id: Uuid) -> Result<Option<MenuPreset>, StorageError>;
+    fn get_menu_schedules(&self) -> Result<Vec<MenuSchedule>, StorageError>;
+    fn get_menu_schedule_by_id(&self, id: Uuid) -> Result<Option<MenuSchedule>, StorageError>;
+    fn get_admin_users(&self) -> Result<Vec<AdminUser>, StorageError>;
+    fn get_admin_user_by_username(&self, username: &str)
+    -> Result<Option<AdminUser>, StorageError>;
+    fn get_app_settings(&self) -> Result<AppSettings, StorageError>;
+    fn get_validation_rules(&self) -> Result<ValidationRules, StorageError>;
+
+    // Records are added at version 1. Updates and deletes fail with
+    // `VersionConflict` unless the stored record is still at `expected_version`;
+    // updates return the record's new version. Deleting moves a record to the
+    // trash, after which getters and updates no longer see it.
+    //
+    // Presets and schedules may only point at live records; writes that would
+    // break this fail with `Referenced`. Deleting a referenced menu item or
+    // preset is rejected or cascades according to `on_delete_referenced`.
+
+    // Menu items
+    fn add_menu_item(&self, item: MenuItem) -> Result<(), StorageError>;
+    fn update_menu_item(
+        &self,
+        id: Uuid,
+        updated_item: MenuItem,
+        expected_version: u64,
+    ) -> Result<u64, StorageError>;
+    fn delete_menu_item(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError>;
+
+    // Notices
+    fn add_notice(&self, notice: Notice) -> Result<(), StorageError>;
+    fn update_notice(
+        &self,
+        id: Uuid,
+        updated_notice: Notice,
+        expected_version: u64,
+    ) -> Result<u64, StorageError>;
+    fn delete_notice(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError>;
+
+    // Menu presets
+    fn add_menu_preset(&self, preset: MenuPreset) -> Result<(), StorageError>;
+    fn update_menu_preset(
+        &self,
+        id: Uuid,
+        updated_preset: MenuPreset,
+        expected_version: u64,
+    ) -> Result<u64, StorageError>;
+    fn delete_menu_preset(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError>;
+
+    // Menu schedules
+    fn add_menu_schedule(&self, schedule: MenuSchedule) -> Result<(), StorageError>;
+    fn update_menu_schedule(
+        &self,
+        id: Uuid,
+        updated_schedule: MenuSchedule,
+        expected_version: u64,
+    ) -> Result<u64, StorageError>;
+    fn delete_menu_schedule(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError>;
+
+    // Trash
+    fn get_trash(&self) -> Result<Trash, StorageError>;
+    /// Move a trashed record back into its collection, returning its new version
+    fn restore_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<u64, StorageError>;
+    /// Permanently remove one trashed record
+    fn purge_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<(), StorageError>;
+    /// Permanently remove every record trashed before `cutoff`, returning how many
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError>;
+
+    // Schedule changes
+    /// A receiver that is marked changed whenever a schedule is added, updated,
+    /// deleted, restored or reloaded
+    fn subscribe_schedule_changes(&self) -> watch::Receiver<()>;
+
+    // Change history
+    /// Append to the change log; revisions are never changed or removed
+    fn append_revision(&self, revision: Revision) -> Result<(), StorageError>;
+    /// Every revision of one record, oldest first
+    fn get_revisions(&self, kind: DataKind, record_id: Uuid)
+    -> Result<Vec<Revision>, StorageError>;
+
+    // Schedule runs
+    /// Append to the run log; runs are never changed or removed
+    fn append_schedule_run(&self, run: ScheduleRun) -> Result<(), StorageError>;
+    /// Every run of one schedule, oldest first
+    fn get_schedule_runs(&self, schedule_id: Uuid) -> Result<Vec<ScheduleRun>, StorageError>;
+
+    // Admin users
+    fn add_admin_user(&self, user: AdminUser) -> Result<(), StorageError>;
+
+    // Backups
+    /// Copy every collection at a single point in time, stamped `now`
+    fn snapshot(&self, now: DateTime<Utc>) -> Result<StorageSnapshot, StorageError>;
+    /// Replace every collection with the snapshot's contents, all or nothing.
+    ///
+    /// Restored records are given versions above every current one (see
+    /// `supersede_versions`), so stale ETags fail rather than match old data.
+    fn restore_snapshot(&self, snapshot: StorageSnapshot) -> Result<(), StorageError>;
+}
+
+/// Open the backend selected by `backend` in the `[storage]` section of settings.toml.
+/// `clock` stamps the metadata of the files the JSON backend writes.
+pub fn open_storage(
+    data_dir: &str,
+    config_dir: &str,
+    clock: Arc<dyn Clock>,
+) -> Result<Arc<dyn Storage>, StorageError> {
+    let app_settings = AppSettings::load(format!("{}/settings.toml", config_dir))?;
+    let validation_rules = ValidationRules::load(format!("{}/validation.toml", config_dir))?;
+    log::info!("Using {:?} storage backend", app_settings.storage.backend);
+
+    match app_settings.storage.backend {
+        StorageBackend::Json => Ok(Arc::new(HybridStorage::new(data_dir, config_dir, clock)?)),
+        StorageBackend::Memory => Ok(Arc::new(MemoryStorage::new(app_settings, validation_rules))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => {
+            fs::create_dir_all(data_dir)?;
+            let database_path = format!("{}/{}", data_dir, app_settings.storage.database_file);
+            Ok(Arc::new(SqliteStorage::open(
+                &database_path,
+                app_settings,
+                validation_rules,
+            )?))
         }
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Err(StorageError::Validation(
+            "The sqlite storage backend requires building with `--features sqlite`".to_string(),
+        )),
     }
 }
 
 pub struct HybridStorage {
-    // JSON data stores (enhanced with metadata)
-    menu_items: Arc<RwLock<Vec<MenuItem>>>,
-    notices: Arc<RwLock<Vec<Notice>>>,
-    menu_presets: Arc<RwLock<Vec<MenuPreset>>>,
-    menu_schedules: Arc<RwLock<Vec<MenuSchedule>>>,
-    
-    // Indexes for O(1) lookups
-    menu_items_index: Arc<RwLock<HashMap<Uuid, usize>>>,
-    presets_index: Arc<RwLock<HashMap<Uuid, usize>>>,
-    schedules_index: Arc<RwLock<HashMap<Uuid, usize>>>,
-    notices_index: Arc<RwLock<HashMap<Uuid, usize>>>,
-    
+    // JSON data stores (enhanced with metadata and O(1) UUID indexes)
+    menu_items: JsonCollection<MenuItem>,
+    notices: JsonCollection<Notice>,
+    menu_presets: JsonCollection<MenuPreset>,
+    menu_schedules: JsonCollection<MenuSchedule>,
+
     // TOML configuration
     admin_config: Arc<RwLock<AdminConfig>>,
-    validation_rules: Arc<RwLock<ValidationRules>>,
     app_settings: Arc<RwLock<AppSettings>>,
-    
+    validation_rules: Arc<RwLock<ValidationRules>>,
+
+    // Append-only change log, one JSON revision per line
+    history: Mutex<String>,
+
+    // Append-only run log, one JSON schedule run per line
+    schedule_runs: Mutex<String>,
+
+    // Wakes the scheduler when schedules are written
+    schedule_changes: ScheduleChanges,
+
+    // What was last written to each data and config file
+    own_writes: Arc<OwnWrites>,
+
+    // Held across reference checks and the writes they guard
+    references: integrity::ReferenceLock,
+
     // File paths
-    menu_items_path: String,
-    notices_path: String,
-    menu_presets_path: String,
-    menu_schedules_path: String,
+    app_settings_path: String,
     admin_config_path: String,
     validation_rules_path: String,
-    app_settings_path: String,
+    restore_journal: Arc<RestoreJournal>,
+
+    // Stamps the metadata of every file written
+    clock: Arc<dyn Clock>,
 }
 
 impl HybridStorage {
     pub fn new(
         data_dir: &str,
         config_dir: &str,
+        clock: Arc<dyn Clock>,
     ) -> Result<Self, StorageError> {
         log::info!("Initializing HybridStorage...");
-        
+
         // Ensure directories exist
         fs::create_dir_all(data_dir)?;
         fs::create_dir_all(config_dir)?;
-        
-        // Define file paths
-        let menu_items_path = format!("{}/menu_items.json", data_dir);
-        let notices_path = format!("{}/notices.json", data_dir);
-        let menu_presets_path = format!("{}/menu_presets.json", data_dir);
-        let menu_schedules_path = format!("{}/menu_schedules.json", data_dir);
-        let admin_config_path = format!("{}/admin.toml", config_dir);
-        let validation_rules_path = format!("{}/validation.toml", config_dir);
-        let app_settings_path = format!("{}/settings.toml", config_dir);
-        
+
         // Load TOML configurations
         log::info!("Loading TOML configurations...");
-        let admin_config = AdminConfig::load(&admin_config_path)?;
-        let validation_rules = ValidationRules::load(&validation_rules_path)?;
+        let app_settings_path = format!("{}/settings.toml", config_dir);
+        let admin_config_path = format!("{}/admin.toml", config_dir);
+        let validation_rules_path = format!("{}/validation.toml", config_dir);
         let app_settings = AppSettings::load(&app_settings_path)?;
-        
-        // Load JSON data
+        let validation_rules = ValidationRules::load(&validation_rules_path)?;
+        let legacy_admin_users_path = format!("{}/admin_users.json", data_dir);
+        let admin_config =
+            Self::load_admin_config(&admin_config_path, &legacy_admin_users_path, clock.now())?;
+
+        // Finish a restore that was interrupted before every file was written
+        let own_writes = Arc::new(OwnWrites::default());
+        let restore_journal = Arc::new(RestoreJournal::open(
+            format!("{}/{}", data_dir, RESTORE_JOURNAL_FILE),
+            own_writes.clone(),
+        ));
+        restore_journal.finish()?;
+
+        // Load JSON data, upgrading legacy bare-array files on the way
         log::info!("Loading JSON data...");
-        let menu_items_data = Self::load_json_file::<MenuItem>(&menu_items_path)?;
-        let notices_data = Self::load_json_file::<Notice>(&notices_path)?;
-        let presets_data = Self::load_json_file::<MenuPreset>(&menu_presets_path)?;
-        let schedules_data = Self::load_json_file::<MenuSchedule>(&menu_schedules_path)?;
-        
-        // Build indexes
-        log::info!("Building indexes...");
-        let menu_items_index = Self::build_index(&menu_items_data.items);
-        let presets_index = Self::build_index(&presets_data.items);
-        let schedules_index = Self::build_index(&schedules_data.items);
-        let notices_index = Self::build_index(&notices_data.items);
-        
-        Ok(Self {
-            menu_items: Arc::new(RwLock::new(menu_items_data.items)),
-            notices: Arc::new(RwLock::new(notices_data.items)),
-            menu_presets: Arc::new(RwLock::new(presets_data.items)),
-            menu_schedules: Arc::new(RwLock::new(schedules_data.items)),
-            menu_items_index: Arc::new(RwLock::new(menu_items_index)),
-            presets_index: Arc::new(RwLock::new(presets_index)),
-            schedules_index: Arc::new(RwLock::new(schedules_index)),
-            notices_index: Arc::new(RwLock::new(notices_index)),
+        let files = &app_settings.storage;
+        let menu_items = JsonCollection::load(
+            DataKind::MenuItems,
+            format!("{}/{}", data_dir, files.menu_items_file),
+            Self::menu_items_metadata,
+            own_writes.clone(),
+            restore_journal.clone(),
+            clock.clone(),
+        )?;
+        let notices = JsonCollection::load(
+            DataKind::Notices,
+            format!("{}/{}", data_dir, files.notices_file),
+            Self::notices_metadata,
+            own_writes.clone(),
+            restore_journal.clone(),
+            clock.clone(),
+        )?;
+        let menu_presets = JsonCollection::load(
+            DataKind::MenuPresets,
+            format!("{}/{}", data_dir, files.menu_presets_file),
+            Self::menu_presets_metadata,
+            own_writes.clone(),
+            restore_journal.clone(),
+            clock.clone(),
+        )?;
+        let menu_schedules = JsonCollection::load(
+            DataKind::MenuSchedules,
+            format!("{}/{}", data_dir, files.menu_schedules_file),
+            Self::menu_schedules_metadata,
+            own_writes.clone(),
+            restore_journal.clone(),
+            clock.clone(),
+        )?;
+
+        let storage = Self {
+            menu_items,
+            notices,
+            menu_presets,
+            menu_schedules,
             admin_config: Arc::new(RwLock::new(admin_config)),
-            validation_rules: Arc::new(RwLock::new(validation_rules)),
             app_settings: Arc::new(RwLock::new(app_settings)),
-            menu_items_path,
-            notices_path,
-            menu_presets_path,
-            menu_schedules_path,
+            validation_rules: Arc::new(RwLock::new(validation_rules)),
+            history: Mutex::new(format!("{}/{}", data_dir, HISTORY_FILE)),
+            schedule_runs: Mutex::new(format!("{}/{}", data_dir, SCHEDULE_RUNS_FILE)),
+            schedule_changes: ScheduleChanges::default(),
+            own_writes,
+            references: integrity::ReferenceLock::default(),
+            app_settings_path,
             admin_config_path,
             validation_rules_path,
-            app_settings_path,
-        })
+            restore_journal,
+            clock,
+        };
+        log::info!("Checking references between presets, schedules and menu items...");
+        storage.check_integrity();
+        Ok(storage)
+    }
+
+    /// Scan presets and schedules for references to missing records and record
+    /// the findings in their data files' `data_integrity_check`.
+    ///
+    /// Runs after every write that can add or clear a finding. Failures are logged
+    /// rather than returned, since the write itself already went through.
+    fn check_integrity(&self) {
+        let result = integrity::scan(self).and_then(|findings| {
+            self.menu_presets
+                .set_integrity_check(integrity::summary(&findings, DataKind::MenuPresets))?;
+            self.menu_schedules
+                .set_integrity_check(integrity::summary(&findings, DataKind::MenuSchedules))
+        });
+        if let Err(e) = result {
+            log::error!("Integrity check failed to run: {}", e);
+        }
     }
-    
-    fn load_json_file<T: for<'de> Deserialize<'de> + Clone>(path: &str) -> Result<JsonDataFile<T>, StorageError> {
-        if !Path::new(path).exists() {
+
+    /// Load admin.toml, migrating users from a legacy admin_users.json on first run
+    fn load_admin_config(
+        admin_config_path: &str,
+        legacy_admin_users_path: &str,
+        now: DateTime<Utc>,
+    ) -> Result<AdminConfig, StorageError> {
+        if Path::new(admin_config_path).exists() {
+            return Ok(AdminConfig::load(admin_config_path)?);
+        }
+
+        let mut config = AdminConfig::default();
+        if Path::new(legacy_admin_users_path).exists() {
+            let content = fs::read_to_string(legacy_admin_users_path)?;
+            let users: Vec<AdminUser> = serde_json::from_str(&content)?;
+            let backup_path = format!("{}.legacy.bak", legacy_admin_users_path);
+            log::info!(
+                "Migrating {} admin users from {} to {} (backup kept at {})",
+                users.len(),
+                legacy_admin_users_path,
+                admin_config_path,
+                backup_path
+            );
+            config.admin_users = users
+                .into_iter()
+                .map(|mut user| {
+                    if user.roles.is_empty() {
+                        user.roles.push("super_admin".to_string());
+                    }
+                    user
+                })
+                .collect();
+            config.metadata.last_modified = Some(now.to_rfc3339());
+            Self::save_admin_config(admin_config_path, &config)?;
+            fs::rename(legacy_admin_users_path, &backup_path)?;
+        } else {
+            log::warn!(
+                "File {} not found, starting with no admin users",
+                admin_config_path
+            );
+        }
+        Ok(config)
+    }
+
+    fn load_json_file<T: Serialize + DeserializeOwned + Clone>(
+        path: &str,
+        kind: DataKind,
+        metadata_fn: fn(&[T]) -> JsonMetadata,
+        now: DateTime<Utc>,
+    ) -> Result<JsonDataFile<T>, StorageError> {
+        let temp_path = temp_path(path);
+        let backup_path = backup_path(path);
+        let nothing_on_disk = [path, temp_path.as_str(), backup_path.as_str()]
+            .iter()
+            .all(|p| !Path::new(p).exists());
+        if nothing_on_disk {
             log::warn!("File {} not found, creating with empty data", path);
-            let empty_file: JsonDataFile<T> = JsonDataFile::default();
-            let json_data = serde_json::to_string_pretty(&empty_file)?;
-            fs::write(path, json_data)?;
-            return Ok(empty_file);
+            Self::save_json_file::<T>(path, &[], metadata_fn, now)?;
+            return Ok(JsonDataFile::new(Vec::new(), JsonMetadata::default(), now));
         }
-        
+
+        let (data_file, migrated_from) = match Self::read_data_file::<T>(path, kind) {
+            Ok(contents) => {
+                if Path::new(&temp_path).exists() {
+                    // The rename never happened, so the live file is still the last good write
+                    log::warn!(
+                        "{}",
+                        StorageError::Recovered {
+                            path: path.to_string(),
+                            source_path: path.to_string(),
+                            reason: format!("discarded interrupted write {}", temp_path),
+                        }
+                    );
+                    fs::remove_file(&temp_path)?;
+                }
+                contents
+            }
+            // A newer file is intact; falling back to an older backup would lose data
+            Err(error @ StorageError::UnsupportedSchemaVersion { .. }) => return Err(error),
+            Err(error) => Self::recover_data_file::<T>(path, kind, error)?,
+        };
+
+        match migrated_from {
+            Some(from) => Self::save_migrated_file(path, &from, data_file.items, metadata_fn, now),
+            None => Ok(data_file),
+        }
+    }
+
+    /// Parse a data file, upgrading it in memory to the current schema.
+    ///
+    /// Also returns the version it was stored at when a migration ran.
+    fn read_data_file<T: DeserializeOwned>(
+        path: &str,
+        kind: DataKind,
+    ) -> Result<(JsonDataFile<T>, Option<String>), StorageError> {
         let content = fs::read_to_string(path)?;
-        let data: JsonDataFile<T> = serde_json::from_str(&content)?;
-        Ok(data)
+        let mut value: serde_json::Value = serde_json::from_str(&content)?;
+        let migrated_from = migrations::migrate(&mut value, kind, path)?;
+        Ok((serde_json::from_value(value)?, migrated_from))
+    }
+
+    /// Restore an unreadable data file from a completed-but-unrenamed temp file
+    /// or from the last good backup, keeping the damaged file as `<file>.corrupt`
+    fn recover_data_file<T: DeserializeOwned>(
+        path: &str,
+        kind: DataKind,
+        error: StorageError,
+    ) -> Result<(JsonDataFile<T>, Option<String>), StorageError> {
+        log::error!("Failed to read data file {}: {}", path, error);
+
+        for candidate in [temp_path(path), backup_path(path)] {
+            if !Path::new(&candidate).exists() {
+                continue;
+            }
+            let contents = match Self::read_data_file::<T>(&candidate, kind) {
+                Ok(contents) => contents,
+                Err(candidate_error) => {
+                    log::warn!(
+                        "Cannot recover {} from {}: {}",
+                        path,
+                        candidate,
+                        candidate_error
+                    );
+                    continue;
+                }
+            };
+
+            if Path::new(path).exists() {
+                fs::rename(path, format!("{}.corrupt", path))?;
+            }
+            if candidate == temp_path(path) {
+                // Finish the rename the interrupted write never got to
+                fs::rename(&candidate, path)?;
+            } else {
+                write_atomic(path, &fs::read(&candidate)?)?;
+            }
+            log::warn!(
+                "{}",
+                StorageError::Recovered {
+                    path: path.to_string(),
+                    source_path: candidate,
+                    reason: error.to_string(),
+                }
+            );
+            return Ok(contents);
+        }
+
+        Err(StorageError::Unrecoverable {
+            path: path.to_string(),
+            reason: error.to_string(),
+        })
+    }
+
+    /// Write a migrated data file back in the current schema, keeping the original
+    /// alongside it (`<file>.legacy.bak` for bare arrays, `<file>.v<version>.bak` otherwise)
+    fn save_migrated_file<T: Serialize + Clone>(
+        path: &str,
+        from: &str,
+        items: Vec<T>,
+        metadata_fn: fn(&[T]) -> JsonMetadata,
+        now: DateTime<Utc>,
+    ) -> Result<JsonDataFile<T>, StorageError> {
+        let backup_path = if from == migrations::LEGACY_SCHEMA_VERSION {
+            format!("{}.legacy.bak", path)
+        } else {
+            format!("{}.v{}.bak", path, from)
+        };
+        fs::copy(path, &backup_path)?;
+        log::info!(
+            "Upgraded {} from schema {} to {} ({} records, original kept at {})",
+            path,
+            from,
+            SCHEMA_VERSION,
+            items.len(),
+            backup_path
+        );
+        Self::save_json_file(path, &items, metadata_fn, now)?;
+        let metadata = metadata_fn(&items);
+        Ok(JsonDataFile::new(items, metadata, now))
     }
-    
-    fn build_index<T>(items: &[T]) -> HashMap<Uuid, usize> 
+
+    fn build_index<T>(items: &[T]) -> HashMap<Uuid, usize>
     where
         T: HasId,
     {
-        items.iter()
+        items
+            .iter()
             .enumerate()
             .map(|(idx, item)| (item.get_id(), idx))
             .collect()
     }
-    
+
     fn save_json_file<T: Serialize + Clone>(
         path: &str,
         items: &[T],
         metadata_fn: impl FnOnce(&[T]) -> JsonMetadata,
+        now: DateTime<Utc>,
     ) -> Result<(), StorageError> {
-        let data_file = JsonDataFile {
-            schema_version: "1.0.0".to_string(),
-            last_updated: Utc::now().to_rfc3339(),
-            generated_by: "platter-admin-ui".to_string(),
-            metadata: metadata_fn(items),
-            items: items.to_vec(),
-        };
-        
-        let json_data = serde_json::to_string_pretty(&data_file)?;
-        fs::write(path, json_data)?;
-        Ok(())
+        let json_data = Self::render_json_file(items, metadata_fn, now)?;
+        write_atomic(path, json_data.as_bytes())
     }
-    
-    // Public getters
-    pub fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError> {
-        let items = self.menu_items.read()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(items.clone())
-    }
-    
-    pub fn get_admin_config(&self) -> Result<AdminConfig, StorageError> {
-        let config = self.admin_config.read()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(config.clone())
+
+    fn render_json_file<T: Serialize + Clone>(
+        items: &[T],
+        metadata_fn: impl FnOnce(&[T]) -> JsonMetadata,
+        now: DateTime<Utc>,
+    ) -> Result<String, StorageError> {
+        let data_file = JsonDataFile::new(items.to_vec(), metadata_fn(items), now);
+
+        Ok(serde_json::to_string_pretty(&data_file)?)
     }
-    
-    pub fn get_validation_rules(&self) -> Result<ValidationRules, StorageError> {
-        let rules = self.validation_rules.read()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(rules.clone())
+
+    fn save_admin_config(path: &str, config: &AdminConfig) -> Result<String, StorageError> {
+        let content = toml::to_string_pretty(config).map_err(ConfigError::from)?;
+        write_atomic(path, content.as_bytes())?;
+        Ok(content)
     }
-    
-    pub fn get_app_settings(&self) -> Result<AppSettings, StorageError> {
-        let settings = self.app_settings.read()
-            .map_err(|_| StorageError::PoisonError)?;
-        Ok(settings.clone())
+
+    fn menu_items_metadata(items: &[MenuItem]) -> JsonMetadata {
+        let mut categories = HashMap::new();
+        for item in items {
+            *categories
+                .entry(format!("{:?}", item.category))
+                .or_insert(0) += 1;
+        }
+        JsonMetadata {
+            total_items: Some(items.len()),
+            categories: Some(categories),
+            ..JsonMetadata::default()
+        }
     }
-}
 
-// Helper trait for items with IDs
-trait HasId {
-    fn get_id(&self) -> Uuid;
-}
+    fn notices_metadata(notices: &[Notice]) -> JsonMetadata {
+        JsonMetadata {
+            total_notices: Some(notices.len()),
+            active_notices: Some(notices.iter().filter(|n| n.is_active).count()),
+            ..JsonMetadata::default()
+        }
+    }
+
+    fn menu_presets_metadata(presets: &[MenuPreset]) -> JsonMetadata {
+        JsonMetadata {
+            total_presets: Some(presets.len()),
+            ..JsonMetadata::default()
+        }
+    }
+
+    fn menu_schedules_metadata(schedules: &[MenuSchedule]) -> JsonMetadata {
+        JsonMetadata {
+            total_schedules: Some(schedules.len()),
+            active_schedules: Some(
+                schedules
+                    .iter()
+                    .filter(|s| s.status == ScheduleStatus::Active)
+                    .count(),
+            ),
+            ..JsonMetadata::default()
+        }
+    }
+
+    pub fn get_admin_config(&self) -> Result<AdminConfig, StorageError> {
+        let config = self
+            .admin_config
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(config.clone())
+    }
+}
+
+impl Storage for HybridStorage {
+    // Reload from disk
+    fn load_menu_items(&self) -> Result<(), StorageError> {
+        self.menu_items.reload()?;
+        self.check_integrity();
+        Ok(())
+    }
+
+    fn load_notices(&self) -> Result<(), StorageError> {
+        self.notices.reload()
+    }
+
+    fn load_menu_presets(&self) -> Result<(), StorageError> {
+        self.menu_presets.reload()?;
+        self.check_integrity();
+        Ok(())
+    }
+
+    fn load_menu_schedules(&self) -> Result<(), StorageError> {
+        self.menu_schedules.reload()?;
+        self.check_integrity();
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    fn load_admin_users(&self) -> Result<(), StorageError> {
+        let config = AdminConfig::load(&self.admin_config_path)?;
+        let mut admin_config = self
+            .admin_config
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        *admin_config = config;
+        log::debug!("Admin users loaded: {}", admin_config.admin_users.len());
+        Ok(())
+    }
+
+    fn load_app_settings(&self) -> Result<(), StorageError> {
+        let settings = AppSettings::load(&self.app_settings_path)?;
+        let mut app_settings = self
+            .app_settings
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        *app_settings = settings;
+        log::debug!("App settings reloaded from {}", self.app_settings_path);
+        Ok(())
+    }
+
+    fn load_validation_rules(&self) -> Result<(), StorageError> {
+        let rules = ValidationRules::load(&self.validation_rules_path)?;
+        let mut validation_rules = self
+            .validation_rules
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        *validation_rules = rules;
+        log::debug!(
+            "Validation rules reloaded from {}",
+            self.validation_rules_path
+        );
+        Ok(())
+    }
+
+    fn wrote_file(&self, path: &Path) -> bool {
+        self.own_writes.matches(path)
+    }
+
+    // Public getters
+    fn get_menu_items(&self) -> Result<Vec<MenuItem>, StorageError> {
+        self.menu_items.all()
+    }
+
+    fn get_menu_item_by_id(&self, id: Uuid) -> Result<Option<MenuItem>, StorageError> {
+        self.menu_items.get(id)
+    }
+
+    fn get_notices(&self) -> Result<Vec<Notice>, StorageError> {
+        self.notices.all()
+    }
+
+    fn get_notice_by_id(&self, id: Uuid) -> Result<Option<Notice>, StorageError> {
+        self.notices.get(id)
+    }
+
+    fn get_menu_presets(&self) -> Result<Vec<MenuPreset>, StorageError> {
+        self.menu_presets.all()
+    }
+
+    fn get_menu_preset_by_id(&self, id: Uuid) -> Result<Option<MenuPreset>, StorageError> {
+        self.menu_presets.get(id)
+    }
+
+    fn get_menu_schedules(&self) -> Result<Vec<MenuSchedule>, StorageError> {
+        self.menu_schedules.all()
+    }
+
+    fn get_menu_schedule_by_id(&self, id: Uuid) -> Result<Option<MenuSchedule>, StorageError> {
+        self.menu_schedules.get(id)
+    }
+
+    fn get_admin_users(&self) -> Result<Vec<AdminUser>, StorageError> {
+        Ok(self.get_admin_config()?.admin_users)
+    }
+
+    fn get_admin_user_by_username(
+        &self,
+        username: &str,
+    ) -> Result<Option<AdminUser>, StorageError> {
+        let config = self
+            .admin_config
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(config
+            .admin_users
+            .iter()
+            .find(|user| user.username == username)
+            .cloned())
+    }
+
+    fn get_app_settings(&self) -> Result<AppSettings, StorageError> {
+        let settings = self
+            .app_settings
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(settings.clone())
+    }
+
+    fn get_validation_rules(&self) -> Result<ValidationRules, StorageError> {
+        let rules = self
+            .validation_rules
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(rules.clone())
+    }
+
+    // Menu items
+    fn add_menu_item(&self, item: MenuItem) -> Result<(), StorageError> {
+        self.menu_items.insert(item)
+    }
+
+    fn update_menu_item(
+        &self,
+        id: Uuid,
+        updated_item: MenuItem,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.menu_items.update(id, updated_item, expected_version)
+    }
+
+    fn delete_menu_item(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_item(self, id, expected_version, deleted, || {
+            self.menu_items
+                .set_trashed(id, expected_version, Some(deleted))
+                .map(|_| ())
+        })
+    }
+
+    // Notices
+    fn add_notice(&self, notice: Notice) -> Result<(), StorageError> {
+        self.notices.insert(notice)
+    }
+
+    fn update_notice(
+        &self,
+        id: Uuid,
+        updated_notice: Notice,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        self.notices.update(id, updated_notice, expected_version)
+    }
+
+    fn delete_notice(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.notices
+            .set_trashed(id, expected_version, Some(deleted))
+            .map(|_| ())
+    }
+
+    // Menu presets
+    fn add_menu_preset(&self, preset: MenuPreset) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_preset(self, &preset, None)?;
+        self.menu_presets.insert(preset)
+    }
+
+    fn update_menu_preset(
+        &self,
+        id: Uuid,
+        updated_preset: MenuPreset,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.menu_presets.get(id)?;
+        integrity::check_preset(self, &updated_preset, previous.as_ref())?;
+        let version = self
+            .menu_presets
+            .update(id, updated_preset, expected_version)?;
+        self.check_integrity();
+        Ok(version)
+    }
+
+    fn delete_menu_preset(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::delete_menu_preset(self, id, expected_version, deleted, || {
+            self.menu_presets
+                .set_trashed(id, expected_version, Some(deleted))
+                .map(|_| ())
+        })?;
+        self.check_integrity();
+        Ok(())
+    }
+
+    // Menu schedules
+    fn add_menu_schedule(&self, schedule: MenuSchedule) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_schedule(self, &schedule, None)?;
+        self.menu_schedules.insert(schedule)?;
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    fn update_menu_schedule(
+        &self,
+        id: Uuid,
+        updated_schedule: MenuSchedule,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        let previous = self.menu_schedules.get(id)?;
+        integrity::check_schedule(self, &updated_schedule, previous.as_ref())?;
+        let version = self
+            .menu_schedules
+            .update(id, updated_schedule, expected_version)?;
+        self.check_integrity();
+        self.schedule_changes.notify();
+        Ok(version)
+    }
+
+    fn delete_menu_schedule(
+        &self,
+        id: Uuid,
+        expected_version: u64,
+        deleted: Change<'_>,
+    ) -> Result<(), StorageError> {
+        self.menu_schedules
+            .set_trashed(id, expected_version, Some(deleted))?;
+        self.check_integrity();
+        self.schedule_changes.notify();
+        Ok(())
+    }
+
+    // Trash
+    fn get_trash(&self) -> Result<Trash, StorageError> {
+        Ok(Trash {
+            menu_items: self.menu_items.trashed()?,
+            notices: self.notices.trashed()?,
+            menu_presets: self.menu_presets.trashed()?,
+            menu_schedules: self.menu_schedules.trashed()?,
+        })
+    }
+
+    fn restore_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<u64, StorageError> {
+        let _references = self.references.hold()?;
+        integrity::check_restore(self, kind, id)?;
+        let version = match kind {
+            DataKind::MenuItems => self.menu_items.set_trashed(id, expected_version, None),
+            DataKind::Notices => self.notices.set_trashed(id, expected_version, None),
+            DataKind::MenuPresets => self.menu_presets.set_trashed(id, expected_version, None),
+            DataKind::MenuSchedules => self.menu_schedules.set_trashed(id, expected_version, None),
+        }?;
+        self.check_integrity();
+        if kind == DataKind::MenuSchedules {
+            self.schedule_changes.notify();
+        }
+        Ok(version)
+    }
+
+    fn purge_from_trash(
+        &self,
+        kind: DataKind,
+        id: Uuid,
+        expected_version: u64,
+    ) -> Result<(), StorageError> {
+        match kind {
+            DataKind::MenuItems => self.menu_items.purge(id, expected_version),
+            DataKind::Notices => self.notices.purge(id, expected_version),
+            DataKind::MenuPresets => self.menu_presets.purge(id, expected_version),
+            DataKind::MenuSchedules => self.menu_schedules.purge(id, expected_version),
+        }
+    }
+
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        Ok(self.menu_items.purge_before(cutoff)?
+            + self.notices.purge_before(cutoff)?
+            + self.menu_presets.purge_before(cutoff)?
+            + self.menu_schedules.purge_before(cutoff)?)
+    }
+
+    // Schedule changes
+    fn subscribe_schedule_changes(&self) -> watch::Receiver<()> {
+        self.schedule_changes.subscribe()
+    }
+
+    // Change history
+    fn append_revision(&self, revision: Revision) -> Result<(), StorageError> {
+        let path = self.history.lock().map_err(|_| StorageError::PoisonError)?;
+        append_json_line(&path, &revision)
+    }
+
+    fn get_revisions(
+        &self,
+        kind: DataKind,
+        record_id: Uuid,
+    ) -> Result<Vec<Revision>, StorageError> {
+        let path = self.history.lock().map_err(|_| StorageError::PoisonError)?;
+        read_json_lines(&path, |revision: &Revision| {
+            revision.kind == kind && revision.record_id == record_id
+        })
+    }
+
+    // Schedule runs
+    fn append_schedule_run(&self, run: ScheduleRun) -> Result<(), StorageError> {
+        let path = self
+            .schedule_runs
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        append_json_line(&path, &run)
+    }
+
+    fn get_schedule_runs(&self, schedule_id: Uuid) -> Result<Vec<ScheduleRun>, StorageError> {
+        let path = self
+            .schedule_runs
+            .lock()
+            .map_err(|_| StorageError::PoisonError)?;
+        read_json_lines(&path, |run: &ScheduleRun| run.schedule_id == schedule_id)
+    }
+
+    // Admin users
+    fn add_admin_user(&self, user: AdminUser) -> Result<(), StorageError> {
+        let mut config = self
+            .admin_config
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        if config
+            .admin_users
+            .iter()
+            .any(|u| u.username == user.username)
+        {
+            return Err(StorageError::Validation(format!(
+                "Duplicate username found: {}",
+                user.username
+            )));
+        }
+        config.admin_users.push(user);
+        config.metadata.last_modified = Some(self.clock.now().to_rfc3339());
+        let content = Self::save_admin_config(&self.admin_config_path, &config)?;
+        self.own_writes
+            .record(&self.admin_config_path, content.as_bytes());
+        Ok(())
+    }
+
+    // Backups
+    fn snapshot(&self, now: DateTime<Utc>) -> Result<StorageSnapshot, StorageError> {
+        // Hold every read lock together so the copy is consistent across collections
+        let menu_items = self
+            .menu_items
+            .data
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let notices = self
+            .notices
+            .data
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let menu_presets = self
+            .menu_presets
+            .data
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        let menu_schedules = self
+            .menu_schedules
+            .data
+            .read()
+            .map_err(|_| StorageError::PoisonError)?;
+        Ok(StorageSnapshot::new(
+            now,
+            menu_items.records.clone(),
+            notices.records.clone(),
+            menu_presets.records.clone(),
+            menu_schedules.records.clone(),
+        ))
+    }
+
+    fn restore_snapshot(&self, mut snapshot: StorageSnapshot) -> Result<(), StorageError> {
+        let _references = self.references.hold()?;
+        let mut menu_items = self
+            .menu_items
+            .data
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut notices = self
+            .notices
+            .data
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut menu_presets = self
+            .menu_presets
+            .data
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        let mut menu_schedules = self
+            .menu_schedules
+            .data
+            .write()
+            .map_err(|_| StorageError::PoisonError)?;
+        supersede_versions(&mut snapshot.menu_items, &menu_items.records);
+        supersede_versions(&mut snapshot.notices, &notices.records);
+        supersede_versions(&mut snapshot.menu_presets, &menu_presets.records);
+        supersede_versions(&mut snapshot.menu_schedules, &menu_schedules.records);
+
+        // A failure before the journal is written leaves both files and memory untouched
+        self.restore_journal.begin(&[
+            self.menu_items.render(&snapshot.menu_items)?,
+            self.notices.render(&snapshot.notices)?,
+            self.menu_presets.render(&snapshot.menu_presets)?,
+            self.menu_schedules.render(&snapshot.menu_schedules)?,
+        ])?;
+
+        // From here on the restore is rolled forward, by the next write if not now
+        *menu_items = IndexedRecords::new(snapshot.menu_items);
+        *notices = IndexedRecords::new(snapshot.notices);
+        *menu_presets = IndexedRecords::new(snapshot.menu_presets);
+        *menu_schedules = IndexedRecords::new(snapshot.menu_schedules);
+        drop((menu_items, notices, menu_presets, menu_schedules));
+        self.schedule_changes.notify();
+        match self.restore_journal.finish() {
+            Ok(()) => log::info!("Restored snapshot taken at {}", snapshot.created_at),
+            Err(e) => log::error!(
+                "Restored snapshot taken at {}, but its files are left for the next write to finish: {}",
+                snapshot.created_at,
+                e
+            ),
+        }
+
+        self.check_integrity();
+        Ok(())
+    }
+}
+
+fn temp_path(path: &str) -> String {
+    format!("{}.tmp", path)
+}
+
+fn backup_path(path: &str) -> String {
+    format!("{}.bak", path)
+}
+
+/// Replace `path` with `contents` so that a crash leaves either the old or the new file.
+///
+/// The data is written to `<path>.tmp` and fsynced, the current file is copied to
+/// `<path>.bak`, and the temp file is renamed over the original.
+pub fn write_atomic(path: &str, contents: &[u8]) -> Result<(), StorageError> {
+    stage_write(path, contents)?;
+    commit_write(path)
+}
+
+/// Write and fsync `<path>.tmp` without touching the live file
+fn stage_write(path: &str, contents: &[u8]) -> Result<(), StorageError> {
+    let mut file = fs::File::create(temp_path(path))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Move a staged `<path>.tmp` over `path`, keeping the old file as `<path>.bak`
+fn commit_write(path: &str) -> Result<(), StorageError> {
+    if Path::new(path).exists() {
+        fs::copy(path, backup_path(path))?;
+    }
+    fs::rename(temp_path(path), path)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = Path::new(path).parent() {
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Append `entry` to a log of one JSON value per line, fsyncing so the entry
+/// survives a crash
+fn append_json_line<T: Serialize>(path: &str, entry: &T) -> Result<(), StorageError> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(&line)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// The entries of a log written by `append_json_line` that `keep` accepts, in
+/// the order they were appended
+fn read_json_lines<T: DeserializeOwned>(
+    path: &str,
+    keep: impl Fn(&T) -> bool,
+) -> Result<Vec<T>, StorageError> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<T>(line) {
+            Ok(entry) if keep(&entry) => entries.push(entry),
+            Ok(_) => {}
+            // Only a crash mid-append can leave a partial line, so skip it
+            Err(e) => log::warn!("Skipping line {} of {}: {}", number + 1, path, e),
+        }
+    }
+    Ok(entries)
+}
+
+// Helper trait for items with IDs
+pub trait HasId {
+    fn get_id(&self) -> Uuid;
+}
 
 impl HasId for MenuItem {
     fn get_id(&self) -> Uuid {
@@ -288,4 +1809,831 @@ impl HasId for MenuSchedule {
     fn get_id(&self) -> Uuid {
         self.id
     }
-}
\ No newline at end of file
+}
+
+/// Records carrying an optimistic concurrency version, bumped on every write
+pub trait Versioned {
+    fn version(&self) -> u64;
+    fn set_version(&mut self, version: u64);
+}
+
+impl Versioned for MenuItem {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for Notice {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for MenuPreset {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl Versioned for MenuSchedule {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+/// Records that are moved to the trash on delete rather than removed
+pub trait Trashable {
+    fn deleted_at(&self) -> Option<DateTime<Utc>>;
+    /// Mark the record as deleted at a time by a user, or clear the mark with `None`
+    fn set_deleted(&mut self, deleted: Option<(DateTime<Utc>, String)>);
+
+    fn is_trashed(&self) -> bool {
+        self.deleted_at().is_some()
+    }
+}
+
+impl Trashable for MenuItem {
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted(&mut self, deleted: Option<(DateTime<Utc>, String)>) {
+        (self.deleted_at, self.deleted_by) = deleted.unzip();
+    }
+}
+
+impl Trashable for Notice {
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted(&mut self, deleted: Option<(DateTime<Utc>, String)>) {
+        (self.deleted_at, self.deleted_by) = deleted.unzip();
+    }
+}
+
+impl Trashable for MenuPreset {
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted(&mut self, deleted: Option<(DateTime<Utc>, String)>) {
+        (self.deleted_at, self.deleted_by) = deleted.unzip();
+    }
+}
+
+impl Trashable for MenuSchedule {
+    fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    fn set_deleted(&mut self, deleted: Option<(DateTime<Utc>, String)>) {
+        (self.deleted_at, self.deleted_by) = deleted.unzip();
+    }
+}
+
+/// `NotFound` for a record missing from its collection, or from the trash
+pub fn not_found(label: &str, id: Uuid, in_trash: bool) -> StorageError {
+    let place = if in_trash { " in the trash" } else { "" };
+    StorageError::NotFound(format!("{} with id {} not found{}", label, id, place))
+}
+
+/// Version a write to `stored` should leave behind, or a `VersionConflict` if the
+/// writer last saw an older (or newer) version
+pub fn next_version<T: HasId + Versioned>(
+    label: &'static str,
+    stored: &T,
+    expected_version: u64,
+) -> Result<u64, StorageError> {
+    if stored.version() != expected_version {
+        return Err(StorageError::VersionConflict {
+            label,
+            id: stored.get_id(),
+            expected: expected_version,
+            current: stored.version(),
+        });
+    }
+    Ok(expected_version + 1)
+}
+
+/// Give every restored record a version above all of `current`, so an ETag taken
+/// before a restore can never match a restored record
+pub fn supersede_versions<T: Versioned>(restored: &mut [T], current: &[T]) {
+    let newest = current
+        .iter()
+        .map(Versioned::version)
+        .max()
+        .unwrap_or_default();
+    for record in restored {
+        record.set_version(newest.max(record.version()) + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ManualClock, SystemClock};
+    use crate::history::RevisionAction;
+    use crate::storage::MenuCategory;
+    use std::path::PathBuf;
+
+    /// Create a scratch data/config directory pair with the shipped settings.toml
+    fn scratch_dirs() -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("platter-storage-{}", Uuid::new_v4()));
+        let data_dir = root.join("data");
+        let config_dir = root.join("config");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::copy("config/settings.toml", config_dir.join("settings.toml")).unwrap();
+        fs::copy("config/validation.toml", config_dir.join("validation.toml")).unwrap();
+        (data_dir, config_dir)
+    }
+
+    fn open(data_dir: &Path, config_dir: &Path) -> HybridStorage {
+        HybridStorage::new(
+            data_dir.to_str().unwrap(),
+            config_dir.to_str().unwrap(),
+            Arc::new(SystemClock),
+        )
+        .unwrap()
+    }
+
+    fn sample_item(name: &str) -> MenuItem {
+        MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: MenuCategory::Mains,
+            description: "A test dish".to_string(),
+            allergens: vec!["milk".to_string()],
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    #[test]
+    fn test_legacy_array_file_is_upgraded_with_backup() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let item = sample_item("Legacy Lasagne");
+        let legacy = serde_json::to_string_pretty(&vec![item.clone()]).unwrap();
+        fs::write(data_dir.join("menu_items.json"), &legacy).unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, item.id);
+
+        let backup = fs::read_to_string(data_dir.join("menu_items.json.legacy.bak")).unwrap();
+        assert_eq!(backup, legacy);
+
+        let upgraded: JsonDataFile<MenuItem> =
+            serde_json::from_str(&fs::read_to_string(data_dir.join("menu_items.json")).unwrap())
+                .unwrap();
+        assert_eq!(upgraded.schema_version, SCHEMA_VERSION);
+        assert_eq!(upgraded.metadata.total_items, Some(1));
+        assert_eq!(upgraded.items[0].id, item.id);
+    }
+
+    #[test]
+    fn test_enveloped_files_load_without_migration() {
+        let (data_dir, config_dir) = scratch_dirs();
+        fs::copy(
+            "data/menu_schedules.json",
+            data_dir.join("menu_schedules.json"),
+        )
+        .unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        assert!(storage.get_menu_schedules().unwrap().is_empty());
+        assert!(!data_dir.join("menu_schedules.json.legacy.bak").exists());
+    }
+
+    #[test]
+    fn test_legacy_admin_users_are_moved_to_admin_toml() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let user_id = Uuid::new_v4();
+        let legacy = serde_json::json!([{
+            "id": user_id,
+            "username": "cook",
+            "password_hash": "$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"
+        }]);
+        fs::write(data_dir.join("admin_users.json"), legacy.to_string()).unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        let user = storage.get_admin_user_by_username("cook").unwrap().unwrap();
+        assert_eq!(user.id, user_id);
+        assert!(user.is_active);
+        assert_eq!(user.roles, vec!["super_admin".to_string()]);
+        assert!(config_dir.join("admin.toml").exists());
+        assert!(!data_dir.join("admin_users.json").exists());
+        assert!(data_dir.join("admin_users.json.legacy.bak").exists());
+
+        // admin.toml is now the source of truth
+        let reopened = open(&data_dir, &config_dir);
+        assert_eq!(reopened.get_admin_users().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_crud_keeps_index_and_file_in_sync() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let first = sample_item("Fish Pie");
+        let second = sample_item("Shepherd's Pie");
+
+        storage.add_menu_item(first.clone()).unwrap();
+        storage.add_menu_item(second.clone()).unwrap();
+        assert!(matches!(
+            storage.add_menu_item(first.clone()),
+            Err(StorageError::Validation(_))
+        ));
+
+        let mut renamed = second.clone();
+        renamed.name = "Cottage Pie".to_string();
+        storage.update_menu_item(second.id, renamed, 1).unwrap();
+
+        storage
+            .delete_menu_item(first.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+        assert!(storage.get_menu_item_by_id(first.id).unwrap().is_none());
+        assert_eq!(
+            storage
+                .get_menu_item_by_id(second.id)
+                .unwrap()
+                .unwrap()
+                .name,
+            "Cottage Pie"
+        );
+        assert!(matches!(
+            storage.delete_menu_item(first.id, 1, Change::new("admin", Utc::now())),
+            Err(StorageError::NotFound(_))
+        ));
+
+        let reopened = open(&data_dir, &config_dir);
+        let items = reopened.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Cottage Pie");
+    }
+
+    #[test]
+    fn test_file_metadata_and_snapshots_are_stamped_by_the_clock() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let start = DateTime::parse_from_rfc3339("2025-11-03T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = Arc::new(ManualClock::new(start));
+        let storage = HybridStorage::new(
+            data_dir.to_str().unwrap(),
+            config_dir.to_str().unwrap(),
+            clock.clone(),
+        )
+        .unwrap();
+
+        let later = start + chrono::Duration::hours(1);
+        clock.set(later);
+        storage.add_menu_item(sample_item("Fish Pie")).unwrap();
+        storage
+            .add_admin_user(AdminUser {
+                id: Uuid::new_v4(),
+                username: "alice".to_string(),
+                password_hash: String::new(),
+                created_at: Some(later),
+                last_login: None,
+                roles: vec!["super_admin".to_string()],
+                is_active: true,
+            })
+            .unwrap();
+
+        let file: JsonDataFile<MenuItem> =
+            serde_json::from_str(&fs::read_to_string(data_dir.join("menu_items.json")).unwrap())
+                .unwrap();
+        assert_eq!(file.last_updated, later.to_rfc3339());
+        let config = AdminConfig::load(config_dir.join("admin.toml")).unwrap();
+        assert_eq!(config.metadata.last_modified, Some(later.to_rfc3339()));
+        assert_eq!(storage.snapshot(start).unwrap().created_at, start);
+    }
+
+    #[test]
+    fn test_stale_versions_are_rejected() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let item = sample_item("Beef Wellington");
+        storage.add_menu_item(item.clone()).unwrap();
+
+        let mut first_edit = item.clone();
+        first_edit.name = "Mushroom Wellington".to_string();
+        assert_eq!(storage.update_menu_item(item.id, first_edit, 1).unwrap(), 2);
+
+        let mut second_edit = item.clone();
+        second_edit.is_available = false;
+        assert!(matches!(
+            storage.update_menu_item(item.id, second_edit, 1),
+            Err(StorageError::VersionConflict {
+                expected: 1,
+                current: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            storage.delete_menu_item(item.id, 1, Change::new("admin", Utc::now())),
+            Err(StorageError::VersionConflict { .. })
+        ));
+
+        let stored = storage.get_menu_item_by_id(item.id).unwrap().unwrap();
+        assert_eq!(stored.name, "Mushroom Wellington");
+        assert!(stored.is_available);
+        assert_eq!(stored.version, 2);
+        storage
+            .delete_menu_item(item.id, 2, Change::new("admin", Utc::now()))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_failed_writes_leave_memory_unchanged() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let kept = sample_item("Shepherd's Pie");
+        let trashed = sample_item("Toad in the Hole");
+        storage.add_menu_item(kept.clone()).unwrap();
+        storage.add_menu_item(trashed.clone()).unwrap();
+        storage
+            .delete_menu_item(trashed.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+
+        // Root ignores the read-only bit, so also block the temp file every write stages
+        fs::create_dir(data_dir.join("menu_items.json.tmp")).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o555)).unwrap();
+        }
+
+        let mut edit = kept.clone();
+        edit.name = "Cottage Pie".to_string();
+        assert!(storage.add_menu_item(sample_item("Faggots")).is_err());
+        assert!(storage.update_menu_item(kept.id, edit, 1).is_err());
+        assert!(
+            storage
+                .delete_menu_item(kept.id, 1, Change::new("admin", Utc::now()))
+                .is_err()
+        );
+        assert!(
+            storage
+                .restore_from_trash(DataKind::MenuItems, trashed.id, 2)
+                .is_err()
+        );
+        assert!(
+            storage
+                .purge_from_trash(DataKind::MenuItems, trashed.id, 2)
+                .is_err()
+        );
+        assert!(storage.purge_trash_before(Utc::now()).is_err());
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Shepherd's Pie");
+        assert_eq!(items[0].version, 1);
+        let trash = storage.get_trash().unwrap();
+        assert_eq!(trash.menu_items.len(), 1);
+        assert_eq!(trash.menu_items[0].version, 2);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_revisions_are_appended_and_survive_a_restart() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let item = sample_item("Ploughman's Lunch");
+        let other = sample_item("Scotch Egg");
+        for (record, version) in [(&item, 1), (&other, 1), (&item, 2)] {
+            storage
+                .append_revision(Revision {
+                    id: Uuid::new_v4(),
+                    kind: DataKind::MenuItems,
+                    record_id: record.id,
+                    version,
+                    action: RevisionAction::Updated,
+                    changed_by: "admin".to_string(),
+                    changed_at: Utc::now(),
+                    changes: Vec::new(),
+                    record: serde_json::to_value(record).unwrap(),
+                })
+                .unwrap();
+        }
+
+        let reopened = open(&data_dir, &config_dir);
+        let versions: Vec<u64> = reopened
+            .get_revisions(DataKind::MenuItems, item.id)
+            .unwrap()
+            .iter()
+            .map(|r| r.version)
+            .collect();
+        assert_eq!(versions, vec![1, 2]);
+        assert!(
+            reopened
+                .get_revisions(DataKind::Notices, item.id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_revisions_that_cannot_be_written_are_reported() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let item = sample_item("Ploughman's Lunch");
+        storage.add_menu_item(item.clone()).unwrap();
+        fs::create_dir(data_dir.join(HISTORY_FILE)).unwrap();
+
+        let recorded = crate::history::record(
+            &storage,
+            DataKind::MenuItems,
+            item.id,
+            RevisionAction::Created,
+            None,
+            Some(&item),
+            Change::new("admin", Utc::now()),
+        );
+
+        assert!(recorded.is_err());
+    }
+
+    #[test]
+    fn test_schedule_runs_are_appended_and_survive_a_restart() {
+        use crate::runs::{RunOutcome, ScheduleRun};
+
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let (schedule_id, other_id) = (Uuid::new_v4(), Uuid::new_v4());
+        for (id, outcome) in [
+            (schedule_id, RunOutcome::Applied),
+            (other_id, RunOutcome::Skipped),
+            (schedule_id, RunOutcome::Failed),
+        ] {
+            storage
+                .append_schedule_run(ScheduleRun {
+                    id: Uuid::new_v4(),
+                    schedule_id: id,
+                    occurrence_start: Utc::now(),
+                    started_at: Utc::now(),
+                    preset_ids: Vec::new(),
+                    items_changed: Default::default(),
+                    outcome,
+                    error: None,
+                })
+                .unwrap();
+        }
+
+        let reopened = open(&data_dir, &config_dir);
+        let outcomes: Vec<RunOutcome> = reopened
+            .get_schedule_runs(schedule_id)
+            .unwrap()
+            .iter()
+            .map(|run| run.outcome)
+            .collect();
+        assert_eq!(outcomes, vec![RunOutcome::Applied, RunOutcome::Failed]);
+    }
+
+    #[test]
+    fn test_startup_scan_records_dangling_references_in_metadata() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let presets_path = data_dir.join("menu_presets.json");
+        let now = Utc::now();
+        let preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Orphaned".to_string(),
+            description: "Lists an item that was never saved".to_string(),
+            menu_item_ids: vec![Uuid::new_v4()],
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        HybridStorage::save_json_file(
+            presets_path.to_str().unwrap(),
+            std::slice::from_ref(&preset),
+            HybridStorage::menu_presets_metadata,
+            Utc::now(),
+        )
+        .unwrap();
+        let integrity_check = || {
+            let file: JsonDataFile<MenuPreset> =
+                serde_json::from_str(&fs::read_to_string(&presets_path).unwrap()).unwrap();
+            file.metadata.data_integrity_check
+        };
+
+        let storage = open(&data_dir, &config_dir);
+        assert!(integrity_check().starts_with("failed: preset 'Orphaned'"));
+
+        let fixed = MenuPreset {
+            menu_item_ids: Vec::new(),
+            scope: PresetScope::default(),
+            ..preset.clone()
+        };
+        storage.update_menu_preset(preset.id, fixed, 1).unwrap();
+        assert_eq!(integrity_check(), "passed");
+    }
+
+    #[test]
+    fn test_trashed_records_can_be_restored_or_purged() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let kept = sample_item("Bread and Butter Pudding");
+        let purged = sample_item("Spotted Dick");
+        storage.add_menu_item(kept.clone()).unwrap();
+        storage.add_menu_item(purged.clone()).unwrap();
+
+        storage
+            .delete_menu_item(kept.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+        storage
+            .delete_menu_item(purged.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+        assert!(storage.get_menu_items().unwrap().is_empty());
+        assert!(matches!(
+            storage.update_menu_item(kept.id, kept.clone(), 2),
+            Err(StorageError::NotFound(_))
+        ));
+
+        // The trash survives a restart
+        let reopened = open(&data_dir, &config_dir);
+        let trash = reopened.get_trash().unwrap();
+        assert_eq!(trash.menu_items.len(), 2);
+        assert_eq!(trash.menu_items[0].deleted_by.as_deref(), Some("admin"));
+        assert!(trash.menu_items[0].deleted_at.is_some());
+
+        assert_eq!(
+            reopened
+                .restore_from_trash(DataKind::MenuItems, kept.id, 2)
+                .unwrap(),
+            3
+        );
+        reopened
+            .purge_from_trash(DataKind::MenuItems, purged.id, 2)
+            .unwrap();
+        assert!(matches!(
+            reopened.purge_from_trash(DataKind::MenuItems, kept.id, 3),
+            Err(StorageError::NotFound(_))
+        ));
+
+        let restored = reopened.get_menu_item_by_id(kept.id).unwrap().unwrap();
+        assert!(restored.deleted_at.is_none() && restored.deleted_by.is_none());
+        assert!(reopened.get_trash().unwrap().menu_items.is_empty());
+        assert_eq!(reopened.snapshot(Utc::now()).unwrap().menu_items.len(), 1);
+    }
+
+    #[test]
+    fn test_writes_leave_no_temp_file_and_keep_last_good_backup() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let item = sample_item("Roast Chicken");
+
+        storage.add_menu_item(item.clone()).unwrap();
+
+        let live = data_dir.join("menu_items.json");
+        assert!(!data_dir.join("menu_items.json.tmp").exists());
+        let backup: JsonDataFile<MenuItem> = serde_json::from_str(
+            &fs::read_to_string(data_dir.join("menu_items.json.bak")).unwrap(),
+        )
+        .unwrap();
+        assert!(backup.items.is_empty());
+        let current: JsonDataFile<MenuItem> =
+            serde_json::from_str(&fs::read_to_string(live).unwrap()).unwrap();
+        assert_eq!(current.items[0].id, item.id);
+    }
+
+    #[test]
+    fn test_truncated_file_is_recovered_from_backup() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let item = sample_item("Vegetable Curry");
+        {
+            let storage = open(&data_dir, &config_dir);
+            storage.add_menu_item(item.clone()).unwrap();
+            // A second write makes the first one the last good backup
+            storage.add_menu_item(sample_item("Rice")).unwrap();
+        }
+        let live = data_dir.join("menu_items.json");
+        let full = fs::read_to_string(&live).unwrap();
+        fs::write(&live, &full[..full.len() / 2]).unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, item.id);
+        assert!(data_dir.join("menu_items.json.corrupt").exists());
+    }
+
+    #[test]
+    fn test_completed_temp_file_is_preferred_over_backup() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let item = sample_item("Apple Crumble");
+        {
+            let storage = open(&data_dir, &config_dir);
+            storage.add_menu_item(item.clone()).unwrap();
+        }
+        let live = data_dir.join("menu_items.json");
+        fs::copy(&live, data_dir.join("menu_items.json.tmp")).unwrap();
+        fs::write(&live, "").unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        assert_eq!(storage.get_menu_items().unwrap()[0].id, item.id);
+        assert!(!data_dir.join("menu_items.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_interrupted_temp_file_is_discarded_when_live_file_is_intact() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let item = sample_item("Tomato Soup");
+        {
+            let storage = open(&data_dir, &config_dir);
+            storage.add_menu_item(item.clone()).unwrap();
+        }
+        fs::write(data_dir.join("menu_items.json.tmp"), "{\"schema_ver").unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        assert_eq!(storage.get_menu_items().unwrap()[0].id, item.id);
+        assert!(!data_dir.join("menu_items.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_unrecoverable_file_refuses_to_start() {
+        let (data_dir, config_dir) = scratch_dirs();
+        fs::write(data_dir.join("notices.json"), "{\"items\": [").unwrap();
+
+        let result = HybridStorage::new(
+            data_dir.to_str().unwrap(),
+            config_dir.to_str().unwrap(),
+            Arc::new(SystemClock),
+        );
+
+        assert!(matches!(result, Err(StorageError::Unrecoverable { .. })));
+    }
+
+    #[test]
+    fn test_restore_snapshot_replaces_every_collection() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let kept = sample_item("Shepherd's Pie");
+        storage.add_menu_item(kept.clone()).unwrap();
+        let snapshot = storage.snapshot(Utc::now()).unwrap();
+        storage.add_menu_item(sample_item("Quiche")).unwrap();
+
+        storage.restore_snapshot(snapshot).unwrap();
+
+        assert_eq!(storage.get_menu_items().unwrap().len(), 1);
+        // Quiche was at version 1 too, so Shepherd's Pie moves past it
+        assert_eq!(storage.get_menu_items().unwrap()[0].version, 2);
+        assert!(!data_dir.join(RESTORE_JOURNAL_FILE).exists());
+        let reopened = open(&data_dir, &config_dir);
+        assert_eq!(reopened.get_menu_items().unwrap()[0].id, kept.id);
+    }
+
+    #[test]
+    fn test_interrupted_restore_is_rolled_forward() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let restored = sample_item("Bread and Butter Pudding");
+        {
+            let storage = open(&data_dir, &config_dir);
+            storage.add_menu_item(sample_item("Trifle")).unwrap();
+        }
+        // Simulate a crash after journaling but before any data file was written
+        let items_path = data_dir
+            .join("menu_items.json")
+            .to_string_lossy()
+            .to_string();
+        let journal = vec![JournaledFile {
+            path: items_path,
+            contents: HybridStorage::render_json_file(
+                std::slice::from_ref(&restored),
+                HybridStorage::menu_items_metadata,
+                Utc::now(),
+            )
+            .unwrap(),
+        }];
+        fs::write(
+            data_dir.join(RESTORE_JOURNAL_FILE),
+            serde_json::to_vec(&journal).unwrap(),
+        )
+        .unwrap();
+
+        let storage = open(&data_dir, &config_dir);
+
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, restored.id);
+        assert!(!data_dir.join(RESTORE_JOURNAL_FILE).exists());
+    }
+
+    #[test]
+    fn test_restore_failing_part_way_is_finished_before_the_next_write() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let storage = open(&data_dir, &config_dir);
+        let kept = sample_item("Shepherd's Pie");
+        storage.add_menu_item(kept.clone()).unwrap();
+        let notice = Notice {
+            id: Uuid::new_v4(),
+            title: "Closed on Friday".to_string(),
+            content: "The dining hall is closed for the open day".to_string(),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_notice(notice.clone()).unwrap();
+        let snapshot = storage.snapshot(Utc::now()).unwrap();
+        storage.add_menu_item(sample_item("Quiche")).unwrap();
+
+        // Menu items are written first, then notices.json cannot be replaced
+        let notices_path = data_dir.join("notices.json");
+        fs::remove_file(&notices_path).unwrap();
+        fs::create_dir(&notices_path).unwrap();
+        storage.restore_snapshot(snapshot).unwrap();
+
+        // The restore stands once journaled: memory holds the whole snapshot, and
+        // writes wait for the files
+        let items = storage.get_menu_items().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, kept.id);
+        assert!(data_dir.join(RESTORE_JOURNAL_FILE).exists());
+        assert!(storage.add_menu_item(sample_item("Trifle")).is_err());
+
+        fs::remove_dir(&notices_path).unwrap();
+        let added = sample_item("Apple Crumble");
+        storage.add_menu_item(added.clone()).unwrap();
+        assert!(!data_dir.join(RESTORE_JOURNAL_FILE).exists());
+
+        let reopened = open(&data_dir, &config_dir);
+        let ids: Vec<Uuid> = reopened
+            .get_menu_items()
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        assert!(ids.contains(&kept.id) && ids.contains(&added.id));
+        assert!(
+            !reopened
+                .get_menu_items()
+                .unwrap()
+                .iter()
+                .any(|item| item.name == "Quiche")
+        );
+        let notices = reopened.get_notices().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].id, notice.id);
+    }
+
+    #[test]
+    fn test_newer_schema_refuses_to_start_and_leaves_file_alone() {
+        let (data_dir, config_dir) = scratch_dirs();
+        let newer = r#"{"schema_version": "99.0.0", "items": []}"#;
+        fs::write(data_dir.join("notices.json"), newer).unwrap();
+        fs::write(data_dir.join("notices.json.bak"), "[]").unwrap();
+
+        let result = HybridStorage::new(
+            data_dir.to_str().unwrap(),
+            config_dir.to_str().unwrap(),
+            Arc::new(SystemClock),
+        );
+
+        assert!(matches!(
+            result,
+            Err(StorageError::UnsupportedSchemaVersion { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(data_dir.join("notices.json")).unwrap(),
+            newer
+        );
+    }
+}
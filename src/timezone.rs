@@ -0,0 +1,133 @@
+//! Wall-clock times in the configured `localization.timezone`.
+//!
+//! Schedules are stored as UTC instants, but admins enter them and expect them to
+//! repeat in local time, so breakfast stays at 07:30 when the clocks change.
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+/// The instant a wall-clock time in `tz` refers to.
+///
+/// A time skipped when the clocks go forward is read with the offset from before
+/// the change, as RFC 5545 does, so 01:30 on the spring-forward night in London is
+/// 02:30 BST. A time that happens twice when the clocks go back is the first one.
+pub fn resolve(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => time.with_timezone(&Utc),
+        LocalResult::Ambiguous(first, _) => first.with_timezone(&Utc),
+        LocalResult::None => {
+            let before = tz
+                .offset_from_utc_datetime(&(local - Duration::days(1)))
+                .fix();
+            (local - Duration::seconds(before.local_minus_utc().into())).and_utc()
+        }
+    }
+}
+
+/// The wall-clock time in `tz` at `time`
+pub fn local(tz: Tz, time: DateTime<Utc>) -> NaiveDateTime {
+    time.with_timezone(&tz).naive_local()
+}
+
+/// A schedule time sent to the API: an RFC 3339 instant such as
+/// `2025-09-08T12:00:00Z`, or a wall-clock time such as `2025-09-08T12:00` that is
+/// read in the configured timezone
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TimeInput {
+    Instant(DateTime<Utc>),
+    Local(NaiveDateTime),
+}
+
+impl TimeInput {
+    pub fn resolve(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            TimeInput::Instant(time) => time,
+            TimeInput::Local(local) => resolve(tz, local),
+        }
+    }
+}
+
+impl TryFrom<String> for TimeInput {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(&value) {
+            return Ok(TimeInput::Instant(time.with_timezone(&Utc)));
+        }
+        ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+            .map(TimeInput::Local)
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a time such as 2025-09-08T12:00 or 2025-09-08T12:00:00Z",
+                    value
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn london(text: &str) -> DateTime<Utc> {
+        let local = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        resolve(chrono_tz::Europe::London, local)
+    }
+
+    #[test]
+    fn test_wall_clock_times_follow_daylight_saving() {
+        assert_eq!(
+            london("2025-03-29 07:30").to_rfc3339(),
+            "2025-03-29T07:30:00+00:00"
+        );
+        assert_eq!(
+            london("2025-03-30 07:30").to_rfc3339(),
+            "2025-03-30T06:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_skipped_times_move_past_the_gap() {
+        // Clocks go from 01:00 GMT to 02:00 BST on 2025-03-30
+        assert_eq!(
+            london("2025-03-30 01:30").to_rfc3339(),
+            "2025-03-30T01:30:00+00:00"
+        );
+        assert_eq!(
+            local(chrono_tz::Europe::London, london("2025-03-30 01:30")).to_string(),
+            "2025-03-30 02:30:00"
+        );
+    }
+
+    #[test]
+    fn test_repeated_times_take_the_first() {
+        // 01:30 happens in BST and then again in GMT on 2025-10-26
+        assert_eq!(
+            london("2025-10-26 01:30").to_rfc3339(),
+            "2025-10-26T00:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_inputs_with_an_offset_are_exact() {
+        let tz = chrono_tz::Europe::London;
+        let parse = |text: &str| TimeInput::try_from(text.to_string());
+
+        assert_eq!(
+            parse("2025-07-01T12:00:00+02:00")
+                .unwrap()
+                .resolve(tz)
+                .to_rfc3339(),
+            "2025-07-01T10:00:00+00:00"
+        );
+        assert_eq!(
+            parse("2025-07-01T12:00").unwrap().resolve(tz).to_rfc3339(),
+            "2025-07-01T11:00:00+00:00"
+        );
+        assert!(parse("next Tuesday").is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use actix_web::web::Data;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::storage_v2::{Storage, StorageError};
+
+/// How often the trash is checked for records past their retention period
+const PURGE_INTERVAL: Duration = Duration::hours(1);
+
+/// Permanently remove records that were trashed more than `retention_days` before `now`
+pub fn purge_expired(
+    storage: &dyn Storage,
+    retention_days: u32,
+    now: DateTime<Utc>,
+) -> Result<usize, StorageError> {
+    let cutoff = now - Duration::days(i64::from(retention_days));
+    storage.purge_trash_before(cutoff)
+}
+
+/// Starts the background task that empties the trash after `trash_retention_days`
+pub async fn start_trash_purge_task(storage: Data<dyn Storage>, clock: Data<dyn Clock>) {
+    let retention_days = match storage.get_app_settings() {
+        Ok(settings) => settings.storage.trash_retention_days,
+        Err(e) => {
+            error!("Cannot start trash purge task: {}", e);
+            return;
+        }
+    };
+
+    info!(
+        "Starting trash purge task (keeping deleted records for {} days)",
+        retention_days
+    );
+    tokio::spawn(async move {
+        loop {
+            let now = clock.now();
+            match purge_expired(storage.get_ref(), retention_days, now) {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired records from the trash", purged),
+                Err(e) => warn!("Purging the trash failed: {}", e),
+            }
+            clock.sleep_until(now + PURGE_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppSettings, ValidationRules};
+    use crate::history::Change;
+    use crate::memory_storage::MemoryStorage;
+    use crate::storage::{MenuCategory, MenuItem};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_only_records_past_retention_are_purged() {
+        let storage = MemoryStorage::new(
+            AppSettings::load("config/settings.toml").unwrap(),
+            ValidationRules::load("config/validation.toml").unwrap(),
+        );
+        let item = MenuItem {
+            id: Uuid::new_v4(),
+            name: "Soup of the Day".to_string(),
+            category: MenuCategory::Mains,
+            description: "A test dish".to_string(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_item(item.clone()).unwrap();
+        storage
+            .delete_menu_item(item.id, 1, Change::new("admin", Utc::now()))
+            .unwrap();
+
+        assert_eq!(purge_expired(&storage, 30, Utc::now()).unwrap(), 0);
+        assert_eq!(storage.get_trash().unwrap().menu_items.len(), 1);
+
+        let later = Utc::now() + Duration::days(31);
+        assert_eq!(purge_expired(&storage, 30, later).unwrap(), 1);
+        assert!(storage.get_trash().unwrap().menu_items.is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use actix_web::web::Data;
+use log::{debug, error, info, warn};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::storage_v2::{Storage, StorageError};
+
+/// Quiet period after the last event before changed files are reloaded, so an
+/// editor that saves in several steps only triggers one reload
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// A file the watcher knows how to reload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum WatchedFile {
+    MenuItems,
+    Notices,
+    MenuPresets,
+    MenuSchedules,
+    AdminUsers,
+    AppSettings,
+    ValidationRules,
+}
+
+impl WatchedFile {
+    fn reload(self, storage: &dyn Storage) -> Result<(), StorageError> {
+        match self {
+            WatchedFile::MenuItems => storage.load_menu_items(),
+            WatchedFile::Notices => storage.load_notices(),
+            WatchedFile::MenuPresets => storage.load_menu_presets(),
+            WatchedFile::MenuSchedules => storage.load_menu_schedules(),
+            WatchedFile::AdminUsers => storage.load_admin_users(),
+            WatchedFile::AppSettings => storage.load_app_settings(),
+            WatchedFile::ValidationRules => storage.load_validation_rules(),
+        }
+    }
+}
+
+/// Maps `(directory, file name)` pairs to the collection they hold
+struct WatchedPaths {
+    files: Vec<(PathBuf, String, WatchedFile)>,
+}
+
+impl WatchedPaths {
+    fn new(
+        storage: &dyn Storage,
+        data_dir: &Path,
+        config_dir: &Path,
+    ) -> Result<Self, StorageError> {
+        let settings = storage.get_app_settings()?.storage;
+        let data_dir = fs::canonicalize(data_dir)?;
+        let config_dir = fs::canonicalize(config_dir)?;
+        let in_data = |name: String, file| (data_dir.clone(), name, file);
+        let in_config = |name: &str, file| (config_dir.clone(), name.to_string(), file);
+        Ok(Self {
+            files: vec![
+                in_data(settings.menu_items_file, WatchedFile::MenuItems),
+                in_data(settings.notices_file, WatchedFile::Notices),
+                in_data(settings.menu_presets_file, WatchedFile::MenuPresets),
+                in_data(settings.menu_schedules_file, WatchedFile::MenuSchedules),
+                in_config("admin.toml", WatchedFile::AdminUsers),
+                in_config("settings.toml", WatchedFile::AppSettings),
+                in_config("validation.toml", WatchedFile::ValidationRules),
+            ],
+        })
+    }
+
+    fn lookup(&self, path: &Path) -> Option<WatchedFile> {
+        // Temp, backup and journal files have other names and are never matched
+        let name = path.file_name()?.to_str()?;
+        let dir = fs::canonicalize(path.parent()?).ok()?;
+        self.files
+            .iter()
+            .find(|(file_dir, file_name, _)| *file_dir == dir && file_name == name)
+            .map(|(_, _, file)| *file)
+    }
+}
+
+/// Watches `data_dir` and `config_dir` (inotify on Linux) and reloads files that
+/// change on disk.
+///
+/// A file that fails to parse or validate is logged and ignored, so the previous
+/// state keeps being served. Reloading schedules wakes the scheduler through the
+/// storage's schedule change channel. Changes the storage made itself are skipped.
+pub fn start_file_watcher(
+    storage: Data<dyn Storage>,
+    data_dir: &str,
+    config_dir: &str,
+) -> Result<(), StorageError> {
+    let watched = WatchedPaths::new(
+        storage.get_ref(),
+        Path::new(data_dir),
+        Path::new(config_dir),
+    )?;
+
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| StorageError::Io(std::io::Error::other(e)))?;
+    for dir in [data_dir, config_dir] {
+        watcher
+            .watch(Path::new(dir), RecursiveMode::NonRecursive)
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))?;
+    }
+    info!("Watching {} and {} for changes", data_dir, config_dir);
+
+    thread::Builder::new()
+        .name("file-watcher".to_string())
+        .spawn(move || {
+            // The watcher stops when dropped, so it lives as long as this thread
+            let _watcher = watcher;
+            // The changed files, with a path to each
+            let mut pending = HashMap::new();
+            loop {
+                let received = if pending.is_empty() {
+                    rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                } else {
+                    rx.recv_timeout(DEBOUNCE)
+                };
+                match received {
+                    Ok(Ok(event)) => {
+                        if matches!(event.kind, EventKind::Access(_)) {
+                            continue;
+                        }
+                        pending.extend(
+                            event
+                                .paths
+                                .iter()
+                                .filter_map(|p| Some((watched.lookup(p)?, p.clone()))),
+                        );
+                    }
+                    Ok(Err(e)) => warn!("File watcher error: {}", e),
+                    Err(RecvTimeoutError::Timeout) => {
+                        for (file, path) in pending.drain() {
+                            if storage.wrote_file(&path) {
+                                debug!("Skipping {:?}, which holds the storage's own write", file);
+                                continue;
+                            }
+                            reload_file(storage.get_ref(), file);
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            debug!("File watcher stopped");
+        })?;
+    Ok(())
+}
+
+fn reload_file(storage: &dyn Storage, file: WatchedFile) {
+    match file.reload(storage) {
+        Ok(()) => info!("Reloaded {:?} after a change on disk", file),
+        Err(e) => error!(
+            "Ignoring change to {:?}, still serving the previous version: {}",
+            file, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::storage::{
+        MenuCategory, MenuItem, MenuPreset, MenuSchedule, PresetScope, ScheduleRecurrence,
+        ScheduleStatus,
+    };
+    use crate::storage_v2::HybridStorage;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use std::sync::Arc;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    fn start() -> (Data<dyn Storage>, PathBuf) {
+        let root = std::env::temp_dir().join(format!("platter-watcher-{}", Uuid::new_v4()));
+        let data_dir = root.join("data");
+        let config_dir = root.join("config");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::copy("config/settings.toml", config_dir.join("settings.toml")).unwrap();
+        fs::copy("config/validation.toml", config_dir.join("validation.toml")).unwrap();
+        let (data_dir, config_dir) = (data_dir.to_str().unwrap(), config_dir.to_str().unwrap());
+
+        let storage: Data<dyn Storage> = Data::from(Arc::new(
+            HybridStorage::new(data_dir, config_dir, Arc::new(SystemClock)).unwrap(),
+        ) as Arc<dyn Storage>);
+        start_file_watcher(storage.clone(), data_dir, config_dir).unwrap();
+        (storage, PathBuf::from(data_dir))
+    }
+
+    fn item_file(name: &str) -> (Uuid, String) {
+        let item = MenuItem {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: MenuCategory::Beverages,
+            description: "A test drink".to_string(),
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            is_available: true,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        let file = serde_json::json!({
+            "schema_version": crate::storage_v2::SCHEMA_VERSION,
+            "last_updated": "2025-10-04T15:00:00Z",
+            "generated_by": "hand-edited",
+            "metadata": { "data_integrity_check": "passed" },
+            "items": [item],
+        });
+        (item.id, file.to_string())
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_edited_data_file_is_reloaded() {
+        let (storage, data_dir) = start();
+        let (id, content) = item_file("Hot Chocolate");
+
+        fs::write(data_dir.join("menu_items.json"), content).unwrap();
+
+        assert!(wait_for(|| storage
+            .get_menu_item_by_id(id)
+            .unwrap()
+            .is_some()));
+    }
+
+    #[test]
+    fn test_invalid_edit_keeps_previous_state() {
+        let (storage, data_dir) = start();
+        let (id, content) = item_file("Orange Juice");
+        fs::write(data_dir.join("menu_items.json"), content).unwrap();
+        assert!(wait_for(|| storage
+            .get_menu_item_by_id(id)
+            .unwrap()
+            .is_some()));
+
+        fs::write(data_dir.join("menu_items.json"), r#"{"schema_version": "#).unwrap();
+        thread::sleep(DEBOUNCE * 4);
+
+        assert!(storage.get_menu_item_by_id(id).unwrap().is_some());
+        // The broken file is left for the editor to fix rather than replaced from backup
+        assert!(!data_dir.join("menu_items.json.corrupt").exists());
+    }
+
+    #[test]
+    fn test_own_writes_are_not_reloaded() {
+        let (storage, data_dir) = start();
+        let mut changes = storage.subscribe_schedule_changes();
+        let now = Utc::now();
+        let preset = MenuPreset {
+            id: Uuid::new_v4(),
+            name: "Lunch".to_string(),
+            description: "Weekday lunch".to_string(),
+            menu_item_ids: Vec::new(),
+            scope: PresetScope::default(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+            deleted_by: None,
+        };
+        storage.add_menu_preset(preset.clone()).unwrap();
+        storage
+            .add_menu_schedule(MenuSchedule {
+                id: Uuid::new_v4(),
+                preset_id: preset.id,
+                fallback_preset_id: None,
+                name: "Lunch Service".to_string(),
+                description: "Weekday lunch".to_string(),
+                start_time: now,
+                end_time: now + ChronoDuration::hours(2),
+                series_start: now,
+                series_end: None,
+                recurrence: ScheduleRecurrence::Daily,
+                rrule: None,
+                exception_dates: Vec::new(),
+                priority: 0,
+                status: ScheduleStatus::Pending,
+                error_message: None,
+                conflict_decision: None,
+                retry_count: 0,
+                next_retry_at: None,
+                availability_snapshot: None,
+                created_at: now,
+                updated_at: now,
+                version: 1,
+                deleted_at: None,
+                deleted_by: None,
+            })
+            .unwrap();
+        changes.borrow_and_update();
+
+        // Reloading the schedules would wake their subscribers a second time
+        thread::sleep(DEBOUNCE * 4);
+        assert!(!changes.has_changed().unwrap());
+
+        let path = data_dir.join("menu_schedules.json");
+        let edited = fs::read_to_string(&path)
+            .unwrap()
+            .replace("Lunch Service", "Dinner Service");
+        fs::write(&path, edited).unwrap();
+        assert!(wait_for(|| changes.has_changed().unwrap()));
+        assert_eq!(
+            storage.get_menu_schedules().unwrap()[0].name,
+            "Dinner Service"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Integration tests that step a manual clock through a schedule's lifecycle
+
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use actix_web::web::Data;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use uuid::Uuid;
+
+use platter::clock::{Clock, ManualClock};
+use platter::config::{AppSettings, ValidationRules};
+use platter::history::Change;
+use platter::leader::LeaderLock;
+use platter::memory_storage::MemoryStorage;
+use platter::migrations::DataKind;
+use platter::runs::RunOutcome;
+use platter::scheduler::start_scheduler;
+use platter::storage::{
+    MenuCategory, MenuItem, MenuPreset, MenuSchedule, PresetScope, ScheduleRecurrence,
+    ScheduleStatus,
+};
+use platter::storage_v2::Storage;
+use platter::trash::start_trash_purge_task;
+
+fn storage() -> Data<dyn Storage> {
+    Data::from(Arc::new(MemoryStorage::new(
+        AppSettings::load("config/settings.toml").unwrap(),
+        ValidationRules::load("config/validation.toml").unwrap(),
+    )) as Arc<dyn Storage>)
+}
+
+fn item(name: &str, is_available: bool) -> MenuItem {
+    MenuItem {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        category: MenuCategory::Mains,
+        description: "A test dish".to_string(),
+        allergens: Vec::new(),
+        tags: Vec::new(),
+        is_available,
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
+    }
+}
+
+fn monday_at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 11, 3, hour, 0, 0).unwrap()
+}
+
+/// Wait for the background tasks to catch up with the clock. Once a schedule has
+/// ended the scheduler has no deadline left to wait for, so this polls the storage.
+async fn eventually(mut reached: impl FnMut() -> bool) {
+    for _ in 0..500 {
+        if reached() {
+            return;
+        }
+        tokio::time::sleep(StdDuration::from_millis(10)).await;
+    }
+    panic!("the background tasks did not catch up with the clock");
+}
+
+#[tokio::test]
+async fn test_daily_schedule_runs_to_its_series_end() {
+    let storage = storage();
+    let clock = Arc::new(ManualClock::new(monday_at(11)));
+    let data_dir = std::env::temp_dir().join(format!("platter-lifecycle-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&data_dir).unwrap();
+
+    let porridge = item("Porridge", true);
+    let fish_pie = item("Fish Pie", false);
+    let preset = MenuPreset {
+        id: Uuid::new_v4(),
+        name: "Lunch".to_string(),
+        description: "Test preset".to_string(),
+        menu_item_ids: vec![fish_pie.id],
+        scope: PresetScope::default(),
+        created_at: monday_at(9),
+        updated_at: monday_at(9),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
+    };
+    let schedule = MenuSchedule {
+        id: Uuid::new_v4(),
+        preset_id: preset.id,
+        fallback_preset_id: None,
+        name: "Lunch Service".to_string(),
+        description: "Monday and Tuesday lunches".to_string(),
+        start_time: monday_at(12),
+        end_time: monday_at(14),
+        series_start: monday_at(12),
+        series_end: Some(monday_at(14) + Duration::days(1)),
+        recurrence: ScheduleRecurrence::Daily,
+        rrule: None,
+        exception_dates: Vec::new(),
+        priority: 0,
+        status: ScheduleStatus::Pending,
+        error_message: None,
+        conflict_decision: None,
+        retry_count: 0,
+        next_retry_at: None,
+        availability_snapshot: None,
+        created_at: monday_at(9),
+        updated_at: monday_at(9),
+        version: 1,
+        deleted_at: None,
+        deleted_by: None,
+    };
+    storage.add_menu_item(porridge.clone()).unwrap();
+    storage.add_menu_item(fish_pie.clone()).unwrap();
+    storage.add_menu_preset(preset).unwrap();
+    storage.add_menu_schedule(schedule.clone()).unwrap();
+
+    start_scheduler(
+        storage.clone(),
+        Data::from(clock.clone() as Arc<dyn Clock>),
+        Data::from(Arc::new(clock.handle()) as Arc<dyn Clock>),
+        Data::new(LeaderLock::new(&data_dir, 30)),
+    )
+    .await;
+
+    let menu = || {
+        [porridge.id, fish_pie.id].map(|id| {
+            storage
+                .get_menu_item_by_id(id)
+                .unwrap()
+                .unwrap()
+                .is_available
+        })
+    };
+    let status = || {
+        let current = storage
+            .get_menu_schedule_by_id(schedule.id)
+            .unwrap()
+            .unwrap();
+        (current.status, current.start_time)
+    };
+
+    // Nothing happens before the first lunch
+    clock.set(monday_at(11) + Duration::minutes(59));
+    tokio::time::sleep(StdDuration::from_millis(50)).await;
+    assert_eq!(menu(), [true, false]);
+    assert_eq!(status(), (ScheduleStatus::Pending, monday_at(12)));
+
+    for day in 0..2 {
+        let lunch = monday_at(12) + Duration::days(day);
+        clock.set(lunch);
+        eventually(|| menu() == [false, true]).await;
+        eventually(|| status() == (ScheduleStatus::Active, lunch)).await;
+
+        clock.set(lunch + Duration::hours(2));
+        eventually(|| menu() == [true, false]).await;
+    }
+    eventually(|| status().0 == ScheduleStatus::Ended).await;
+
+    let runs = storage.get_schedule_runs(schedule.id).unwrap();
+    assert_eq!(runs.len(), 2);
+    assert!(runs.iter().all(|run| run.outcome == RunOutcome::Applied));
+    assert_eq!(runs[1].occurrence_start, monday_at(12) + Duration::days(1));
+
+    // The scheduler's changes are recorded at the times it read from the clock
+    let revisions = storage
+        .get_revisions(DataKind::MenuItems, fish_pie.id)
+        .unwrap();
+    let changed_at: Vec<_> = revisions
+        .iter()
+        .map(|revision| revision.changed_at)
+        .collect();
+    assert!(changed_at.contains(&monday_at(12)));
+    assert!(changed_at.contains(&(monday_at(14) + Duration::days(1))));
+
+    std::fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_trash_is_purged_once_the_clock_passes_the_retention_period() {
+    let storage = storage();
+    let clock = Arc::new(ManualClock::new(monday_at(9)));
+    let retention_days = storage
+        .get_app_settings()
+        .unwrap()
+        .storage
+        .trash_retention_days;
+
+    let porridge = item("Porridge", true);
+    storage.add_menu_item(porridge.clone()).unwrap();
+    storage
+        .delete_menu_item(porridge.id, 1, Change::new("alice", clock.now()))
+        .unwrap();
+    let trashed = storage.get_trash().unwrap().menu_items;
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].deleted_at, Some(monday_at(9)));
+
+    start_trash_purge_task(storage.clone(), Data::from(clock.clone() as Arc<dyn Clock>)).await;
+
+    // Kept through the retention period
+    clock.set(monday_at(8) + Duration::days(i64::from(retention_days)));
+    tokio::time::sleep(StdDuration::from_millis(50)).await;
+    assert_eq!(storage.get_trash().unwrap().menu_items.len(), 1);
+
+    clock.set(monday_at(10) + Duration::days(i64::from(retention_days)));
+    eventually(|| storage.get_trash().unwrap().menu_items.is_empty()).await;
+}